@@ -0,0 +1,21 @@
+ALTER TABLE users ADD COLUMN public_key TEXT;
+
+CREATE TABLE workspace_members (
+    workspace_id INTEGER NOT NULL,
+    user_id TEXT NOT NULL,
+    role TEXT NOT NULL,
+    wrapped_key TEXT,
+    accepted BOOLEAN NOT NULL DEFAULT 0,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (workspace_id, user_id),
+    FOREIGN KEY (workspace_id) REFERENCES workspaces(id) ON DELETE CASCADE,
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+
+-- Existing workspaces are owned by their creator and stay encrypted under the
+-- creator's master key until they are re-keyed for sharing.
+INSERT INTO workspace_members (workspace_id, user_id, role, accepted)
+SELECT id, user_id, 'owner', 1 FROM workspaces;
+
+CREATE INDEX idx_workspace_members_user_id ON workspace_members(user_id, accepted);
+CREATE INDEX idx_tasks_workspace_archived ON tasks(workspace_id, archived);
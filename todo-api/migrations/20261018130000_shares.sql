@@ -0,0 +1,12 @@
+CREATE TABLE shares (
+    id TEXT PRIMARY KEY,
+    user_id TEXT NOT NULL,
+    workspace_id INTEGER NOT NULL,
+    snapshot TEXT NOT NULL,
+    expires_at TIMESTAMP,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE,
+    FOREIGN KEY (workspace_id) REFERENCES workspaces(id) ON DELETE CASCADE
+);
+
+CREATE INDEX idx_shares_expires_at ON shares(expires_at);
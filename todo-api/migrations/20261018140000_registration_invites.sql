@@ -0,0 +1,8 @@
+CREATE TABLE registration_invites (
+    code TEXT PRIMARY KEY,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    expires_at TIMESTAMP,
+    used_by TEXT,
+    used_at TIMESTAMP,
+    FOREIGN KEY (used_by) REFERENCES users(id) ON DELETE SET NULL
+);
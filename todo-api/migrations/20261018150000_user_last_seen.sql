@@ -0,0 +1,5 @@
+ALTER TABLE users ADD COLUMN last_seen_at TIMESTAMP;
+
+UPDATE users SET last_seen_at = CURRENT_TIMESTAMP;
+
+CREATE INDEX idx_users_last_seen_at ON users(last_seen_at);
@@ -0,0 +1,399 @@
+//! `todo-api admin ...`: maintenance commands run against the database
+//! directly, on the server host.
+
+use std::{
+    io::{self, Write},
+    path::PathBuf,
+};
+
+use chrono::{Duration, Utc};
+use clap::Subcommand;
+use sqlx::migrate::Migrator;
+
+use crate::{
+    backup::{self, BackupConfig},
+    db::Database,
+};
+
+/// Alphabet for invite codes, without look-alikes such as 0/O and 1/I.
+const INVITE_ALPHABET: &[u8; 32] = b"ABCDEFGHJKLMNPQRSTUVWXYZ23456789";
+const INVITE_GROUPS: usize = 4;
+const INVITE_GROUP_LEN: usize = 5;
+
+#[derive(Subcommand)]
+pub enum AdminCommand {
+    /// Show user, workspace and task counts and the database size
+    Stats,
+    /// Rebuild the database file to reclaim free pages
+    Vacuum,
+    /// Run SQLite's integrity and foreign key checks
+    Check,
+    /// Apply pending migrations without starting the server
+    Migrate,
+    /// Delete users who have not made a request in a number of days
+    PurgeInactive {
+        /// Days without activity before a user is purged
+        #[arg(long)]
+        days: u32,
+        /// Only list the users that would be purged
+        #[arg(long)]
+        dry_run: bool,
+        /// Do not ask for confirmation
+        #[arg(long)]
+        yes: bool,
+    },
+    /// Delete a user and the workspaces they own
+    DeleteUser {
+        user_id: String,
+        /// Do not ask for confirmation
+        #[arg(long)]
+        yes: bool,
+    },
+    /// Take a snapshot of the database now
+    Backup {
+        /// Directory for the snapshot; defaults to TODO_BACKUP_DIR
+        #[arg(long)]
+        dir: Option<PathBuf>,
+    },
+    /// List snapshots in the backup directory
+    Backups {
+        /// Directory to look in; defaults to TODO_BACKUP_DIR
+        #[arg(long)]
+        dir: Option<PathBuf>,
+    },
+    /// Replace the database with a snapshot; stop the server first
+    Restore {
+        snapshot: PathBuf,
+        /// Do not ask for confirmation
+        #[arg(long)]
+        yes: bool,
+    },
+    /// Manage registration invite codes
+    #[command(subcommand)]
+    Invites(InviteCommand),
+}
+
+#[derive(Subcommand)]
+pub enum InviteCommand {
+    /// Create single-use invite codes and print them
+    Mint {
+        /// How many codes to create
+        #[arg(long, default_value_t = 1)]
+        count: u32,
+        /// Days until unused codes expire; never by default
+        #[arg(long)]
+        expires_in_days: Option<u32>,
+    },
+    /// List invite codes and whether they were used
+    List {
+        /// Include used and expired codes
+        #[arg(long)]
+        all: bool,
+    },
+}
+
+pub async fn run(
+    db: &Database,
+    migrator: &'static Migrator,
+    command: AdminCommand,
+) -> anyhow::Result<()> {
+    match command {
+        AdminCommand::Migrate => migrate(db, migrator).await,
+        AdminCommand::Restore { snapshot, yes } => restore(db, migrator, &snapshot, yes).await,
+        AdminCommand::Stats => {
+            ensure_current_schema(db, migrator).await?;
+            stats(db).await
+        }
+        AdminCommand::Vacuum => {
+            ensure_current_schema(db, migrator).await?;
+            vacuum(db).await
+        }
+        AdminCommand::Check => {
+            ensure_current_schema(db, migrator).await?;
+            check(db).await
+        }
+        AdminCommand::PurgeInactive { days, dry_run, yes } => {
+            ensure_current_schema(db, migrator).await?;
+            purge_inactive(db, days, dry_run, yes).await
+        }
+        AdminCommand::DeleteUser { user_id, yes } => {
+            ensure_current_schema(db, migrator).await?;
+            delete_user(db, &user_id, yes).await
+        }
+        AdminCommand::Backup { dir } => take_backup(db, dir).await,
+        AdminCommand::Backups { dir } => list_backups(dir),
+        AdminCommand::Invites(command) => {
+            ensure_current_schema(db, migrator).await?;
+            match command {
+                InviteCommand::Mint {
+                    count,
+                    expires_in_days,
+                } => mint_invites(db, count, expires_in_days).await,
+                InviteCommand::List { all } => list_invites(db, all).await,
+            }
+        }
+    }
+}
+
+/// Commands that read or change the data expect the schema this binary was
+/// built for, and should not migrate it as a side effect.
+async fn ensure_current_schema(db: &Database, migrator: &'static Migrator) -> anyhow::Result<()> {
+    if !db.pending_migrations(migrator).await?.is_empty() {
+        anyhow::bail!("the database has pending migrations; run `todo-api admin migrate` first");
+    }
+    Ok(())
+}
+
+async fn migrate(db: &Database, migrator: &'static Migrator) -> anyhow::Result<()> {
+    let pending = db.pending_migrations(migrator).await?;
+    if pending.is_empty() {
+        println!("The database is up to date.");
+        return Ok(());
+    }
+
+    db.run_migrations(migrator).await?;
+    for (version, description) in pending {
+        println!("Applied {version} {description}");
+    }
+
+    Ok(())
+}
+
+async fn stats(db: &Database) -> anyhow::Result<()> {
+    let stats = db.instance_stats().await?;
+    println!("users           {}", stats.users);
+    println!("workspaces      {}", stats.workspaces);
+    println!(
+        "tasks           {} ({} archived)",
+        stats.tasks, stats.archived_tasks
+    );
+    println!("shares          {}", stats.shares);
+    println!("unused invites  {}", stats.unused_invites);
+    println!(
+        "database size   {} ({} free)",
+        format_bytes(stats.size_bytes),
+        format_bytes(stats.free_bytes)
+    );
+
+    Ok(())
+}
+
+async fn vacuum(db: &Database) -> anyhow::Result<()> {
+    let before = db.instance_stats().await?.size_bytes;
+    db.vacuum().await?;
+    let after = db.instance_stats().await?.size_bytes;
+    println!(
+        "Vacuumed: {} -> {}",
+        format_bytes(before),
+        format_bytes(after)
+    );
+
+    Ok(())
+}
+
+async fn check(db: &Database) -> anyhow::Result<()> {
+    let problems = db.integrity_problems().await?;
+    if problems.is_empty() {
+        println!("ok");
+        return Ok(());
+    }
+
+    for problem in &problems {
+        println!("{problem}");
+    }
+    anyhow::bail!("integrity check found {} problem(s)", problems.len())
+}
+
+async fn purge_inactive(db: &Database, days: u32, dry_run: bool, yes: bool) -> anyhow::Result<()> {
+    let users = db
+        .inactive_users(Utc::now() - Duration::days(days.into()))
+        .await?;
+    if users.is_empty() {
+        println!("No users inactive for {days} days.");
+        return Ok(());
+    }
+
+    for user_id in &users {
+        println!("{user_id}");
+    }
+    if dry_run {
+        println!("{} user(s) would be purged.", users.len());
+        return Ok(());
+    }
+    if !yes
+        && !confirm(&format!(
+            "Delete these {} user(s) and their workspaces?",
+            users.len()
+        ))?
+    {
+        return Ok(());
+    }
+
+    for user_id in &users {
+        db.delete_user(user_id).await?;
+    }
+    println!("Purged {} user(s).", users.len());
+
+    Ok(())
+}
+
+async fn delete_user(db: &Database, user_id: &str, yes: bool) -> anyhow::Result<()> {
+    if !db.user_exists(user_id).await? {
+        anyhow::bail!("no user {user_id}");
+    }
+
+    let usage = db.user_usage(user_id).await?;
+    println!(
+        "{user_id} owns {} workspace(s) and created {} task(s).",
+        usage.workspaces, usage.tasks
+    );
+    if !yes && !confirm("Delete this user and the workspaces they own?")? {
+        return Ok(());
+    }
+
+    db.delete_user(user_id).await?;
+    println!("Deleted {user_id}.");
+
+    Ok(())
+}
+
+async fn take_backup(db: &Database, dir: Option<PathBuf>) -> anyhow::Result<()> {
+    let snapshot = backup::snapshot(db, &backup_dir(dir)?).await?;
+    println!("{}", snapshot.path.display());
+
+    Ok(())
+}
+
+fn list_backups(dir: Option<PathBuf>) -> anyhow::Result<()> {
+    for snapshot in backup::list_snapshots(&backup_dir(dir)?)? {
+        let size = snapshot.path.metadata()?.len();
+        println!(
+            "{}  {}  {}",
+            snapshot.taken_at.format("%Y-%m-%d %H:%M:%S"),
+            format_bytes(size),
+            snapshot.path.display()
+        );
+    }
+
+    Ok(())
+}
+
+async fn restore(
+    db: &Database,
+    migrator: &'static Migrator,
+    snapshot: &std::path::Path,
+    yes: bool,
+) -> anyhow::Result<()> {
+    let target = db
+        .path()
+        .ok_or_else(|| anyhow::anyhow!("DATABASE_URL does not name a database file"))?
+        .to_path_buf();
+    if !snapshot.is_file() {
+        anyhow::bail!("no snapshot at {}", snapshot.display());
+    }
+    if !yes
+        && !confirm(&format!(
+            "Replace {} with {}? The server must be stopped.",
+            target.display(),
+            snapshot.display()
+        ))?
+    {
+        return Ok(());
+    }
+
+    db.close().await;
+    let previous = backup::restore(snapshot, &target, migrator).await?;
+    println!("Restored {}.", snapshot.display());
+    if let Some(previous) = previous {
+        println!("The replaced database was kept at {}.", previous.display());
+    }
+
+    Ok(())
+}
+
+fn backup_dir(dir: Option<PathBuf>) -> anyhow::Result<PathBuf> {
+    match dir {
+        Some(dir) => Ok(dir),
+        None => BackupConfig::from_env()?
+            .map(|config| config.dir)
+            .ok_or_else(|| anyhow::anyhow!("pass --dir or set TODO_BACKUP_DIR")),
+    }
+}
+
+fn confirm(question: &str) -> anyhow::Result<bool> {
+    print!("{question} [y/N] ");
+    io::stdout().flush()?;
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer)?;
+
+    Ok(matches!(answer.trim(), "y" | "Y" | "yes"))
+}
+
+fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KiB", "MiB", "GiB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+
+    if unit == 0 {
+        format!("{bytes} B")
+    } else {
+        format!("{value:.1} {}", UNITS[unit])
+    }
+}
+
+async fn mint_invites(
+    db: &Database,
+    count: u32,
+    expires_in_days: Option<u32>,
+) -> anyhow::Result<()> {
+    let expires_at = expires_in_days.map(|days| Utc::now() + Duration::days(days.into()));
+    for _ in 0..count {
+        let code = invite_code();
+        db.create_registration_invite(&code, expires_at).await?;
+        println!("{code}");
+    }
+
+    Ok(())
+}
+
+async fn list_invites(db: &Database, all: bool) -> anyhow::Result<()> {
+    let now = Utc::now();
+    for invite in db.get_registration_invites().await? {
+        let status = match (&invite.used_by, invite.expires_at) {
+            (Some(user_id), _) => format!("used by {}", &user_id[..user_id.len().min(12)]),
+            (None, Some(expires_at)) if expires_at <= now => "expired".to_string(),
+            (None, Some(expires_at)) => {
+                format!("unused, expires {}", expires_at.format("%Y-%m-%d"))
+            }
+            (None, None) => "unused".to_string(),
+        };
+        if !all && !status.starts_with("unused") {
+            continue;
+        }
+        println!(
+            "{}  created {}  {status}",
+            invite.code,
+            invite.created_at.format("%Y-%m-%d")
+        );
+    }
+
+    Ok(())
+}
+
+fn invite_code() -> String {
+    let bytes = rand::random::<[u8; INVITE_GROUPS * INVITE_GROUP_LEN]>();
+    bytes
+        .chunks(INVITE_GROUP_LEN)
+        .map(|group| {
+            group
+                .iter()
+                .map(|byte| char::from(INVITE_ALPHABET[usize::from(byte % 32)]))
+                .collect::<String>()
+        })
+        .collect::<Vec<_>>()
+        .join("-")
+}
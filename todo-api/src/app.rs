@@ -1,50 +1,121 @@
+use std::sync::Arc;
+
 use axum::{
     Router,
-    routing::{get, patch, post},
+    extract::{DefaultBodyLimit, Request, State},
+    middleware::{self, Next},
+    response::Response,
+    routing::{get, patch, post, put},
 };
+use todo_client::protocol::API_VERSION;
 use tower::ServiceBuilder;
 use tower_http::trace::{DefaultMakeSpan, DefaultOnRequest, DefaultOnResponse, TraceLayer};
 use tracing::Level;
 
 use crate::{
     AppState,
+    db::Database,
     handlers::{
-        archive_completed_tasks, create_task, create_workspace, delete_task, delete_workspace,
-        health, list_tasks, list_workspaces, toggle_task, update_task, update_workspace,
+        accept_invite, archive_completed_tasks, create_share, create_task, create_workspace,
+        delete_share, delete_task, delete_workspace, get_public_key, get_share, health, info,
+        invite_member, list_invites, list_members, list_tasks, list_workspaces, register,
+        register_public_key, rekey_workspace, remove_member, stream_events, toggle_task,
+        unarchive_tasks, update_member, update_task, update_workspace,
     },
+    limits::rate_limit,
+    validation::{DEFAULT_BODY_LIMIT, REKEY_BODY_LIMIT, SHARE_BODY_LIMIT},
 };
 
-pub fn router(db: AppState) -> Router {
+pub fn router(state: AppState) -> Router {
+    let api = api_routes()
+        .layer(middleware::from_fn_with_state(
+            Arc::clone(&state.db),
+            track_activity,
+        ))
+        .layer(middleware::from_fn_with_state(
+            state.limiter.clone(),
+            rate_limit,
+        ));
+
     Router::new()
         .route("/health", get(health))
+        .nest(&format!("/api/v{API_VERSION}"), api.clone())
+        // Unversioned aliases for clients from before versioning.
+        .nest("/api", api)
+        .layer(DefaultBodyLimit::max(DEFAULT_BODY_LIMIT))
+        .layer(
+            ServiceBuilder::new().layer(
+                TraceLayer::new_for_http()
+                    .make_span_with(DefaultMakeSpan::new().level(Level::INFO))
+                    .on_request(DefaultOnRequest::new().level(Level::INFO))
+                    .on_response(DefaultOnResponse::new().level(Level::INFO)),
+            ),
+        )
+        .with_state(state)
+}
+
+fn api_routes() -> Router<AppState> {
+    Router::new()
+        .route("/info", get(info))
+        .route("/events", get(stream_events))
+        .route("/register", post(register))
+        .route("/me/public-key", put(register_public_key))
+        .route("/users/{user_id}/public-key", get(get_public_key))
+        .route("/invites", get(list_invites))
         .route(
-            "/api/workspaces",
-            get(list_workspaces).post(create_workspace),
+            "/shares",
+            post(create_share).layer(DefaultBodyLimit::max(SHARE_BODY_LIMIT)),
         )
+        .route("/shares/{share_id}", get(get_share).delete(delete_share))
+        .route("/workspaces", get(list_workspaces).post(create_workspace))
         .route(
-            "/api/workspaces/{workspace_id}",
+            "/workspaces/{workspace_id}",
             patch(update_workspace).delete(delete_workspace),
         )
         .route(
-            "/api/workspaces/{workspace_id}/tasks",
+            "/workspaces/{workspace_id}/key",
+            put(rekey_workspace).layer(DefaultBodyLimit::max(REKEY_BODY_LIMIT)),
+        )
+        .route(
+            "/workspaces/{workspace_id}/members",
+            get(list_members).post(invite_member),
+        )
+        .route(
+            "/workspaces/{workspace_id}/members/{user_id}",
+            patch(update_member).delete(remove_member),
+        )
+        .route("/workspaces/{workspace_id}/accept", post(accept_invite))
+        .route(
+            "/workspaces/{workspace_id}/tasks",
             get(list_tasks).post(create_task),
         )
         .route(
-            "/api/workspaces/{workspace_id}/archive-completed",
+            "/workspaces/{workspace_id}/archive-completed",
             post(archive_completed_tasks),
         )
         .route(
-            "/api/tasks/{task_id}",
-            patch(update_task).delete(delete_task),
+            "/workspaces/{workspace_id}/unarchive",
+            post(unarchive_tasks),
         )
-        .route("/api/tasks/{task_id}/toggle", post(toggle_task))
-        .layer(
-            ServiceBuilder::new().layer(
-                TraceLayer::new_for_http()
-                    .make_span_with(DefaultMakeSpan::new().level(Level::INFO))
-                    .on_request(DefaultOnRequest::new().level(Level::INFO))
-                    .on_response(DefaultOnResponse::new().level(Level::INFO)),
-            ),
-        )
-        .with_state(db)
+        .route("/tasks/{task_id}", patch(update_task).delete(delete_task))
+        .route("/tasks/{task_id}/toggle", post(toggle_task))
+}
+
+/// Keeps `last_seen_at` current so inactive users can be purged.
+async fn track_activity(State(db): State<Arc<Database>>, request: Request, next: Next) -> Response {
+    let user_id = request
+        .headers()
+        .get("x-user-id")
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string);
+    let response = next.run(request).await;
+
+    if let Some(user_id) = user_id
+        && response.status().is_success()
+        && let Err(err) = db.touch_user(&user_id).await
+    {
+        tracing::warn!(%err, "failed to record user activity");
+    }
+
+    response
 }
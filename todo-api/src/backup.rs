@@ -0,0 +1,291 @@
+//! Online snapshots of the SQLite database with `VACUUM INTO`, pruned by
+//! retention rules, and restoring a snapshot over the live database file.
+
+use std::{
+    collections::HashSet,
+    env, fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+
+use chrono::{DateTime, NaiveDateTime, Utc};
+use sqlx::migrate::Migrator;
+use tokio::task::JoinHandle;
+
+use crate::db::Database;
+
+const SNAPSHOT_PREFIX: &str = "todo-";
+const SNAPSHOT_SUFFIX: &str = ".db";
+const SNAPSHOT_TIME_FORMAT: &str = "%Y%m%dT%H%M%S%.3fZ";
+
+/// Where and how often snapshots are taken, and which ones are kept.
+#[derive(Debug, Clone)]
+pub struct BackupConfig {
+    pub dir: PathBuf,
+    pub interval: Duration,
+    /// The most recent snapshots to keep.
+    pub keep_last: usize,
+    /// Days for which the newest snapshot of the day is kept as well.
+    pub keep_daily: usize,
+}
+
+impl BackupConfig {
+    /// Reads `TODO_BACKUP_DIR`, `TODO_BACKUP_INTERVAL_MINUTES` (60),
+    /// `TODO_BACKUP_KEEP_LAST` (24) and `TODO_BACKUP_KEEP_DAILY` (14).
+    /// Backups are off unless a directory is set.
+    pub fn from_env() -> anyhow::Result<Option<Self>> {
+        let Ok(dir) = env::var("TODO_BACKUP_DIR") else {
+            return Ok(None);
+        };
+
+        let minutes = env_number("TODO_BACKUP_INTERVAL_MINUTES", 60)?;
+        if minutes == 0 {
+            anyhow::bail!("TODO_BACKUP_INTERVAL_MINUTES must be at least 1");
+        }
+
+        Ok(Some(Self {
+            dir: PathBuf::from(dir),
+            interval: Duration::from_secs(minutes * 60),
+            keep_last: env_number("TODO_BACKUP_KEEP_LAST", 24)? as usize,
+            keep_daily: env_number("TODO_BACKUP_KEEP_DAILY", 14)? as usize,
+        }))
+    }
+}
+
+fn env_number(name: &str, default: u64) -> anyhow::Result<u64> {
+    match env::var(name) {
+        Ok(value) => value
+            .parse()
+            .map_err(|err| anyhow::anyhow!("invalid {name}: {err}")),
+        Err(_) => Ok(default),
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Snapshot {
+    pub path: PathBuf,
+    pub taken_at: DateTime<Utc>,
+}
+
+/// Takes a snapshot into `dir`. The file only appears under its final name
+/// once it is complete.
+pub async fn snapshot(db: &Database, dir: &Path) -> anyhow::Result<Snapshot> {
+    fs::create_dir_all(dir)?;
+    let taken_at = Utc::now();
+    let name = format!(
+        "{SNAPSHOT_PREFIX}{}{SNAPSHOT_SUFFIX}",
+        taken_at.format(SNAPSHOT_TIME_FORMAT)
+    );
+    let partial = dir.join(format!(".{name}.partial"));
+    let path = dir.join(name);
+
+    let _ = fs::remove_file(&partial);
+    db.snapshot_into(&partial).await?;
+    fs::rename(&partial, &path)?;
+
+    Ok(Snapshot { path, taken_at })
+}
+
+/// Snapshots in `dir`, oldest first.
+pub fn list_snapshots(dir: &Path) -> anyhow::Result<Vec<Snapshot>> {
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut snapshots = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let Some(taken_at) = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(|name| name.strip_prefix(SNAPSHOT_PREFIX))
+            .and_then(|name| name.strip_suffix(SNAPSHOT_SUFFIX))
+            .and_then(|time| NaiveDateTime::parse_from_str(time, SNAPSHOT_TIME_FORMAT).ok())
+        else {
+            continue;
+        };
+        snapshots.push(Snapshot {
+            path,
+            taken_at: taken_at.and_utc(),
+        });
+    }
+    snapshots.sort_by_key(|snapshot| snapshot.taken_at);
+
+    Ok(snapshots)
+}
+
+/// Deletes the snapshots that neither of the retention rules keeps, and
+/// returns them.
+pub fn prune(dir: &Path, keep_last: usize, keep_daily: usize) -> anyhow::Result<Vec<PathBuf>> {
+    let snapshots = list_snapshots(dir)?;
+    let mut keep = HashSet::new();
+    for snapshot in snapshots.iter().rev().take(keep_last) {
+        keep.insert(snapshot.path.clone());
+    }
+
+    let mut days = HashSet::new();
+    for snapshot in snapshots.iter().rev() {
+        if days.len() == keep_daily && !days.contains(&snapshot.taken_at.date_naive()) {
+            break;
+        }
+        if days.insert(snapshot.taken_at.date_naive()) {
+            keep.insert(snapshot.path.clone());
+        }
+    }
+
+    let mut removed = Vec::new();
+    for snapshot in snapshots {
+        if !keep.contains(&snapshot.path) {
+            fs::remove_file(&snapshot.path)?;
+            removed.push(snapshot.path);
+        }
+    }
+
+    Ok(removed)
+}
+
+/// Takes a snapshot every `config.interval` and prunes old ones, logging
+/// failures instead of stopping.
+pub fn spawn(db: Arc<Database>, config: BackupConfig) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(config.interval);
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            interval.tick().await;
+            match snapshot(&db, &config.dir).await {
+                Ok(snapshot) => {
+                    tracing::info!(path = %snapshot.path.display(), "database snapshot taken")
+                }
+                Err(err) => {
+                    tracing::error!(%err, "database snapshot failed");
+                    continue;
+                }
+            }
+            if let Err(err) = prune(&config.dir, config.keep_last, config.keep_daily) {
+                tracing::error!(%err, "pruning database snapshots failed");
+            }
+        }
+    })
+}
+
+/// Checks that `db` is intact and that every migration it records is one of
+/// ours, so the server can run on it once pending migrations are applied.
+pub async fn validate(db: &Database, migrator: &Migrator) -> anyhow::Result<()> {
+    let problems = db.integrity_problems().await?;
+    if let Some(problem) = problems.first() {
+        anyhow::bail!(
+            "snapshot failed the integrity check ({} problem(s)): {problem}",
+            problems.len()
+        );
+    }
+
+    for applied in db.applied_migrations().await? {
+        let Some(known) = migrator
+            .iter()
+            .find(|migration| migration.version == applied.version)
+        else {
+            anyhow::bail!(
+                "snapshot has migration {} that this server does not know; restore it with a newer todo-api",
+                applied.version
+            );
+        };
+        if !applied.success {
+            anyhow::bail!("snapshot has a failed migration {}", applied.version);
+        }
+        if *known.checksum != *applied.checksum {
+            anyhow::bail!(
+                "snapshot migration {} does not match this server's migration",
+                applied.version
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Replaces the database at `target` with `snapshot`. The snapshot is
+/// validated and migrated on a copy first, and the replaced database is kept
+/// next to it; its path is returned. Nothing may have `target` open while
+/// this runs.
+pub async fn restore(
+    snapshot: &Path,
+    target: &Path,
+    migrator: &'static Migrator,
+) -> anyhow::Result<Option<PathBuf>> {
+    let staged = sibling(target, "restoring");
+    let _ = fs::remove_file(&staged);
+    fs::copy(snapshot, &staged)?;
+
+    let staged_db = Database::open(&sqlite_url(&staged)?).await?;
+    let prepared = async {
+        validate(&staged_db, migrator).await?;
+        staged_db.run_migrations(migrator).await
+    }
+    .await;
+    staged_db.close().await;
+    if let Err(err) = prepared {
+        remove_database_files(&staged);
+        return Err(err);
+    }
+
+    let previous = if target.exists() {
+        // Writes still in the WAL belong to the copy that is kept.
+        let old_db = Database::open(&sqlite_url(target)?).await?;
+        let checkpointed = old_db.checkpoint().await;
+        old_db.close().await;
+        checkpointed?;
+
+        let previous = sibling(
+            target,
+            &format!("pre-restore-{}", Utc::now().format(SNAPSHOT_TIME_FORMAT)),
+        );
+        rename_database_files(target, &previous)?;
+        Some(previous)
+    } else {
+        None
+    };
+    remove_database_files(target);
+    fs::rename(&staged, target)?;
+
+    Ok(previous)
+}
+
+fn sibling(path: &Path, label: &str) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(".{label}"));
+    path.with_file_name(name)
+}
+
+fn sqlite_url(path: &Path) -> anyhow::Result<String> {
+    let path = path
+        .to_str()
+        .ok_or_else(|| anyhow::anyhow!("database path is not valid UTF-8"))?;
+    Ok(format!("sqlite://{path}"))
+}
+
+/// Moves a database file together with its WAL and shared-memory companions.
+fn rename_database_files(from: &Path, to: &Path) -> anyhow::Result<()> {
+    fs::rename(from, to)?;
+    for label in ["-wal", "-shm"] {
+        let mut companion = from.as_os_str().to_os_string();
+        companion.push(label);
+        let companion = PathBuf::from(companion);
+        if companion.exists() {
+            let mut moved = to.as_os_str().to_os_string();
+            moved.push(label);
+            fs::rename(companion, PathBuf::from(moved))?;
+        }
+    }
+    Ok(())
+}
+
+/// Removes a database file's WAL and shared-memory companions, and the file
+/// itself if it still exists.
+fn remove_database_files(path: &Path) {
+    for label in ["", "-wal", "-shm"] {
+        let mut name = path.as_os_str().to_os_string();
+        name.push(label);
+        let _ = fs::remove_file(PathBuf::from(name));
+    }
+}
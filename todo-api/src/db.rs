@@ -1,29 +1,63 @@
-use std::{env, path::Path};
+use std::{
+    env,
+    path::{Path, PathBuf},
+};
 
 use chrono::{DateTime, Utc};
-use sqlx::{FromRow, sqlite::SqlitePool};
-use todo_client::{EncryptedField, EncryptedTask, EncryptedWorkspace, WorkspaceStats};
+use sqlx::{FromRow, QueryBuilder, Sqlite, sqlite::SqlitePool};
+use todo_client::{
+    EncryptedField, EncryptedShare, EncryptedTask, EncryptedWorkspace, EncryptedWorkspaceInvite,
+    WorkspaceMember, WorkspaceRole, WorkspaceStats, WrappedKey,
+};
 
 pub struct Database {
     pool: SqlitePool,
+    path: Option<PathBuf>,
 }
 
 impl Database {
     pub async fn connect() -> anyhow::Result<Self> {
-        let database_url = env::var("DATABASE_URL")?;
-        if let Some(db_path) = sqlite_database_path(&database_url)
-            && let Some(parent) = Path::new(db_path).parent()
+        Self::open(&env::var("DATABASE_URL")?).await
+    }
+
+    pub async fn open(database_url: &str) -> anyhow::Result<Self> {
+        let path = sqlite_database_path(database_url).map(PathBuf::from);
+        if let Some(parent) = path.as_deref().and_then(Path::parent)
             && !parent.as_os_str().is_empty()
         {
             std::fs::create_dir_all(parent)?;
         }
 
-        let pool = SqlitePool::connect(&database_url).await?;
+        let pool = SqlitePool::connect(database_url).await?;
         sqlx::query("PRAGMA journal_mode = WAL")
             .execute(&pool)
             .await?;
 
-        Ok(Database { pool })
+        Ok(Database { pool, path })
+    }
+
+    /// The database file, unless the database lives in memory.
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// Closes every connection, checkpointing the WAL into the main file.
+    pub async fn close(&self) {
+        self.pool.close().await;
+    }
+
+    /// Writes a consistent copy of the live database to `path`, which must not
+    /// exist yet. Readers and writers carry on while the copy is made.
+    pub async fn snapshot_into(&self, path: &Path) -> anyhow::Result<()> {
+        let path = path
+            .to_str()
+            .ok_or_else(|| anyhow::anyhow!("snapshot path is not valid UTF-8"))?;
+        sqlx::query("VACUUM INTO ?")
+            .bind(path)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
     }
 
     pub async fn run_migrations(
@@ -35,6 +69,158 @@ impl Database {
         Ok(())
     }
 
+    /// Migrations recorded in the database, whether or not they succeeded.
+    pub async fn applied_migrations(&self) -> anyhow::Result<Vec<AppliedMigration>> {
+        let tracked = sqlx::query_scalar::<_, bool>(
+            "SELECT EXISTS(SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = '_sqlx_migrations')",
+        )
+        .fetch_one(&self.pool)
+        .await?;
+        if !tracked {
+            return Ok(Vec::new());
+        }
+
+        let applied = sqlx::query_as::<_, AppliedMigration>(
+            "SELECT version, checksum, success FROM _sqlx_migrations ORDER BY version",
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(applied)
+    }
+
+    /// Migrations `migrator` knows about that the database has not applied.
+    pub async fn pending_migrations(
+        &self,
+        migrator: &sqlx::migrate::Migrator,
+    ) -> anyhow::Result<Vec<(i64, String)>> {
+        let applied = self.applied_migrations().await?;
+
+        Ok(migrator
+            .iter()
+            .filter(|migration| {
+                !applied
+                    .iter()
+                    .any(|applied| applied.success && applied.version == migration.version)
+            })
+            .map(|migration| (migration.version, migration.description.to_string()))
+            .collect())
+    }
+
+    pub async fn instance_stats(&self) -> anyhow::Result<InstanceStats> {
+        let stats = sqlx::query_as::<_, InstanceStats>(
+            "SELECT
+                (SELECT COUNT(*) FROM users) AS users,
+                (SELECT COUNT(*) FROM workspaces) AS workspaces,
+                (SELECT COUNT(*) FROM tasks) AS tasks,
+                (SELECT COUNT(*) FROM tasks WHERE archived = 1) AS archived_tasks,
+                (SELECT COUNT(*) FROM shares) AS shares,
+                (SELECT COUNT(*) FROM registration_invites WHERE used_by IS NULL) AS unused_invites,
+                (SELECT page_count * page_size FROM pragma_page_count(), pragma_page_size())
+                    AS size_bytes,
+                (SELECT freelist_count * page_size FROM pragma_freelist_count(), pragma_page_size())
+                    AS free_bytes",
+        )
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(stats)
+    }
+
+    /// Moves everything in the WAL into the main file and empties the WAL.
+    pub async fn checkpoint(&self) -> anyhow::Result<()> {
+        sqlx::query("PRAGMA wal_checkpoint(TRUNCATE)")
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn vacuum(&self) -> anyhow::Result<()> {
+        sqlx::query("VACUUM").execute(&self.pool).await?;
+
+        Ok(())
+    }
+
+    /// Runs SQLite's integrity and foreign key checks; an empty result means
+    /// the database is healthy.
+    pub async fn integrity_problems(&self) -> anyhow::Result<Vec<String>> {
+        let mut problems = sqlx::query_scalar::<_, String>("PRAGMA integrity_check")
+            .fetch_all(&self.pool)
+            .await?;
+        problems.retain(|problem| problem != "ok");
+
+        let orphans = sqlx::query_as::<_, (String, i64, String)>(
+            "SELECT \"table\", rowid, parent FROM pragma_foreign_key_check()",
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        problems.extend(orphans.into_iter().map(|(table, rowid, parent)| {
+            format!("{table} row {rowid} references a missing {parent} row")
+        }));
+
+        Ok(problems)
+    }
+
+    /// Records that `user_id` made a request, at most once a minute.
+    pub async fn touch_user(&self, user_id: &str) -> anyhow::Result<()> {
+        let now = Utc::now();
+        sqlx::query(
+            "UPDATE users SET last_seen_at = ?
+             WHERE id = ?
+               AND (last_seen_at IS NULL OR datetime(last_seen_at) < datetime(?))",
+        )
+        .bind(now)
+        .bind(user_id)
+        .bind(now - chrono::Duration::minutes(1))
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn inactive_users(&self, seen_before: DateTime<Utc>) -> anyhow::Result<Vec<String>> {
+        let users = sqlx::query_scalar::<_, String>(
+            "SELECT id FROM users
+             WHERE datetime(COALESCE(last_seen_at, created_at)) < datetime(?)
+             ORDER BY id",
+        )
+        .bind(seen_before)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(users)
+    }
+
+    /// Deletes a user with the workspaces they own. Tasks they created in
+    /// other users' workspaces are handed to those workspaces' owners.
+    pub async fn delete_user(&self, user_id: &str) -> anyhow::Result<bool> {
+        let mut tx = self.pool.begin().await?;
+        let owned = "SELECT id FROM workspaces WHERE user_id = ?1";
+        for statement in [
+            format!("DELETE FROM tasks WHERE workspace_id IN ({owned})"),
+            format!("DELETE FROM shares WHERE workspace_id IN ({owned}) OR user_id = ?1"),
+            format!("DELETE FROM workspace_members WHERE workspace_id IN ({owned}) OR user_id = ?1"),
+            "DELETE FROM workspaces WHERE user_id = ?1".to_string(),
+            "UPDATE tasks SET user_id = (SELECT w.user_id FROM workspaces w WHERE w.id = tasks.workspace_id)
+             WHERE user_id = ?1"
+                .to_string(),
+            "UPDATE registration_invites SET used_by = NULL WHERE used_by = ?1".to_string(),
+        ] {
+            sqlx::query(&statement)
+                .bind(user_id)
+                .execute(&mut *tx)
+                .await?;
+        }
+        let result = sqlx::query("DELETE FROM users WHERE id = ?")
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
     pub async fn ensure_user(&self, user_id: &str) -> anyhow::Result<()> {
         sqlx::query("INSERT OR IGNORE INTO users (id) VALUES (?)")
             .bind(user_id)
@@ -44,9 +230,121 @@ impl Database {
         Ok(())
     }
 
+    pub async fn user_exists(&self, user_id: &str) -> anyhow::Result<bool> {
+        let exists =
+            sqlx::query_scalar::<_, bool>("SELECT EXISTS(SELECT 1 FROM users WHERE id = ?)")
+                .bind(user_id)
+                .fetch_one(&self.pool)
+                .await?;
+
+        Ok(exists)
+    }
+
+    /// Registers `user_id` if `code` names an unused, unexpired invite, and
+    /// marks the invite used. Returns whether the invite was consumed.
+    pub async fn register_with_invite(&self, user_id: &str, code: &str) -> anyhow::Result<bool> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query("INSERT OR IGNORE INTO users (id) VALUES (?)")
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await?;
+        let result = sqlx::query(
+            "UPDATE registration_invites SET used_by = ?, used_at = CURRENT_TIMESTAMP
+             WHERE code = ? AND used_by IS NULL
+               AND (expires_at IS NULL OR expires_at > ?)",
+        )
+        .bind(user_id)
+        .bind(code)
+        .bind(Utc::now())
+        .execute(&mut *tx)
+        .await?;
+        if result.rows_affected() == 0 {
+            return Ok(false);
+        }
+        tx.commit().await?;
+
+        Ok(true)
+    }
+
+    pub async fn create_registration_invite(
+        &self,
+        code: &str,
+        expires_at: Option<DateTime<Utc>>,
+    ) -> anyhow::Result<()> {
+        sqlx::query("INSERT INTO registration_invites (code, expires_at) VALUES (?, ?)")
+            .bind(code)
+            .bind(expires_at)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn get_registration_invites(&self) -> anyhow::Result<Vec<RegistrationInvite>> {
+        let invites = sqlx::query_as::<_, RegistrationInvite>(
+            "SELECT code, created_at, expires_at, used_by, used_at
+             FROM registration_invites
+             ORDER BY created_at, code",
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(invites)
+    }
+
+    pub async fn set_public_key(&self, user_id: &str, public_key: &str) -> anyhow::Result<()> {
+        sqlx::query("UPDATE users SET public_key = ? WHERE id = ?")
+            .bind(public_key)
+            .bind(user_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn get_public_key(&self, user_id: &str) -> anyhow::Result<Option<String>> {
+        let public_key =
+            sqlx::query_scalar::<_, Option<String>>("SELECT public_key FROM users WHERE id = ?")
+                .bind(user_id)
+                .fetch_optional(&self.pool)
+                .await?;
+
+        Ok(public_key.flatten())
+    }
+
+    pub async fn workspace_role(
+        &self,
+        user_id: &str,
+        workspace_id: i64,
+    ) -> anyhow::Result<Option<WorkspaceRole>> {
+        let role = sqlx::query_scalar::<_, String>(
+            "SELECT role FROM workspace_members WHERE workspace_id = ? AND user_id = ? AND accepted = 1",
+        )
+        .bind(workspace_id)
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        role.as_deref().map(parse_role).transpose()
+    }
+
+    pub async fn task_workspace_id(&self, task_id: i64) -> anyhow::Result<Option<i64>> {
+        let workspace_id =
+            sqlx::query_scalar::<_, i64>("SELECT workspace_id FROM tasks WHERE id = ?")
+                .bind(task_id)
+                .fetch_optional(&self.pool)
+                .await?;
+
+        Ok(workspace_id)
+    }
+
     pub async fn get_workspaces(&self, user_id: &str) -> anyhow::Result<Vec<EncryptedWorkspace>> {
         let rows = sqlx::query_as::<_, WorkspaceRow>(
-            "SELECT id, name, created_at, updated_at FROM workspaces WHERE user_id = ? ORDER BY created_at",
+            "SELECT w.id, w.name, m.role, m.wrapped_key, w.created_at, w.updated_at
+             FROM workspaces w
+             JOIN workspace_members m ON m.workspace_id = w.id
+             WHERE m.user_id = ? AND m.accepted = 1
+             ORDER BY w.created_at",
         )
         .bind(user_id)
         .fetch_all(&self.pool)
@@ -61,8 +359,8 @@ impl Database {
                     COALESCE(SUM(CASE WHEN t.completed = 1 THEN 1 ELSE 0 END), 0) AS completed,
                     COUNT(t.id) AS total
              FROM workspaces w
-             LEFT JOIN tasks t ON t.user_id = w.user_id AND t.workspace_id = w.id AND t.archived = 0
-             WHERE w.user_id = ?
+             JOIN workspace_members m ON m.workspace_id = w.id AND m.user_id = ? AND m.accepted = 1
+             LEFT JOIN tasks t ON t.workspace_id = w.id AND t.archived = 0
              GROUP BY w.id",
         )
         .bind(user_id)
@@ -72,20 +370,117 @@ impl Database {
         Ok(rows.into_iter().map(Into::into).collect())
     }
 
-    pub async fn get_tasks_for_workspace(
+    /// Who created a task and the stored size of each of its encrypted
+    /// fields, measured as in [`Database::user_usage`].
+    pub async fn task_stored_sizes(&self, task_id: i64) -> anyhow::Result<Option<TaskStoredSizes>> {
+        let sizes = sqlx::query_as::<_, TaskStoredSizes>(
+            "SELECT user_id,
+                    LENGTH(title) AS title,
+                    COALESCE(LENGTH(due_date), 0) AS due_date,
+                    COALESCE(LENGTH(recurrence), 0) AS recurrence,
+                    COALESCE(LENGTH(priority), 0) AS priority,
+                    COALESCE(LENGTH(tags), 0) AS tags
+             FROM tasks WHERE id = ?",
+        )
+        .bind(task_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(sizes)
+    }
+
+    /// Who created a workspace and the stored size of its name.
+    pub async fn workspace_stored_size(
         &self,
-        user_id: &str,
         workspace_id: i64,
-    ) -> anyhow::Result<Vec<EncryptedTask>> {
-        let rows = sqlx::query_as::<_, TaskRow>(
-            "SELECT id, title, description, completed, archived, due_date, workspace_id, parent_task_id, created_at, updated_at
-             FROM tasks WHERE user_id = ? AND workspace_id = ? AND archived = 0 ORDER BY created_at",
+    ) -> anyhow::Result<Option<(String, i64)>> {
+        let size = sqlx::query_as::<_, (String, i64)>(
+            "SELECT user_id, LENGTH(name) FROM workspaces WHERE id = ?",
         )
-        .bind(user_id)
         .bind(workspace_id)
-        .fetch_all(&self.pool)
+        .fetch_optional(&self.pool)
         .await?;
 
+        Ok(size)
+    }
+
+    /// What `user_id` stores, as counted against its quotas.
+    pub async fn user_usage(&self, user_id: &str) -> anyhow::Result<UserUsage> {
+        let usage = sqlx::query_as::<_, UserUsage>(
+            "SELECT
+                (SELECT COUNT(*) FROM workspaces WHERE user_id = ?1) AS workspaces,
+                (SELECT COUNT(*) FROM tasks WHERE user_id = ?1) AS tasks,
+                (SELECT COALESCE(SUM(LENGTH(name)), 0) FROM workspaces WHERE user_id = ?1)
+                + (SELECT COALESCE(SUM(LENGTH(title) + COALESCE(LENGTH(description), 0)
+                                       + COALESCE(LENGTH(due_date), 0)
+                                       + COALESCE(LENGTH(recurrence), 0)
+                                       + COALESCE(LENGTH(priority), 0)
+                                       + COALESCE(LENGTH(tags), 0)), 0)
+                   FROM tasks WHERE user_id = ?1)
+                + (SELECT COALESCE(SUM(LENGTH(snapshot)), 0) FROM shares WHERE user_id = ?1)
+                AS bytes",
+        )
+        .bind(user_id)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(usage)
+    }
+
+    /// Lists tasks in id order, which is the pagination cursor.
+    pub async fn get_tasks_for_workspace(
+        &self,
+        workspace_id: i64,
+        filter: &TaskFilter,
+    ) -> anyhow::Result<Vec<EncryptedTask>> {
+        let mut query = QueryBuilder::<Sqlite>::new(
+            "SELECT id, title, description, completed, archived, due_date, recurrence, priority, tags, workspace_id, parent_task_id, created_at, updated_at
+             FROM tasks WHERE workspace_id = ",
+        );
+        query.push_bind(workspace_id);
+        match filter.archived {
+            Some(archived) => {
+                query.push(" AND archived = ").push_bind(archived);
+            }
+            None if !filter.include_archived => {
+                query.push(" AND archived = 0");
+            }
+            None => {}
+        }
+        if let Some(completed) = filter.completed {
+            query.push(" AND completed = ").push_bind(completed);
+        }
+        if let Some(parent_task_id) = filter.parent_task_id {
+            query
+                .push(" AND parent_task_id = ")
+                .push_bind(parent_task_id);
+        }
+        for (column, op, bound) in [
+            ("created_at", ">=", filter.created_after),
+            ("created_at", "<", filter.created_before),
+            ("updated_at", ">=", filter.updated_after),
+            ("updated_at", "<", filter.updated_before),
+        ] {
+            if let Some(bound) = bound {
+                query
+                    .push(format_args!(" AND datetime({column}) {op} datetime("))
+                    .push_bind(bound.to_rfc3339())
+                    .push(")");
+            }
+        }
+        if let Some(after) = filter.after {
+            query.push(" AND id > ").push_bind(after);
+        }
+        query.push(" ORDER BY id");
+        if let Some(limit) = filter.limit {
+            query.push(" LIMIT ").push_bind(i64::from(limit));
+        }
+
+        let rows = query
+            .build_query_as::<TaskRow>()
+            .fetch_all(&self.pool)
+            .await?;
+
         rows.into_iter().map(TaskRow::try_into).collect()
     }
 
@@ -93,14 +488,209 @@ impl Database {
         &self,
         user_id: &str,
         name: &EncryptedField,
+        wrapped_key: Option<&WrappedKey>,
     ) -> anyhow::Result<i64> {
+        let mut tx = self.pool.begin().await?;
         let result = sqlx::query("INSERT INTO workspaces (user_id, name) VALUES (?, ?)")
             .bind(user_id)
             .bind(encrypted_field_to_string(name)?)
-            .execute(&self.pool)
+            .execute(&mut *tx)
             .await?;
+        let workspace_id = result.last_insert_rowid();
 
-        Ok(result.last_insert_rowid())
+        sqlx::query(
+            "INSERT INTO workspace_members (workspace_id, user_id, role, wrapped_key, accepted)
+             VALUES (?, ?, ?, ?, 1)",
+        )
+        .bind(workspace_id)
+        .bind(user_id)
+        .bind(WorkspaceRole::Owner.as_str())
+        .bind(wrapped_key.map(wrapped_key_to_string).transpose()?)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(workspace_id)
+    }
+
+    pub async fn get_members(&self, workspace_id: i64) -> anyhow::Result<Vec<WorkspaceMember>> {
+        let rows = sqlx::query_as::<_, MemberRow>(
+            "SELECT user_id, role, accepted FROM workspace_members WHERE workspace_id = ? ORDER BY created_at",
+        )
+        .bind(workspace_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.into_iter().map(MemberRow::try_into).collect()
+    }
+
+    /// Everyone who should hear about changes to a workspace, including
+    /// members who have not accepted their invite yet.
+    pub async fn workspace_member_ids(&self, workspace_id: i64) -> anyhow::Result<Vec<String>> {
+        let user_ids = sqlx::query_scalar::<_, String>(
+            "SELECT user_id FROM workspace_members WHERE workspace_id = ?",
+        )
+        .bind(workspace_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(user_ids)
+    }
+
+    pub async fn get_invites(
+        &self,
+        user_id: &str,
+    ) -> anyhow::Result<Vec<EncryptedWorkspaceInvite>> {
+        let rows = sqlx::query_as::<_, InviteRow>(
+            "SELECT w.id AS workspace_id, w.name, m.role, m.wrapped_key
+             FROM workspace_members m
+             JOIN workspaces w ON w.id = m.workspace_id
+             WHERE m.user_id = ? AND m.accepted = 0 AND m.wrapped_key IS NOT NULL
+             ORDER BY m.created_at",
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.into_iter().map(InviteRow::try_into).collect()
+    }
+
+    /// Records a pending invite. Returns `false` when the user is already a
+    /// member or has an open invite.
+    pub async fn add_member(
+        &self,
+        workspace_id: i64,
+        user_id: &str,
+        role: WorkspaceRole,
+        wrapped_key: &WrappedKey,
+    ) -> anyhow::Result<bool> {
+        let result = sqlx::query(
+            "INSERT INTO workspace_members (workspace_id, user_id, role, wrapped_key, accepted)
+             VALUES (?, ?, ?, ?, 0)
+             ON CONFLICT (workspace_id, user_id) DO NOTHING",
+        )
+        .bind(workspace_id)
+        .bind(user_id)
+        .bind(role.as_str())
+        .bind(wrapped_key_to_string(wrapped_key)?)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn accept_invite(&self, user_id: &str, workspace_id: i64) -> anyhow::Result<bool> {
+        let result = sqlx::query(
+            "UPDATE workspace_members SET accepted = 1 WHERE workspace_id = ? AND user_id = ? AND accepted = 0",
+        )
+        .bind(workspace_id)
+        .bind(user_id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn update_member_role(
+        &self,
+        workspace_id: i64,
+        user_id: &str,
+        role: WorkspaceRole,
+    ) -> anyhow::Result<bool> {
+        let result = sqlx::query(
+            "UPDATE workspace_members SET role = ? WHERE workspace_id = ? AND user_id = ? AND role != 'owner'",
+        )
+        .bind(role.as_str())
+        .bind(workspace_id)
+        .bind(user_id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn remove_member(&self, workspace_id: i64, user_id: &str) -> anyhow::Result<bool> {
+        let result = sqlx::query(
+            "DELETE FROM workspace_members WHERE workspace_id = ? AND user_id = ? AND role != 'owner'",
+        )
+        .bind(workspace_id)
+        .bind(user_id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Replaces a legacy workspace's name and every task with copies
+    /// encrypted under a new workspace key. Returns `false` when the workspace
+    /// is already keyed or `tasks` does not cover every task in it.
+    pub async fn rekey_workspace(
+        &self,
+        user_id: &str,
+        workspace_id: i64,
+        name: &EncryptedField,
+        wrapped_key: &WrappedKey,
+        tasks: &[RekeyedTask<'_>],
+    ) -> anyhow::Result<bool> {
+        let mut tx = self.pool.begin().await?;
+
+        let keyed_members = sqlx::query_scalar::<_, i64>(
+            "SELECT COUNT(*) FROM workspace_members WHERE workspace_id = ? AND wrapped_key IS NOT NULL",
+        )
+        .bind(workspace_id)
+        .fetch_one(&mut *tx)
+        .await?;
+        let task_count =
+            sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM tasks WHERE workspace_id = ?")
+                .bind(workspace_id)
+                .fetch_one(&mut *tx)
+                .await?;
+        if keyed_members > 0 || task_count != tasks.len() as i64 {
+            return Ok(false);
+        }
+
+        sqlx::query("UPDATE workspaces SET name = ? WHERE id = ?")
+            .bind(encrypted_field_to_string(name)?)
+            .bind(workspace_id)
+            .execute(&mut *tx)
+            .await?;
+
+        for task in tasks {
+            let result = sqlx::query(
+                "UPDATE tasks SET title = ?, description = ?, due_date = ?, recurrence = ?, priority = ?,
+                                 tags = ?
+                 WHERE workspace_id = ? AND id = ?",
+            )
+            .bind(encrypted_field_to_string(task.title)?)
+            .bind(
+                task.description
+                    .map(encrypted_field_to_string)
+                    .transpose()?,
+            )
+            .bind(task.due_date.map(encrypted_field_to_string).transpose()?)
+            .bind(task.recurrence.map(encrypted_field_to_string).transpose()?)
+            .bind(task.priority.map(encrypted_field_to_string).transpose()?)
+            .bind(task.tags.map(encrypted_field_to_string).transpose()?)
+            .bind(workspace_id)
+            .bind(task.id)
+            .execute(&mut *tx)
+            .await?;
+            if result.rows_affected() == 0 {
+                return Ok(false);
+            }
+        }
+
+        sqlx::query(
+            "UPDATE workspace_members SET wrapped_key = ? WHERE workspace_id = ? AND user_id = ?",
+        )
+        .bind(wrapped_key_to_string(wrapped_key)?)
+        .bind(workspace_id)
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(true)
     }
 
     pub async fn create_task(
@@ -140,41 +730,115 @@ impl Database {
         Ok(result.last_insert_rowid())
     }
 
-    pub async fn toggle_task_completion(&self, user_id: &str, task_id: i64) -> anyhow::Result<()> {
-        sqlx::query("UPDATE tasks SET completed = NOT completed WHERE user_id = ? AND id = ?")
-            .bind(user_id)
-            .bind(task_id)
-            .execute(&self.pool)
-            .await?;
-
-        Ok(())
-    }
-
-    pub async fn archive_completed_tasks(
+    pub async fn create_share(
         &self,
+        share_id: &str,
         user_id: &str,
         workspace_id: i64,
+        snapshot: &EncryptedField,
+        expires_at: Option<DateTime<Utc>>,
     ) -> anyhow::Result<()> {
         sqlx::query(
-            "UPDATE tasks SET archived = 1 WHERE user_id = ? AND workspace_id = ? AND completed = 1",
+            "INSERT INTO shares (id, user_id, workspace_id, snapshot, expires_at) VALUES (?, ?, ?, ?, ?)",
+        )
+        .bind(share_id)
+        .bind(user_id)
+        .bind(workspace_id)
+        .bind(encrypted_field_to_string(snapshot)?)
+        .bind(expires_at)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Returns a share unless it does not exist or has expired.
+    pub async fn get_share(&self, share_id: &str) -> anyhow::Result<Option<EncryptedShare>> {
+        let row = sqlx::query_as::<_, ShareRow>(
+            "SELECT snapshot, created_at, expires_at FROM shares WHERE id = ?",
         )
+        .bind(share_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        row.filter(|row| {
+            row.expires_at
+                .is_none_or(|expires_at| expires_at > Utc::now())
+        })
+        .map(ShareRow::try_into)
+        .transpose()
+    }
+
+    pub async fn delete_share(&self, user_id: &str, share_id: &str) -> anyhow::Result<bool> {
+        let result = sqlx::query("DELETE FROM shares WHERE id = ? AND user_id = ?")
+            .bind(share_id)
             .bind(user_id)
-            .bind(workspace_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn purge_expired_shares(&self) -> anyhow::Result<()> {
+        sqlx::query("DELETE FROM shares WHERE expires_at IS NOT NULL AND expires_at <= ?")
+            .bind(Utc::now())
             .execute(&self.pool)
             .await?;
 
         Ok(())
     }
 
+    pub async fn toggle_task_completion(&self, task_id: i64) -> anyhow::Result<()> {
+        sqlx::query("UPDATE tasks SET completed = NOT completed WHERE id = ?")
+            .bind(task_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Archives every completed task of a workspace and returns the ids that
+    /// were newly archived.
+    pub async fn archive_completed_tasks(&self, workspace_id: i64) -> anyhow::Result<Vec<i64>> {
+        let ids = sqlx::query_scalar(
+            "UPDATE tasks SET archived = 1
+             WHERE workspace_id = ? AND completed = 1 AND archived = 0
+             RETURNING id",
+        )
+        .bind(workspace_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(ids)
+    }
+
+    /// Restores archived tasks of a workspace; ids from other workspaces are
+    /// ignored.
+    pub async fn unarchive_tasks(&self, workspace_id: i64, task_ids: &[i64]) -> anyhow::Result<()> {
+        if task_ids.is_empty() {
+            return Ok(());
+        }
+
+        let mut query =
+            QueryBuilder::<Sqlite>::new("UPDATE tasks SET archived = 0 WHERE workspace_id = ");
+        query.push_bind(workspace_id).push(" AND id IN (");
+        let mut ids = query.separated(", ");
+        for id in task_ids {
+            ids.push_bind(id);
+        }
+        ids.push_unseparated(")");
+        query.build().execute(&self.pool).await?;
+
+        Ok(())
+    }
+
     pub async fn update_workspace_name(
         &self,
-        user_id: &str,
         workspace_id: i64,
         name: &EncryptedField,
     ) -> anyhow::Result<()> {
-        sqlx::query("UPDATE workspaces SET name = ? WHERE user_id = ? AND id = ?")
+        sqlx::query("UPDATE workspaces SET name = ? WHERE id = ?")
             .bind(encrypted_field_to_string(name)?)
-            .bind(user_id)
             .bind(workspace_id)
             .execute(&self.pool)
             .await?;
@@ -184,13 +848,11 @@ impl Database {
 
     pub async fn update_task_name(
         &self,
-        user_id: &str,
         task_id: i64,
         title: &EncryptedField,
     ) -> anyhow::Result<()> {
-        sqlx::query("UPDATE tasks SET title = ? WHERE user_id = ? AND id = ?")
+        sqlx::query("UPDATE tasks SET title = ? WHERE id = ?")
             .bind(encrypted_field_to_string(title)?)
-            .bind(user_id)
             .bind(task_id)
             .execute(&self.pool)
             .await?;
@@ -200,13 +862,11 @@ impl Database {
 
     pub async fn update_task_due_date(
         &self,
-        user_id: &str,
         task_id: i64,
         due_date: Option<&EncryptedField>,
     ) -> anyhow::Result<()> {
-        sqlx::query("UPDATE tasks SET due_date = ? WHERE user_id = ? AND id = ?")
+        sqlx::query("UPDATE tasks SET due_date = ? WHERE id = ?")
             .bind(due_date.map(encrypted_field_to_string).transpose()?)
-            .bind(user_id)
             .bind(task_id)
             .execute(&self.pool)
             .await?;
@@ -214,25 +874,68 @@ impl Database {
         Ok(())
     }
 
-    pub async fn delete_workspace(&self, user_id: &str, workspace_id: i64) -> anyhow::Result<()> {
-        sqlx::query("DELETE FROM tasks WHERE user_id = ? AND workspace_id = ?")
-            .bind(user_id)
-            .bind(workspace_id)
+    pub async fn update_task_recurrence(
+        &self,
+        task_id: i64,
+        recurrence: Option<&EncryptedField>,
+    ) -> anyhow::Result<()> {
+        sqlx::query("UPDATE tasks SET recurrence = ? WHERE id = ?")
+            .bind(recurrence.map(encrypted_field_to_string).transpose()?)
+            .bind(task_id)
             .execute(&self.pool)
             .await?;
 
-        sqlx::query("DELETE FROM workspaces WHERE user_id = ? AND id = ?")
-            .bind(user_id)
-            .bind(workspace_id)
+        Ok(())
+    }
+
+    pub async fn update_task_priority(
+        &self,
+        task_id: i64,
+        priority: Option<&EncryptedField>,
+    ) -> anyhow::Result<()> {
+        sqlx::query("UPDATE tasks SET priority = ? WHERE id = ?")
+            .bind(priority.map(encrypted_field_to_string).transpose()?)
+            .bind(task_id)
             .execute(&self.pool)
             .await?;
 
         Ok(())
     }
 
-    pub async fn delete_task(&self, user_id: &str, task_id: i64) -> anyhow::Result<()> {
-        sqlx::query("DELETE FROM tasks WHERE user_id = ? AND id = ?")
-            .bind(user_id)
+    pub async fn update_task_tags(
+        &self,
+        task_id: i64,
+        tags: Option<&EncryptedField>,
+    ) -> anyhow::Result<()> {
+        sqlx::query("UPDATE tasks SET tags = ? WHERE id = ?")
+            .bind(tags.map(encrypted_field_to_string).transpose()?)
+            .bind(task_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn delete_workspace(&self, workspace_id: i64) -> anyhow::Result<()> {
+        let mut tx = self.pool.begin().await?;
+        for statement in [
+            "DELETE FROM tasks WHERE workspace_id = ?",
+            "DELETE FROM shares WHERE workspace_id = ?",
+            "DELETE FROM workspace_members WHERE workspace_id = ?",
+            "DELETE FROM workspaces WHERE id = ?",
+        ] {
+            sqlx::query(statement)
+                .bind(workspace_id)
+                .execute(&mut *tx)
+                .await?;
+        }
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    pub async fn delete_task(&self, task_id: i64) -> anyhow::Result<()> {
+        sqlx::query("DELETE FROM tasks WHERE id = ?")
             .bind(task_id)
             .execute(&self.pool)
             .await?;
@@ -254,10 +957,82 @@ fn sqlite_database_path(database_url: &str) -> Option<&str> {
     }
 }
 
+/// Filters on the task metadata the server stores in plaintext.
+#[derive(Default)]
+pub struct TaskFilter {
+    pub include_archived: bool,
+    pub archived: Option<bool>,
+    pub completed: Option<bool>,
+    pub parent_task_id: Option<i64>,
+    pub created_after: Option<DateTime<Utc>>,
+    pub created_before: Option<DateTime<Utc>>,
+    pub updated_after: Option<DateTime<Utc>>,
+    pub updated_before: Option<DateTime<Utc>>,
+    pub after: Option<i64>,
+    pub limit: Option<u32>,
+}
+
+#[derive(FromRow)]
+pub struct AppliedMigration {
+    pub version: i64,
+    pub checksum: Vec<u8>,
+    pub success: bool,
+}
+
+#[derive(FromRow)]
+pub struct InstanceStats {
+    pub users: u64,
+    pub workspaces: u64,
+    pub tasks: u64,
+    pub archived_tasks: u64,
+    pub shares: u64,
+    pub unused_invites: u64,
+    pub size_bytes: u64,
+    pub free_bytes: u64,
+}
+
+#[derive(FromRow)]
+pub struct RegistrationInvite {
+    pub code: String,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub used_by: Option<String>,
+    pub used_at: Option<DateTime<Utc>>,
+}
+
+#[derive(FromRow)]
+pub struct UserUsage {
+    pub workspaces: u64,
+    pub tasks: u64,
+    pub bytes: u64,
+}
+
+#[derive(FromRow)]
+pub struct TaskStoredSizes {
+    pub user_id: String,
+    pub title: i64,
+    pub due_date: i64,
+    pub recurrence: i64,
+    pub priority: i64,
+    pub tags: i64,
+}
+
+pub struct RekeyedTask<'a> {
+    pub id: i64,
+    pub title: &'a EncryptedField,
+    pub description: Option<&'a EncryptedField>,
+    pub due_date: Option<&'a EncryptedField>,
+    pub recurrence: Option<&'a EncryptedField>,
+    pub priority: Option<&'a EncryptedField>,
+    pub tags: Option<&'a EncryptedField>,
+}
+
 #[derive(FromRow)]
 struct WorkspaceRow {
     id: i64,
     name: String,
+    role: String,
+    wrapped_key: Option<String>,
     created_at: DateTime<Utc>,
     updated_at: DateTime<Utc>,
 }
@@ -269,12 +1044,58 @@ impl TryFrom<WorkspaceRow> for EncryptedWorkspace {
         Ok(Self {
             id: row.id,
             name: encrypted_field_from_string(&row.name)?,
+            role: parse_role(&row.role)?,
+            wrapped_key: row
+                .wrapped_key
+                .as_deref()
+                .map(wrapped_key_from_string)
+                .transpose()?,
             created_at: row.created_at,
             updated_at: row.updated_at,
         })
     }
 }
 
+#[derive(FromRow)]
+struct MemberRow {
+    user_id: String,
+    role: String,
+    accepted: bool,
+}
+
+impl TryFrom<MemberRow> for WorkspaceMember {
+    type Error = anyhow::Error;
+
+    fn try_from(row: MemberRow) -> anyhow::Result<Self> {
+        Ok(Self {
+            user_id: row.user_id,
+            role: parse_role(&row.role)?,
+            accepted: row.accepted,
+        })
+    }
+}
+
+#[derive(FromRow)]
+struct InviteRow {
+    workspace_id: i64,
+    name: String,
+    role: String,
+    wrapped_key: String,
+}
+
+impl TryFrom<InviteRow> for EncryptedWorkspaceInvite {
+    type Error = anyhow::Error;
+
+    fn try_from(row: InviteRow) -> anyhow::Result<Self> {
+        Ok(Self {
+            workspace_id: row.workspace_id,
+            name: encrypted_field_from_string(&row.name)?,
+            role: parse_role(&row.role)?,
+            wrapped_key: wrapped_key_from_string(&row.wrapped_key)?,
+        })
+    }
+}
+
 #[derive(FromRow)]
 struct WorkspaceStatsRow {
     workspace_id: i64,
@@ -292,6 +1113,25 @@ impl From<WorkspaceStatsRow> for WorkspaceStats {
     }
 }
 
+#[derive(FromRow)]
+struct ShareRow {
+    snapshot: String,
+    created_at: DateTime<Utc>,
+    expires_at: Option<DateTime<Utc>>,
+}
+
+impl TryFrom<ShareRow> for EncryptedShare {
+    type Error = anyhow::Error;
+
+    fn try_from(row: ShareRow) -> anyhow::Result<Self> {
+        Ok(Self {
+            snapshot: encrypted_field_from_string(&row.snapshot)?,
+            created_at: row.created_at,
+            expires_at: row.expires_at,
+        })
+    }
+}
+
 #[derive(FromRow)]
 struct TaskRow {
     id: i64,
@@ -300,6 +1140,9 @@ struct TaskRow {
     completed: bool,
     archived: bool,
     due_date: Option<String>,
+    recurrence: Option<String>,
+    priority: Option<String>,
+    tags: Option<String>,
     workspace_id: i64,
     parent_task_id: Option<i64>,
     created_at: DateTime<Utc>,
@@ -325,6 +1168,21 @@ impl TryFrom<TaskRow> for EncryptedTask {
                 .as_deref()
                 .map(encrypted_field_from_string)
                 .transpose()?,
+            recurrence: row
+                .recurrence
+                .as_deref()
+                .map(encrypted_field_from_string)
+                .transpose()?,
+            priority: row
+                .priority
+                .as_deref()
+                .map(encrypted_field_from_string)
+                .transpose()?,
+            tags: row
+                .tags
+                .as_deref()
+                .map(encrypted_field_from_string)
+                .transpose()?,
             workspace_id: row.workspace_id,
             parent_task_id: row.parent_task_id,
             created_at: row.created_at,
@@ -333,6 +1191,11 @@ impl TryFrom<TaskRow> for EncryptedTask {
     }
 }
 
+/// The size `field` takes once stored, which is what quotas count.
+pub fn stored_size(field: &EncryptedField) -> anyhow::Result<usize> {
+    Ok(encrypted_field_to_string(field)?.len())
+}
+
 fn encrypted_field_to_string(field: &EncryptedField) -> anyhow::Result<String> {
     serde_json::to_string(field).map_err(Into::into)
 }
@@ -340,3 +1203,15 @@ fn encrypted_field_to_string(field: &EncryptedField) -> anyhow::Result<String> {
 fn encrypted_field_from_string(value: &str) -> anyhow::Result<EncryptedField> {
     serde_json::from_str(value).map_err(Into::into)
 }
+
+fn wrapped_key_to_string(wrapped_key: &WrappedKey) -> anyhow::Result<String> {
+    serde_json::to_string(wrapped_key).map_err(Into::into)
+}
+
+fn wrapped_key_from_string(value: &str) -> anyhow::Result<WrappedKey> {
+    serde_json::from_str(value).map_err(Into::into)
+}
+
+fn parse_role(value: &str) -> anyhow::Result<WorkspaceRole> {
+    WorkspaceRole::parse(value).ok_or_else(|| anyhow::anyhow!("unknown workspace role {value}"))
+}
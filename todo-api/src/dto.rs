@@ -1,5 +1,14 @@
 use serde::{Deserialize, Serialize};
 
+use crate::{
+    error::ApiError,
+    validation::{
+        self, MAX_DESCRIPTION_LEN, MAX_DUE_DATE_LEN, MAX_INVITE_CODE_LEN, MAX_NAME_LEN,
+        MAX_PRIORITY_LEN, MAX_RECURRENCE_LEN, MAX_SNAPSHOT_LEN, MAX_TAGS_LEN, MAX_TITLE_LEN,
+        Validate,
+    },
+};
+
 #[derive(Serialize)]
 pub struct WorkspacesResponse {
     pub workspaces: Vec<todo_client::EncryptedWorkspace>,
@@ -11,9 +20,71 @@ pub struct IdResponse {
     pub id: i64,
 }
 
+#[derive(Serialize, Deserialize)]
+pub struct TaskIdsBody {
+    pub task_ids: Vec<i64>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct PublicKeyBody {
+    pub public_key: String,
+}
+
+#[derive(Deserialize)]
+pub struct RegisterRequest {
+    pub invite_code: Option<String>,
+}
+
 #[derive(Deserialize)]
 pub struct CreateWorkspaceRequest {
     pub name: todo_client::EncryptedField,
+    pub wrapped_key: Option<todo_client::WrappedKey>,
+}
+
+#[derive(Deserialize)]
+pub struct InviteMemberRequest {
+    pub user_id: String,
+    pub role: todo_client::WorkspaceRole,
+    pub wrapped_key: todo_client::WrappedKey,
+}
+
+#[derive(Deserialize)]
+pub struct UpdateMemberRequest {
+    pub role: todo_client::WorkspaceRole,
+}
+
+#[derive(Deserialize)]
+pub struct RekeyWorkspaceRequest {
+    pub name: todo_client::EncryptedField,
+    pub wrapped_key: todo_client::WrappedKey,
+    pub tasks: Vec<RekeyedTask>,
+}
+
+#[derive(Deserialize)]
+pub struct RekeyedTask {
+    pub id: i64,
+    pub title: todo_client::EncryptedField,
+    pub description: Option<todo_client::EncryptedField>,
+    pub due_date: Option<todo_client::EncryptedField>,
+    pub recurrence: Option<todo_client::EncryptedField>,
+    pub priority: Option<todo_client::EncryptedField>,
+    pub tags: Option<todo_client::EncryptedField>,
+}
+
+#[derive(Deserialize)]
+pub struct ListTasksQuery {
+    #[serde(default)]
+    pub include_archived: bool,
+    pub archived: Option<bool>,
+    pub completed: Option<bool>,
+    pub parent_task_id: Option<i64>,
+    pub created_after: Option<chrono::DateTime<chrono::Utc>>,
+    pub created_before: Option<chrono::DateTime<chrono::Utc>>,
+    pub updated_after: Option<chrono::DateTime<chrono::Utc>>,
+    pub updated_before: Option<chrono::DateTime<chrono::Utc>>,
+    /// Cursor: only tasks with a larger id are returned.
+    pub after: Option<i64>,
+    pub limit: Option<u32>,
 }
 
 #[derive(Deserialize)]
@@ -33,4 +104,145 @@ pub struct UpdateTaskRequest {
     pub due_date: Option<todo_client::EncryptedField>,
     #[serde(default)]
     pub due_date_set: bool,
+    pub recurrence: Option<todo_client::EncryptedField>,
+    #[serde(default)]
+    pub recurrence_set: bool,
+    pub priority: Option<todo_client::EncryptedField>,
+    #[serde(default)]
+    pub priority_set: bool,
+    pub tags: Option<todo_client::EncryptedField>,
+    #[serde(default)]
+    pub tags_set: bool,
+}
+
+#[derive(Deserialize)]
+pub struct CreateShareRequest {
+    pub workspace_id: i64,
+    pub snapshot: todo_client::EncryptedField,
+    pub expires_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+#[derive(Serialize)]
+pub struct ShareIdResponse {
+    pub id: String,
+}
+
+impl Validate for PublicKeyBody {
+    fn validate(&self) -> Result<(), ApiError> {
+        validation::public_key("public_key", &self.public_key)
+    }
+}
+
+impl Validate for TaskIdsBody {
+    fn validate(&self) -> Result<(), ApiError> {
+        Ok(())
+    }
+}
+
+impl Validate for RegisterRequest {
+    fn validate(&self) -> Result<(), ApiError> {
+        match &self.invite_code {
+            Some(code) if code.len() > MAX_INVITE_CODE_LEN => Err(ApiError::unprocessable(
+                "invalid_invite",
+                format!("invite_code: longer than {MAX_INVITE_CODE_LEN} bytes"),
+            )),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl Validate for CreateWorkspaceRequest {
+    fn validate(&self) -> Result<(), ApiError> {
+        validation::encrypted_field("name", &self.name, MAX_NAME_LEN)?;
+        if let Some(wrapped_key) = &self.wrapped_key {
+            validation::wrapped_key("wrapped_key", wrapped_key)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Validate for InviteMemberRequest {
+    fn validate(&self) -> Result<(), ApiError> {
+        validation::wrapped_key("wrapped_key", &self.wrapped_key)
+    }
+}
+
+impl Validate for UpdateMemberRequest {
+    fn validate(&self) -> Result<(), ApiError> {
+        Ok(())
+    }
+}
+
+impl Validate for RekeyWorkspaceRequest {
+    fn validate(&self) -> Result<(), ApiError> {
+        validation::encrypted_field("name", &self.name, MAX_NAME_LEN)?;
+        validation::wrapped_key("wrapped_key", &self.wrapped_key)?;
+        for (index, task) in self.tasks.iter().enumerate() {
+            validation::encrypted_field(
+                &format!("tasks[{index}].title"),
+                &task.title,
+                MAX_TITLE_LEN,
+            )?;
+            validation::optional_encrypted_field(
+                &format!("tasks[{index}].description"),
+                task.description.as_ref(),
+                MAX_DESCRIPTION_LEN,
+            )?;
+            validation::optional_encrypted_field(
+                &format!("tasks[{index}].due_date"),
+                task.due_date.as_ref(),
+                MAX_DUE_DATE_LEN,
+            )?;
+            validation::optional_encrypted_field(
+                &format!("tasks[{index}].recurrence"),
+                task.recurrence.as_ref(),
+                MAX_RECURRENCE_LEN,
+            )?;
+            validation::optional_encrypted_field(
+                &format!("tasks[{index}].priority"),
+                task.priority.as_ref(),
+                MAX_PRIORITY_LEN,
+            )?;
+            validation::optional_encrypted_field(
+                &format!("tasks[{index}].tags"),
+                task.tags.as_ref(),
+                MAX_TAGS_LEN,
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Validate for UpdateWorkspaceRequest {
+    fn validate(&self) -> Result<(), ApiError> {
+        validation::encrypted_field("name", &self.name, MAX_NAME_LEN)
+    }
+}
+
+impl Validate for CreateTaskRequest {
+    fn validate(&self) -> Result<(), ApiError> {
+        validation::encrypted_field("title", &self.title, MAX_TITLE_LEN)
+    }
+}
+
+impl Validate for UpdateTaskRequest {
+    fn validate(&self) -> Result<(), ApiError> {
+        validation::optional_encrypted_field("title", self.title.as_ref(), MAX_TITLE_LEN)?;
+        validation::optional_encrypted_field("due_date", self.due_date.as_ref(), MAX_DUE_DATE_LEN)?;
+        validation::optional_encrypted_field(
+            "recurrence",
+            self.recurrence.as_ref(),
+            MAX_RECURRENCE_LEN,
+        )?;
+        validation::optional_encrypted_field("priority", self.priority.as_ref(), MAX_PRIORITY_LEN)?;
+        validation::optional_encrypted_field("tags", self.tags.as_ref(), MAX_TAGS_LEN)
+    }
+}
+
+impl Validate for CreateShareRequest {
+    fn validate(&self) -> Result<(), ApiError> {
+        validation::encrypted_field("snapshot", &self.snapshot, MAX_SNAPSHOT_LEN)
+    }
 }
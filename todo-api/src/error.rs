@@ -7,16 +7,48 @@ use serde::Serialize;
 
 pub struct ApiError {
     status: StatusCode,
+    code: &'static str,
     error: anyhow::Error,
 }
 
 impl ApiError {
-    pub fn bad_request(message: impl Into<String>) -> Self {
+    pub fn new(status: StatusCode, code: &'static str, message: impl Into<String>) -> Self {
         Self {
-            status: StatusCode::BAD_REQUEST,
+            status,
+            code,
             error: anyhow::anyhow!(message.into()),
         }
     }
+
+    pub fn bad_request(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::BAD_REQUEST, "bad_request", message)
+    }
+
+    pub fn forbidden(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::FORBIDDEN, "forbidden", message)
+    }
+
+    /// A refusal the client is expected to act on, told apart by `code`.
+    pub fn forbidden_with(code: &'static str, message: impl Into<String>) -> Self {
+        Self::new(StatusCode::FORBIDDEN, code, message)
+    }
+
+    pub fn not_found(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::NOT_FOUND, "not_found", message)
+    }
+
+    pub fn conflict(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::CONFLICT, "conflict", message)
+    }
+
+    pub fn payload_too_large(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::PAYLOAD_TOO_LARGE, "payload_too_large", message)
+    }
+
+    /// A well-formed request whose content fails validation.
+    pub fn unprocessable(code: &'static str, message: impl Into<String>) -> Self {
+        Self::new(StatusCode::UNPROCESSABLE_ENTITY, code, message)
+    }
 }
 
 impl<E> From<E> for ApiError
@@ -26,6 +58,7 @@ where
     fn from(error: E) -> Self {
         Self {
             status: StatusCode::INTERNAL_SERVER_ERROR,
+            code: "internal",
             error: error.into(),
         }
     }
@@ -36,6 +69,7 @@ impl IntoResponse for ApiError {
         (
             self.status,
             Json(ErrorResponse {
+                code: self.code,
                 error: self.error.to_string(),
             }),
         )
@@ -45,5 +79,6 @@ impl IntoResponse for ApiError {
 
 #[derive(Serialize)]
 struct ErrorResponse {
+    code: &'static str,
     error: String,
 }
@@ -0,0 +1,49 @@
+use std::sync::Arc;
+
+use futures_util::{Stream, stream};
+use todo_client::ChangeEvent;
+use tokio::sync::broadcast;
+
+/// Events buffered per subscriber before it is considered lagging.
+const CHANNEL_CAPACITY: usize = 256;
+
+/// In-process fan-out of change events to the users they concern.
+#[derive(Clone)]
+pub struct EventBus {
+    sender: broadcast::Sender<Arc<Envelope>>,
+}
+
+struct Envelope {
+    recipients: Vec<String>,
+    event: ChangeEvent,
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self {
+            sender: broadcast::channel(CHANNEL_CAPACITY).0,
+        }
+    }
+}
+
+impl EventBus {
+    pub fn publish(&self, recipients: Vec<String>, event: ChangeEvent) {
+        // Sending only fails when nobody is subscribed.
+        let _ = self.sender.send(Arc::new(Envelope { recipients, event }));
+    }
+
+    /// Streams the events addressed to `user_id`. The stream ends when the
+    /// subscriber falls behind, so the client reconnects and reloads instead
+    /// of silently missing changes.
+    pub fn subscribe(&self, user_id: String) -> impl Stream<Item = ChangeEvent> + Send + use<> {
+        let receiver = self.sender.subscribe();
+        stream::unfold((receiver, user_id), |(mut receiver, user_id)| async move {
+            loop {
+                let envelope = receiver.recv().await.ok()?;
+                if envelope.recipients.contains(&user_id) {
+                    return Some((envelope.event.clone(), (receiver, user_id)));
+                }
+            }
+        })
+    }
+}
@@ -1,28 +1,150 @@
+use std::{convert::Infallible, sync::Arc};
+
 use axum::{
     Json,
-    extract::{Path, State},
+    extract::{Path, Query, State},
     http::{HeaderMap, StatusCode},
+    response::sse::{Event, KeepAlive, Sse},
+};
+use futures_util::{Stream, StreamExt};
+use todo_client::{
+    ChangeEvent, ChangeKind, RegistrationPolicy, SUPPORTED_ENCRYPTION_VERSIONS, ServerInfo,
+    WorkspaceRole,
+    protocol::{
+        API_VERSION, FEATURE_EVENTS, FEATURE_MEMBERS, FEATURE_PAGINATION, FEATURE_PRIORITY,
+        FEATURE_RECURRENCE, FEATURE_REGISTRATION, FEATURE_SHARES, FEATURE_TAGS, FEATURE_UNARCHIVE,
+        FEATURE_WORKSPACE_KEYS, MAX_PAGE_SIZE, NEXT_CURSOR_HEADER,
+    },
 };
 
 use crate::{
-    AppState,
+    db::{self, Database},
     dto::{
-        CreateTaskRequest, CreateWorkspaceRequest, IdResponse, UpdateTaskRequest,
+        CreateShareRequest, CreateTaskRequest, CreateWorkspaceRequest, IdResponse,
+        InviteMemberRequest, ListTasksQuery, PublicKeyBody, RegisterRequest, RekeyWorkspaceRequest,
+        ShareIdResponse, TaskIdsBody, UpdateMemberRequest, UpdateTaskRequest,
         UpdateWorkspaceRequest, WorkspacesResponse,
     },
     error::ApiError,
+    events::EventBus,
+    limits::{QuotaCharge, Quotas},
+    validation::ValidJson,
 };
 
 pub async fn health() -> &'static str {
     "ok"
 }
 
+pub async fn info(State(registration): State<RegistrationPolicy>) -> Json<ServerInfo> {
+    Json(ServerInfo {
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        api_versions: vec![API_VERSION],
+        encryption_versions: SUPPORTED_ENCRYPTION_VERSIONS.to_vec(),
+        features: [
+            FEATURE_WORKSPACE_KEYS,
+            FEATURE_MEMBERS,
+            FEATURE_SHARES,
+            FEATURE_EVENTS,
+            FEATURE_PAGINATION,
+            FEATURE_REGISTRATION,
+            FEATURE_UNARCHIVE,
+            FEATURE_RECURRENCE,
+            FEATURE_PRIORITY,
+            FEATURE_TAGS,
+        ]
+        .map(String::from)
+        .to_vec(),
+        registration,
+    })
+}
+
+pub async fn register(
+    State(db): State<Arc<Database>>,
+    State(registration): State<RegistrationPolicy>,
+    headers: HeaderMap,
+    ValidJson(payload): ValidJson<RegisterRequest>,
+) -> Result<StatusCode, ApiError> {
+    let user_id = user_id(&headers)?;
+    if db.user_exists(user_id).await? {
+        return Ok(StatusCode::NO_CONTENT);
+    }
+
+    match (registration, payload.invite_code) {
+        (RegistrationPolicy::Open, _) => db.ensure_user(user_id).await?,
+        (RegistrationPolicy::InviteOnly, None) => {
+            return Err(ApiError::forbidden_with(
+                "invite_required",
+                "an invite code is required to register",
+            ));
+        }
+        (RegistrationPolicy::InviteOnly, Some(code)) => {
+            if !db.register_with_invite(user_id, code.trim()).await? {
+                return Err(ApiError::forbidden_with(
+                    "invalid_invite",
+                    "invite code is invalid, expired or already used",
+                ));
+            }
+        }
+        (RegistrationPolicy::Closed, _) => {
+            return Err(ApiError::forbidden_with(
+                "registration_closed",
+                "this server does not accept new users",
+            ));
+        }
+    }
+
+    Ok(StatusCode::CREATED)
+}
+
+/// Server-sent events for every change to the caller's workspaces.
+pub async fn stream_events(
+    State(events): State<EventBus>,
+    headers: HeaderMap,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, ApiError> {
+    let user_id = user_id(&headers)?;
+    let stream = events.subscribe(user_id.to_string()).map(|event| {
+        Ok(Event::default()
+            .event("change")
+            .data(serde_json::to_string(&event).unwrap_or_default()))
+    });
+
+    Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
+}
+
+pub async fn register_public_key(
+    State(db): State<Arc<Database>>,
+    State(registration): State<RegistrationPolicy>,
+    headers: HeaderMap,
+    ValidJson(payload): ValidJson<PublicKeyBody>,
+) -> Result<StatusCode, ApiError> {
+    let user_id = user_id(&headers)?;
+    ensure_user(&db, registration, user_id).await?;
+    db.set_public_key(user_id, &payload.public_key).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn get_public_key(
+    State(db): State<Arc<Database>>,
+    headers: HeaderMap,
+    Path(target_user_id): Path<String>,
+) -> Result<Json<PublicKeyBody>, ApiError> {
+    user_id(&headers)?;
+    let public_key = db
+        .get_public_key(&target_user_id)
+        .await?
+        .ok_or_else(|| ApiError::not_found("user has no public key"))?;
+
+    Ok(Json(PublicKeyBody { public_key }))
+}
+
 pub async fn list_workspaces(
-    State(db): State<AppState>,
+    State(db): State<Arc<Database>>,
+    State(registration): State<RegistrationPolicy>,
     headers: HeaderMap,
 ) -> Result<Json<WorkspacesResponse>, ApiError> {
     let user_id = user_id(&headers)?;
-    db.ensure_user(user_id).await?;
+    ensure_user(&db, registration, user_id).await?;
     let workspaces = db.get_workspaces(user_id).await?;
     let stats = db.get_workspace_stats(user_id).await?;
 
@@ -30,62 +152,369 @@ pub async fn list_workspaces(
 }
 
 pub async fn create_workspace(
-    State(db): State<AppState>,
+    State(db): State<Arc<Database>>,
+    State(registration): State<RegistrationPolicy>,
+    State(events): State<EventBus>,
+    State(quotas): State<Quotas>,
     headers: HeaderMap,
-    Json(payload): Json<CreateWorkspaceRequest>,
+    ValidJson(payload): ValidJson<CreateWorkspaceRequest>,
 ) -> Result<Json<IdResponse>, ApiError> {
     let user_id = user_id(&headers)?;
-    db.ensure_user(user_id).await?;
-    let id = db.create_workspace(user_id, &payload.name).await?;
+    ensure_user(&db, registration, user_id).await?;
+    let bytes = db::stored_size(&payload.name)?;
+    quotas
+        .check(&db, user_id, QuotaCharge::Workspace { bytes })
+        .await?;
+    let id = db
+        .create_workspace(user_id, &payload.name, payload.wrapped_key.as_ref())
+        .await?;
+    publish_change(&db, &events, ChangeKind::WorkspaceCreated, id, None).await?;
 
     Ok(Json(IdResponse { id }))
 }
 
 pub async fn update_workspace(
-    State(db): State<AppState>,
+    State(db): State<Arc<Database>>,
+    State(events): State<EventBus>,
+    State(quotas): State<Quotas>,
     headers: HeaderMap,
     Path(workspace_id): Path<i64>,
-    Json(payload): Json<UpdateWorkspaceRequest>,
+    ValidJson(payload): ValidJson<UpdateWorkspaceRequest>,
 ) -> Result<StatusCode, ApiError> {
     let user_id = user_id(&headers)?;
-    db.update_workspace_name(user_id, workspace_id, &payload.name)
+    require_workspace_role(&db, user_id, workspace_id, WorkspaceRole::Write).await?;
+    if let Some((owner, stored)) = db.workspace_stored_size(workspace_id).await? {
+        let growth = db::stored_size(&payload.name)? as i64 - stored;
+        charge_growth(&db, &quotas, &owner, growth).await?;
+    }
+    db.update_workspace_name(workspace_id, &payload.name)
         .await?;
+    publish_change(
+        &db,
+        &events,
+        ChangeKind::WorkspaceUpdated,
+        workspace_id,
+        None,
+    )
+    .await?;
 
     Ok(StatusCode::NO_CONTENT)
 }
 
 pub async fn delete_workspace(
-    State(db): State<AppState>,
+    State(db): State<Arc<Database>>,
+    State(events): State<EventBus>,
     headers: HeaderMap,
     Path(workspace_id): Path<i64>,
 ) -> Result<StatusCode, ApiError> {
     let user_id = user_id(&headers)?;
-    db.delete_workspace(user_id, workspace_id).await?;
+    require_workspace_role(&db, user_id, workspace_id, WorkspaceRole::Owner).await?;
+    let recipients = db.workspace_member_ids(workspace_id).await?;
+    db.delete_workspace(workspace_id).await?;
+    events.publish(
+        recipients,
+        ChangeEvent {
+            kind: ChangeKind::WorkspaceDeleted,
+            workspace_id,
+            task_id: None,
+        },
+    );
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn rekey_workspace(
+    State(db): State<Arc<Database>>,
+    State(events): State<EventBus>,
+    headers: HeaderMap,
+    Path(workspace_id): Path<i64>,
+    ValidJson(payload): ValidJson<RekeyWorkspaceRequest>,
+) -> Result<StatusCode, ApiError> {
+    let user_id = user_id(&headers)?;
+    require_workspace_role(&db, user_id, workspace_id, WorkspaceRole::Owner).await?;
+    let tasks: Vec<db::RekeyedTask> = payload
+        .tasks
+        .iter()
+        .map(|task| db::RekeyedTask {
+            id: task.id,
+            title: &task.title,
+            description: task.description.as_ref(),
+            due_date: task.due_date.as_ref(),
+            recurrence: task.recurrence.as_ref(),
+            priority: task.priority.as_ref(),
+            tags: task.tags.as_ref(),
+        })
+        .collect();
+    if !db
+        .rekey_workspace(
+            user_id,
+            workspace_id,
+            &payload.name,
+            &payload.wrapped_key,
+            &tasks,
+        )
+        .await?
+    {
+        return Err(ApiError::conflict(
+            "workspace is already keyed or its tasks changed",
+        ));
+    }
+    publish_change(
+        &db,
+        &events,
+        ChangeKind::WorkspaceUpdated,
+        workspace_id,
+        None,
+    )
+    .await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn list_members(
+    State(db): State<Arc<Database>>,
+    headers: HeaderMap,
+    Path(workspace_id): Path<i64>,
+) -> Result<Json<Vec<todo_client::WorkspaceMember>>, ApiError> {
+    let user_id = user_id(&headers)?;
+    require_workspace_role(&db, user_id, workspace_id, WorkspaceRole::Read).await?;
+    let members = db.get_members(workspace_id).await?;
+
+    Ok(Json(members))
+}
+
+pub async fn invite_member(
+    State(db): State<Arc<Database>>,
+    State(events): State<EventBus>,
+    headers: HeaderMap,
+    Path(workspace_id): Path<i64>,
+    ValidJson(payload): ValidJson<InviteMemberRequest>,
+) -> Result<StatusCode, ApiError> {
+    let user_id = user_id(&headers)?;
+    require_workspace_role(&db, user_id, workspace_id, WorkspaceRole::Owner).await?;
+    if payload.role == WorkspaceRole::Owner {
+        return Err(ApiError::bad_request("members cannot be invited as owner"));
+    }
+    if db.get_public_key(&payload.user_id).await?.is_none() {
+        return Err(ApiError::not_found("user has no public key"));
+    }
+    if !db
+        .add_member(
+            workspace_id,
+            &payload.user_id,
+            payload.role,
+            &payload.wrapped_key,
+        )
+        .await?
+    {
+        return Err(ApiError::conflict("user is already a member"));
+    }
+    publish_change(&db, &events, ChangeKind::MembersChanged, workspace_id, None).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn update_member(
+    State(db): State<Arc<Database>>,
+    State(events): State<EventBus>,
+    headers: HeaderMap,
+    Path((workspace_id, member_id)): Path<(i64, String)>,
+    ValidJson(payload): ValidJson<UpdateMemberRequest>,
+) -> Result<StatusCode, ApiError> {
+    let user_id = user_id(&headers)?;
+    require_workspace_role(&db, user_id, workspace_id, WorkspaceRole::Owner).await?;
+    if payload.role == WorkspaceRole::Owner {
+        return Err(ApiError::bad_request("ownership cannot be transferred"));
+    }
+    if !db
+        .update_member_role(workspace_id, &member_id, payload.role)
+        .await?
+    {
+        return Err(ApiError::not_found("member not found"));
+    }
+    publish_change(&db, &events, ChangeKind::MembersChanged, workspace_id, None).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn remove_member(
+    State(db): State<Arc<Database>>,
+    State(events): State<EventBus>,
+    headers: HeaderMap,
+    Path((workspace_id, member_id)): Path<(i64, String)>,
+) -> Result<StatusCode, ApiError> {
+    let user_id = user_id(&headers)?;
+    if member_id != user_id {
+        require_workspace_role(&db, user_id, workspace_id, WorkspaceRole::Owner).await?;
+    }
+    let recipients = db.workspace_member_ids(workspace_id).await?;
+    if !db.remove_member(workspace_id, &member_id).await? {
+        return Err(ApiError::not_found("member not found"));
+    }
+    events.publish(
+        recipients,
+        ChangeEvent {
+            kind: ChangeKind::MembersChanged,
+            workspace_id,
+            task_id: None,
+        },
+    );
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn list_invites(
+    State(db): State<Arc<Database>>,
+    headers: HeaderMap,
+) -> Result<Json<Vec<todo_client::EncryptedWorkspaceInvite>>, ApiError> {
+    let user_id = user_id(&headers)?;
+    let invites = db.get_invites(user_id).await?;
+
+    Ok(Json(invites))
+}
+
+pub async fn accept_invite(
+    State(db): State<Arc<Database>>,
+    State(events): State<EventBus>,
+    headers: HeaderMap,
+    Path(workspace_id): Path<i64>,
+) -> Result<StatusCode, ApiError> {
+    let user_id = user_id(&headers)?;
+    if !db.accept_invite(user_id, workspace_id).await? {
+        return Err(ApiError::not_found("invite not found"));
+    }
+    publish_change(&db, &events, ChangeKind::MembersChanged, workspace_id, None).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn create_share(
+    State(db): State<Arc<Database>>,
+    State(quotas): State<Quotas>,
+    headers: HeaderMap,
+    ValidJson(payload): ValidJson<CreateShareRequest>,
+) -> Result<Json<ShareIdResponse>, ApiError> {
+    let user_id = user_id(&headers)?;
+    require_workspace_role(&db, user_id, payload.workspace_id, WorkspaceRole::Write).await?;
+    if payload
+        .expires_at
+        .is_some_and(|expires_at| expires_at <= chrono::Utc::now())
+    {
+        return Err(ApiError::bad_request("expiry must be in the future"));
+    }
+
+    db.purge_expired_shares().await?;
+    let bytes = db::stored_size(&payload.snapshot)?;
+    quotas
+        .check(&db, user_id, QuotaCharge::Share { bytes })
+        .await?;
+    let id = share_id();
+    db.create_share(
+        &id,
+        user_id,
+        payload.workspace_id,
+        &payload.snapshot,
+        payload.expires_at,
+    )
+    .await?;
+
+    Ok(Json(ShareIdResponse { id }))
+}
+
+pub async fn get_share(
+    State(db): State<Arc<Database>>,
+    Path(share_id): Path<String>,
+) -> Result<Json<todo_client::EncryptedShare>, ApiError> {
+    let share = db
+        .get_share(&share_id)
+        .await?
+        .ok_or_else(|| ApiError::not_found("share not found"))?;
+
+    Ok(Json(share))
+}
+
+pub async fn delete_share(
+    State(db): State<Arc<Database>>,
+    headers: HeaderMap,
+    Path(share_id): Path<String>,
+) -> Result<StatusCode, ApiError> {
+    let user_id = user_id(&headers)?;
+    if !db.delete_share(user_id, &share_id).await? {
+        return Err(ApiError::not_found("share not found"));
+    }
 
     Ok(StatusCode::NO_CONTENT)
 }
 
 pub async fn list_tasks(
-    State(db): State<AppState>,
+    State(db): State<Arc<Database>>,
     headers: HeaderMap,
     Path(workspace_id): Path<i64>,
-) -> Result<Json<Vec<todo_client::EncryptedTask>>, ApiError> {
+    Query(query): Query<ListTasksQuery>,
+) -> Result<(HeaderMap, Json<Vec<todo_client::EncryptedTask>>), ApiError> {
     let user_id = user_id(&headers)?;
-    let tasks = db.get_tasks_for_workspace(user_id, workspace_id).await?;
+    require_workspace_role(&db, user_id, workspace_id, WorkspaceRole::Read).await?;
+    if query.limit == Some(0) {
+        return Err(ApiError::bad_request("limit must be at least 1"));
+    }
+
+    // Without a limit everything is returned, as for clients from before
+    // pagination. One extra row tells whether another page follows.
+    let limit = query.limit.map(|limit| limit.min(MAX_PAGE_SIZE));
+    let mut tasks = db
+        .get_tasks_for_workspace(
+            workspace_id,
+            &db::TaskFilter {
+                include_archived: query.include_archived,
+                archived: query.archived,
+                completed: query.completed,
+                parent_task_id: query.parent_task_id,
+                created_after: query.created_after,
+                created_before: query.created_before,
+                updated_after: query.updated_after,
+                updated_before: query.updated_before,
+                after: query.after,
+                limit: limit.map(|limit| limit + 1),
+            },
+        )
+        .await?;
+
+    let mut response_headers = HeaderMap::new();
+    if let Some(limit) = limit
+        && tasks.len() > limit as usize
+    {
+        tasks.truncate(limit as usize);
+        if let Some(last) = tasks.last() {
+            response_headers.insert(NEXT_CURSOR_HEADER, last.id.into());
+        }
+    }
 
-    Ok(Json(tasks))
+    Ok((response_headers, Json(tasks)))
 }
 
 pub async fn create_task(
-    State(db): State<AppState>,
+    State(db): State<Arc<Database>>,
+    State(registration): State<RegistrationPolicy>,
+    State(events): State<EventBus>,
+    State(quotas): State<Quotas>,
     headers: HeaderMap,
     Path(workspace_id): Path<i64>,
-    Json(payload): Json<CreateTaskRequest>,
+    ValidJson(payload): ValidJson<CreateTaskRequest>,
 ) -> Result<Json<IdResponse>, ApiError> {
     let user_id = user_id(&headers)?;
-    db.ensure_user(user_id).await?;
+    ensure_user(&db, registration, user_id).await?;
+    require_workspace_role(&db, user_id, workspace_id, WorkspaceRole::Write).await?;
+    let bytes = db::stored_size(&payload.title)?;
+    quotas
+        .check(&db, user_id, QuotaCharge::Task { bytes })
+        .await?;
     let id = match payload.parent_task_id {
         Some(parent_task_id) => {
+            if db.task_workspace_id(parent_task_id).await? != Some(workspace_id) {
+                return Err(ApiError::bad_request(
+                    "parent task is not in this workspace",
+                ));
+            }
             db.create_subtask(user_id, &payload.title, workspace_id, parent_task_id)
                 .await?
         }
@@ -94,62 +523,180 @@ pub async fn create_task(
                 .await?
         }
     };
+    publish_change(
+        &db,
+        &events,
+        ChangeKind::TaskCreated,
+        workspace_id,
+        Some(id),
+    )
+    .await?;
 
     Ok(Json(IdResponse { id }))
 }
 
 pub async fn archive_completed_tasks(
-    State(db): State<AppState>,
+    State(db): State<Arc<Database>>,
+    State(events): State<EventBus>,
+    headers: HeaderMap,
+    Path(workspace_id): Path<i64>,
+) -> Result<Json<TaskIdsBody>, ApiError> {
+    let user_id = user_id(&headers)?;
+    require_workspace_role(&db, user_id, workspace_id, WorkspaceRole::Write).await?;
+    let task_ids = db.archive_completed_tasks(workspace_id).await?;
+    if !task_ids.is_empty() {
+        publish_change(&db, &events, ChangeKind::TasksArchived, workspace_id, None).await?;
+    }
+
+    Ok(Json(TaskIdsBody { task_ids }))
+}
+
+pub async fn unarchive_tasks(
+    State(db): State<Arc<Database>>,
+    State(events): State<EventBus>,
     headers: HeaderMap,
     Path(workspace_id): Path<i64>,
+    ValidJson(payload): ValidJson<TaskIdsBody>,
 ) -> Result<StatusCode, ApiError> {
     let user_id = user_id(&headers)?;
-    db.archive_completed_tasks(user_id, workspace_id).await?;
+    require_workspace_role(&db, user_id, workspace_id, WorkspaceRole::Write).await?;
+    db.unarchive_tasks(workspace_id, &payload.task_ids).await?;
+    // Clients treat this as "archive state changed" and reload the workspace.
+    publish_change(&db, &events, ChangeKind::TasksArchived, workspace_id, None).await?;
 
     Ok(StatusCode::NO_CONTENT)
 }
 
 pub async fn update_task(
-    State(db): State<AppState>,
+    State(db): State<Arc<Database>>,
+    State(events): State<EventBus>,
+    State(quotas): State<Quotas>,
     headers: HeaderMap,
     Path(task_id): Path<i64>,
-    Json(payload): Json<UpdateTaskRequest>,
+    ValidJson(payload): ValidJson<UpdateTaskRequest>,
 ) -> Result<StatusCode, ApiError> {
     let user_id = user_id(&headers)?;
+    let workspace_id = require_task_role(&db, user_id, task_id, WorkspaceRole::Write).await?;
+    // Stored bytes count against whoever created the task.
+    if let Some(stored) = db.task_stored_sizes(task_id).await? {
+        let mut growth = 0;
+        if let Some(title) = &payload.title {
+            growth += db::stored_size(title)? as i64 - stored.title;
+        }
+        for (set, field, stored) in [
+            (payload.due_date_set, &payload.due_date, stored.due_date),
+            (
+                payload.recurrence_set,
+                &payload.recurrence,
+                stored.recurrence,
+            ),
+            (payload.priority_set, &payload.priority, stored.priority),
+            (payload.tags_set, &payload.tags, stored.tags),
+        ] {
+            if set {
+                let size = field.as_ref().map(db::stored_size).transpose()?;
+                growth += size.unwrap_or(0) as i64 - stored;
+            }
+        }
+        charge_growth(&db, &quotas, &stored.user_id, growth).await?;
+    }
     if let Some(title) = payload.title {
-        db.update_task_name(user_id, task_id, &title).await?;
+        db.update_task_name(task_id, &title).await?;
     }
 
     if payload.due_date_set {
-        db.update_task_due_date(user_id, task_id, payload.due_date.as_ref())
+        db.update_task_due_date(task_id, payload.due_date.as_ref())
+            .await?;
+    }
+
+    if payload.recurrence_set {
+        db.update_task_recurrence(task_id, payload.recurrence.as_ref())
             .await?;
     }
 
+    if payload.priority_set {
+        db.update_task_priority(task_id, payload.priority.as_ref())
+            .await?;
+    }
+
+    if payload.tags_set {
+        db.update_task_tags(task_id, payload.tags.as_ref()).await?;
+    }
+    publish_change(
+        &db,
+        &events,
+        ChangeKind::TaskUpdated,
+        workspace_id,
+        Some(task_id),
+    )
+    .await?;
+
     Ok(StatusCode::NO_CONTENT)
 }
 
 pub async fn delete_task(
-    State(db): State<AppState>,
+    State(db): State<Arc<Database>>,
+    State(events): State<EventBus>,
     headers: HeaderMap,
     Path(task_id): Path<i64>,
 ) -> Result<StatusCode, ApiError> {
     let user_id = user_id(&headers)?;
-    db.delete_task(user_id, task_id).await?;
+    let workspace_id = require_task_role(&db, user_id, task_id, WorkspaceRole::Write).await?;
+    db.delete_task(task_id).await?;
+    publish_change(
+        &db,
+        &events,
+        ChangeKind::TaskDeleted,
+        workspace_id,
+        Some(task_id),
+    )
+    .await?;
 
     Ok(StatusCode::NO_CONTENT)
 }
 
 pub async fn toggle_task(
-    State(db): State<AppState>,
+    State(db): State<Arc<Database>>,
+    State(events): State<EventBus>,
     headers: HeaderMap,
     Path(task_id): Path<i64>,
 ) -> Result<StatusCode, ApiError> {
     let user_id = user_id(&headers)?;
-    db.toggle_task_completion(user_id, task_id).await?;
+    let workspace_id = require_task_role(&db, user_id, task_id, WorkspaceRole::Write).await?;
+    db.toggle_task_completion(task_id).await?;
+    publish_change(
+        &db,
+        &events,
+        ChangeKind::TaskUpdated,
+        workspace_id,
+        Some(task_id),
+    )
+    .await?;
 
     Ok(StatusCode::NO_CONTENT)
 }
 
+/// Checks that `owner` may store `growth` more bytes; shrinking is free.
+async fn charge_growth(
+    db: &Database,
+    quotas: &Quotas,
+    owner: &str,
+    growth: i64,
+) -> Result<(), ApiError> {
+    if growth <= 0 {
+        return Ok(());
+    }
+    quotas
+        .check(
+            db,
+            owner,
+            QuotaCharge::Growth {
+                bytes: growth as usize,
+            },
+        )
+        .await
+}
+
 fn user_id(headers: &HeaderMap) -> Result<&str, ApiError> {
     let user_id = headers
         .get("x-user-id")
@@ -163,3 +710,87 @@ fn user_id(headers: &HeaderMap) -> Result<&str, ApiError> {
 
     Ok(user_id)
 }
+
+/// Registers unknown users on first use when registration is open; otherwise
+/// they have to go through [`register`] first.
+async fn ensure_user(
+    db: &Database,
+    registration: RegistrationPolicy,
+    user_id: &str,
+) -> Result<(), ApiError> {
+    if registration == RegistrationPolicy::Open {
+        db.ensure_user(user_id).await?;
+    } else if !db.user_exists(user_id).await? {
+        return Err(ApiError::forbidden_with(
+            "not_registered",
+            "register with the server before using it",
+        ));
+    }
+
+    Ok(())
+}
+
+/// 128 random bits, hex encoded, so share ids cannot be enumerated.
+fn share_id() -> String {
+    rand::random::<[u8; 16]>()
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+/// Tells every member of a workspace, including the caller's other devices,
+/// what changed.
+async fn publish_change(
+    db: &Database,
+    events: &EventBus,
+    kind: ChangeKind,
+    workspace_id: i64,
+    task_id: Option<i64>,
+) -> Result<(), ApiError> {
+    let recipients = db.workspace_member_ids(workspace_id).await?;
+    events.publish(
+        recipients,
+        ChangeEvent {
+            kind,
+            workspace_id,
+            task_id,
+        },
+    );
+
+    Ok(())
+}
+
+async fn require_workspace_role(
+    db: &Database,
+    user_id: &str,
+    workspace_id: i64,
+    required: WorkspaceRole,
+) -> Result<WorkspaceRole, ApiError> {
+    let role = db
+        .workspace_role(user_id, workspace_id)
+        .await?
+        .ok_or_else(|| ApiError::not_found("workspace not found"))?;
+    if !role.allows(required) {
+        return Err(ApiError::forbidden(format!(
+            "{} access required",
+            required.as_str()
+        )));
+    }
+
+    Ok(role)
+}
+
+async fn require_task_role(
+    db: &Database,
+    user_id: &str,
+    task_id: i64,
+    required: WorkspaceRole,
+) -> Result<i64, ApiError> {
+    let workspace_id = db
+        .task_workspace_id(task_id)
+        .await?
+        .ok_or_else(|| ApiError::not_found("task not found"))?;
+    require_workspace_role(db, user_id, workspace_id, required).await?;
+
+    Ok(workspace_id)
+}
@@ -1,11 +1,86 @@
 use std::sync::Arc;
 
+use axum::extract::FromRef;
+use todo_client::RegistrationPolicy;
+
+pub mod admin;
 pub mod app;
+pub mod backup;
 pub mod db;
 mod dto;
 mod error;
+pub mod events;
 mod handlers;
+pub mod limits;
+pub mod validation;
 
 use db::Database;
+use events::EventBus;
+use limits::{Limits, Quotas, RateLimiter};
+
+#[derive(Clone)]
+pub struct AppState {
+    pub db: Arc<Database>,
+    pub events: EventBus,
+    pub limiter: RateLimiter,
+    pub quotas: Quotas,
+    pub registration: RegistrationPolicy,
+}
+
+impl AppState {
+    pub fn new(db: Arc<Database>) -> Self {
+        let limits = Limits::default();
+        Self {
+            db,
+            events: EventBus::default(),
+            limiter: RateLimiter::new(limits.per_user, limits.per_ip),
+            quotas: limits.quotas,
+            registration: RegistrationPolicy::default(),
+        }
+    }
+
+    pub fn with_limits(self, limits: Limits) -> Self {
+        Self {
+            limiter: RateLimiter::new(limits.per_user, limits.per_ip),
+            quotas: limits.quotas,
+            ..self
+        }
+    }
+
+    pub fn with_registration(self, registration: RegistrationPolicy) -> Self {
+        Self {
+            registration,
+            ..self
+        }
+    }
+}
+
+impl FromRef<AppState> for Arc<Database> {
+    fn from_ref(state: &AppState) -> Self {
+        Arc::clone(&state.db)
+    }
+}
+
+impl FromRef<AppState> for EventBus {
+    fn from_ref(state: &AppState) -> Self {
+        state.events.clone()
+    }
+}
+
+impl FromRef<AppState> for RateLimiter {
+    fn from_ref(state: &AppState) -> Self {
+        state.limiter.clone()
+    }
+}
+
+impl FromRef<AppState> for Quotas {
+    fn from_ref(state: &AppState) -> Self {
+        state.quotas
+    }
+}
 
-pub type AppState = Arc<Database>;
+impl FromRef<AppState> for RegistrationPolicy {
+    fn from_ref(state: &AppState) -> Self {
+        state.registration
+    }
+}
@@ -0,0 +1,342 @@
+//! Token-bucket rate limiting per user id and client IP, and per-user storage
+//! quotas. Both are configured from the environment.
+
+use std::{
+    collections::HashMap,
+    env,
+    net::{IpAddr, SocketAddr},
+    str::FromStr,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use axum::{
+    extract::{ConnectInfo, Request, State},
+    http::{HeaderValue, StatusCode, header::RETRY_AFTER},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+
+use crate::{db::Database, error::ApiError};
+
+/// Buckets kept before idle, refilled ones are dropped.
+const MAX_TRACKED_BUCKETS: usize = 10_000;
+/// What a sweep shrinks the buckets to, evicting the least recently used
+/// ones if needed, so the next sweep is a while away.
+const SWEPT_BUCKETS: usize = MAX_TRACKED_BUCKETS / 4 * 3;
+
+#[derive(Debug, Clone, Copy)]
+pub struct Rate {
+    /// Requests that can be made at once after being idle.
+    pub burst: u32,
+    /// Requests regained per second.
+    pub per_second: f64,
+}
+
+/// Limits on what a single user may store. `None` means unlimited.
+#[derive(Debug, Clone, Copy)]
+pub struct Quotas {
+    pub max_workspaces: Option<u64>,
+    pub max_tasks: Option<u64>,
+    /// Encrypted bytes across owned workspaces, created tasks and shares.
+    pub max_bytes: Option<u64>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Limits {
+    pub per_user: Rate,
+    pub per_ip: Rate,
+    pub quotas: Quotas,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            per_user: Rate {
+                burst: 60,
+                per_second: 10.0,
+            },
+            per_ip: Rate {
+                burst: 120,
+                per_second: 20.0,
+            },
+            quotas: Quotas {
+                max_workspaces: Some(200),
+                max_tasks: Some(20_000),
+                max_bytes: Some(64 * 1024 * 1024),
+            },
+        }
+    }
+}
+
+impl Limits {
+    /// Reads `TODO_RATE_{USER,IP}_{BURST,PER_SECOND}` and
+    /// `TODO_QUOTA_{WORKSPACES,TASKS,BYTES}`, falling back to the defaults.
+    /// A quota of 0 disables it; rates must be positive.
+    pub fn from_env() -> anyhow::Result<Self> {
+        let defaults = Self::default();
+
+        Ok(Self {
+            per_user: Rate::from_env("TODO_RATE_USER", defaults.per_user)?,
+            per_ip: Rate::from_env("TODO_RATE_IP", defaults.per_ip)?,
+            quotas: Quotas {
+                max_workspaces: quota_env("TODO_QUOTA_WORKSPACES", defaults.quotas.max_workspaces)?,
+                max_tasks: quota_env("TODO_QUOTA_TASKS", defaults.quotas.max_tasks)?,
+                max_bytes: quota_env("TODO_QUOTA_BYTES", defaults.quotas.max_bytes)?,
+            },
+        })
+    }
+}
+
+impl Rate {
+    /// Reads `{prefix}_BURST` and `{prefix}_PER_SECOND`.
+    fn from_env(prefix: &str, default: Self) -> anyhow::Result<Self> {
+        let burst = env_or(&format!("{prefix}_BURST"), default.burst)?;
+        if burst == 0 {
+            anyhow::bail!("{prefix}_BURST must be at least 1");
+        }
+        let per_second = env_or(&format!("{prefix}_PER_SECOND"), default.per_second)?;
+        if !per_second.is_finite() || per_second <= 0.0 {
+            anyhow::bail!("{prefix}_PER_SECOND must be a positive number");
+        }
+
+        Ok(Self { burst, per_second })
+    }
+}
+
+fn env_or<T>(name: &str, default: T) -> anyhow::Result<T>
+where
+    T: FromStr,
+    T::Err: std::error::Error + Send + Sync + 'static,
+{
+    match env::var(name) {
+        Ok(value) => value
+            .parse()
+            .map_err(|err| anyhow::anyhow!("invalid {name}: {err}")),
+        Err(_) => Ok(default),
+    }
+}
+
+fn quota_env(name: &str, default: Option<u64>) -> anyhow::Result<Option<u64>> {
+    let value = env_or(name, default.unwrap_or(0))?;
+    Ok((value > 0).then_some(value))
+}
+
+/// Usage a new item would add, checked against [`Quotas`] before it is stored.
+pub enum QuotaCharge {
+    Workspace {
+        bytes: usize,
+    },
+    Task {
+        bytes: usize,
+    },
+    Share {
+        bytes: usize,
+    },
+    /// Bytes an update adds to an existing item.
+    Growth {
+        bytes: usize,
+    },
+}
+
+impl Quotas {
+    pub async fn check(
+        &self,
+        db: &Database,
+        user_id: &str,
+        charge: QuotaCharge,
+    ) -> Result<(), ApiError> {
+        let usage = db.user_usage(user_id).await?;
+        let bytes = match charge {
+            QuotaCharge::Workspace { bytes } => {
+                exceeds(self.max_workspaces, usage.workspaces + 1, "workspace")?;
+                bytes
+            }
+            QuotaCharge::Task { bytes } => {
+                exceeds(self.max_tasks, usage.tasks + 1, "task")?;
+                bytes
+            }
+            QuotaCharge::Share { bytes } | QuotaCharge::Growth { bytes } => bytes,
+        };
+
+        exceeds(self.max_bytes, usage.bytes + bytes as u64, "storage")
+    }
+}
+
+fn exceeds(limit: Option<u64>, total: u64, what: &str) -> Result<(), ApiError> {
+    match limit {
+        Some(limit) if total > limit => Err(ApiError::new(
+            StatusCode::FORBIDDEN,
+            "quota_exceeded",
+            format!("{what} quota of {limit} exceeded"),
+        )),
+        _ => Ok(()),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum BucketKey {
+    User(String),
+    Ip(IpAddr),
+}
+
+struct Bucket {
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+/// Shared token buckets for every client the server has seen recently.
+#[derive(Clone)]
+pub struct RateLimiter {
+    per_user: Rate,
+    per_ip: Rate,
+    buckets: Arc<Mutex<HashMap<BucketKey, Bucket>>>,
+}
+
+impl RateLimiter {
+    pub fn new(per_user: Rate, per_ip: Rate) -> Self {
+        Self {
+            per_user,
+            per_ip,
+            buckets: Arc::default(),
+        }
+    }
+
+    /// Takes a token from each bucket the request counts against, or returns
+    /// how long until one is available.
+    fn acquire(&self, ip: Option<IpAddr>, user_id: Option<&str>) -> Result<(), Duration> {
+        let now = Instant::now();
+        let mut buckets = self.buckets.lock().expect("rate limiter lock poisoned");
+        if buckets.len() > MAX_TRACKED_BUCKETS {
+            self.sweep(&mut buckets, now);
+        }
+
+        let keys = ip
+            .map(BucketKey::Ip)
+            .into_iter()
+            .chain(user_id.map(|user_id| BucketKey::User(user_id.to_string())));
+        let mut taken = Vec::new();
+        for key in keys {
+            let rate = self.rate(&key);
+            let bucket = buckets.entry(key.clone()).or_insert(Bucket {
+                tokens: f64::from(rate.burst),
+                refilled_at: now,
+            });
+            bucket.tokens = bucket.available(rate, now);
+            bucket.refilled_at = now;
+            if bucket.tokens < 1.0 {
+                // Give back what earlier buckets handed out for this request.
+                for key in taken {
+                    if let Some(bucket) = buckets.get_mut(&key) {
+                        bucket.tokens += 1.0;
+                    }
+                }
+                let bucket = &buckets[&key];
+                return Err(Duration::from_secs_f64(
+                    (1.0 - bucket.tokens) / rate.per_second,
+                ));
+            }
+            bucket.tokens -= 1.0;
+            taken.push(key);
+        }
+
+        Ok(())
+    }
+
+    /// Drops the buckets that have refilled, then the least recently used
+    /// ones until at most [`SWEPT_BUCKETS`] are left.
+    fn sweep(&self, buckets: &mut HashMap<BucketKey, Bucket>, now: Instant) {
+        buckets.retain(|key, bucket| {
+            let rate = self.rate(key);
+            bucket.available(rate, now) < f64::from(rate.burst)
+        });
+
+        if buckets.len() <= SWEPT_BUCKETS {
+            return;
+        }
+        let excess = buckets.len() - SWEPT_BUCKETS;
+        let mut by_age: Vec<(Instant, BucketKey)> = buckets
+            .iter()
+            .map(|(key, bucket)| (bucket.refilled_at, key.clone()))
+            .collect();
+        by_age.select_nth_unstable_by_key(excess - 1, |(refilled_at, _)| *refilled_at);
+        for (_, key) in &by_age[..excess] {
+            buckets.remove(key);
+        }
+    }
+
+    fn rate(&self, key: &BucketKey) -> Rate {
+        match key {
+            BucketKey::User(_) => self.per_user,
+            BucketKey::Ip(_) => self.per_ip,
+        }
+    }
+}
+
+impl Bucket {
+    fn available(&self, rate: Rate, now: Instant) -> f64 {
+        let elapsed = now.duration_since(self.refilled_at).as_secs_f64();
+        (self.tokens + elapsed * rate.per_second).min(f64::from(rate.burst))
+    }
+}
+
+/// Middleware rejecting requests over their user's or IP's rate with 429 and
+/// a `Retry-After` in whole seconds.
+pub async fn rate_limit(
+    State(limiter): State<RateLimiter>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let ip = request
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| addr.ip());
+    let user_id = request
+        .headers()
+        .get("x-user-id")
+        .and_then(|value| value.to_str().ok());
+
+    match limiter.acquire(ip, user_id) {
+        Ok(()) => next.run(request).await,
+        Err(wait) => {
+            let seconds = wait.as_secs_f64().ceil().max(1.0) as u64;
+            let mut response = ApiError::new(
+                StatusCode::TOO_MANY_REQUESTS,
+                "rate_limited",
+                format!("too many requests, retry in {seconds}s"),
+            )
+            .into_response();
+            response
+                .headers_mut()
+                .insert(RETRY_AFTER, HeaderValue::from(seconds));
+            response
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sweeping_busy_buckets_evicts_the_oldest() {
+        // One request per second never refills a burst of 60 within the test.
+        let rate = Rate {
+            per_second: 1.0,
+            burst: 60,
+        };
+        let limiter = RateLimiter::new(rate, rate);
+        for n in 0..=MAX_TRACKED_BUCKETS {
+            limiter.acquire(None, Some(&format!("user-{n}"))).unwrap();
+        }
+        let tracked = |limiter: &RateLimiter| limiter.buckets.lock().unwrap().len();
+        assert_eq!(tracked(&limiter), MAX_TRACKED_BUCKETS + 1);
+
+        limiter.acquire(None, Some("newcomer")).unwrap();
+        assert_eq!(tracked(&limiter), SWEPT_BUCKETS + 1);
+        let buckets = limiter.buckets.lock().unwrap();
+        assert!(buckets.contains_key(&BucketKey::User("newcomer".to_string())));
+        assert!(buckets.contains_key(&BucketKey::User(format!("user-{MAX_TRACKED_BUCKETS}"))));
+        assert!(!buckets.contains_key(&BucketKey::User("user-0".to_string())));
+    }
+}
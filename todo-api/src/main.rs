@@ -1,13 +1,37 @@
-use std::{net::SocketAddr, sync::Arc};
+use std::{env, net::SocketAddr, sync::Arc};
 
-use todo_api::{app, db::Database};
+use clap::{Parser, Subcommand};
+use todo_api::{
+    AppState,
+    admin::{self, AdminCommand},
+    app,
+    backup::{self, BackupConfig},
+    db::Database,
+    limits::Limits,
+};
+use todo_client::RegistrationPolicy;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
 static MIGRATOR: sqlx::migrate::Migrator = sqlx::migrate!("./migrations");
 
+#[derive(Parser)]
+#[command(name = "todo-api", version, about)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Maintenance commands run against the database
+    #[command(subcommand)]
+    Admin(AdminCommand),
+}
+
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     dotenvy::dotenv().ok();
+    let cli = Cli::parse();
 
     tracing_subscriber::registry()
         .with(
@@ -19,15 +43,40 @@ async fn main() -> anyhow::Result<()> {
         .init();
 
     let db = Arc::new(Database::connect().await?);
+    if let Some(Command::Admin(command)) = cli.command {
+        return admin::run(&db, &MIGRATOR, command).await;
+    }
+
     db.run_migrations(&MIGRATOR).await?;
+    if let Some(config) = BackupConfig::from_env()? {
+        tracing::info!(dir = %config.dir.display(), "scheduled database snapshots enabled");
+        backup::spawn(Arc::clone(&db), config);
+    }
 
-    let app = app::router(db);
+    let app = app::router(
+        AppState::new(db)
+            .with_limits(Limits::from_env()?)
+            .with_registration(registration_policy()?),
+    );
 
     let addr = "0.0.0.0:3000".parse::<SocketAddr>()?;
     let listener = tokio::net::TcpListener::bind(addr).await?;
     tracing::info!(%addr, "todo-api listening");
 
-    axum::serve(listener, app).await?;
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .await?;
 
     Ok(())
 }
+
+/// Reads `TODO_REGISTRATION`: open (the default), invite-only or closed.
+fn registration_policy() -> anyhow::Result<RegistrationPolicy> {
+    match env::var("TODO_REGISTRATION") {
+        Ok(value) => RegistrationPolicy::parse(&value)
+            .ok_or_else(|| anyhow::anyhow!("invalid TODO_REGISTRATION: {value}")),
+        Err(_) => Ok(RegistrationPolicy::Open),
+    }
+}
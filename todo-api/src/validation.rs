@@ -0,0 +1,170 @@
+//! Request body extraction and validation of the encrypted payloads clients
+//! send. The server cannot decrypt anything, but it can refuse fields that no
+//! client could have produced before they are stored.
+
+use axum::{
+    Json,
+    extract::{FromRequest, Request, rejection::JsonRejection},
+    http::StatusCode,
+};
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use serde::de::DeserializeOwned;
+use todo_client::{
+    EncryptedField, SUPPORTED_ENCRYPTION_VERSIONS, SUPPORTED_KEY_WRAP_VERSIONS, WrappedKey,
+};
+
+use crate::error::ApiError;
+
+/// Default cap on request bodies.
+pub const DEFAULT_BODY_LIMIT: usize = 64 * 1024;
+/// Cap on rekey requests, which carry every task of a workspace.
+pub const REKEY_BODY_LIMIT: usize = 16 * 1024 * 1024;
+/// Cap on share publishing, which carries a whole workspace snapshot.
+pub const SHARE_BODY_LIMIT: usize = 8 * 1024 * 1024;
+
+/// Caps on the base64 ciphertext of individual fields.
+pub const MAX_NAME_LEN: usize = 16 * 1024;
+pub const MAX_TITLE_LEN: usize = 16 * 1024;
+pub const MAX_DESCRIPTION_LEN: usize = 48 * 1024;
+pub const MAX_DUE_DATE_LEN: usize = 1024;
+pub const MAX_RECURRENCE_LEN: usize = 1024;
+pub const MAX_PRIORITY_LEN: usize = 1024;
+pub const MAX_TAGS_LEN: usize = 4 * 1024;
+pub const MAX_SNAPSHOT_LEN: usize = SHARE_BODY_LIMIT - 1024;
+pub const MAX_INVITE_CODE_LEN: usize = 64;
+
+const NONCE_LEN: usize = 24;
+const TAG_LEN: usize = 16;
+const KEY_LEN: usize = 32;
+
+/// Like [`Json`], but runs [`Validate`] on the payload and reports failures as
+/// API errors with a machine-readable code.
+pub struct ValidJson<T>(pub T);
+
+impl<T, S> FromRequest<S> for ValidJson<T>
+where
+    T: DeserializeOwned + Validate,
+    S: Send + Sync,
+{
+    type Rejection = ApiError;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let Json(payload) = Json::<T>::from_request(req, state)
+            .await
+            .map_err(json_rejection)?;
+        payload.validate()?;
+
+        Ok(Self(payload))
+    }
+}
+
+fn json_rejection(rejection: JsonRejection) -> ApiError {
+    let code = match rejection.status() {
+        StatusCode::PAYLOAD_TOO_LARGE => "payload_too_large",
+        StatusCode::UNSUPPORTED_MEDIA_TYPE => "unsupported_media_type",
+        StatusCode::UNPROCESSABLE_ENTITY => "invalid_body",
+        _ => "malformed_json",
+    };
+
+    ApiError::new(rejection.status(), code, rejection.body_text())
+}
+
+pub trait Validate {
+    fn validate(&self) -> Result<(), ApiError>;
+}
+
+/// Checks that `field` has the shape of an XChaCha20-Poly1305 field written by
+/// a supported client version, and that its ciphertext fits in `max_len`.
+pub fn encrypted_field(name: &str, field: &EncryptedField, max_len: usize) -> Result<(), ApiError> {
+    if !SUPPORTED_ENCRYPTION_VERSIONS.contains(&field.version) {
+        return Err(ApiError::unprocessable(
+            "unsupported_encryption_version",
+            format!("{name}: unsupported encryption version {}", field.version),
+        ));
+    }
+    nonce(name, &field.nonce)?;
+    if field.ciphertext.len() > max_len {
+        return Err(ApiError::new(
+            StatusCode::PAYLOAD_TOO_LARGE,
+            "field_too_large",
+            format!("{name}: ciphertext exceeds {max_len} bytes"),
+        ));
+    }
+    let ciphertext = decode(name, "ciphertext", "invalid_ciphertext", &field.ciphertext)?;
+    if ciphertext.len() < TAG_LEN {
+        return Err(ApiError::unprocessable(
+            "invalid_ciphertext",
+            format!("{name}: ciphertext is shorter than the authentication tag"),
+        ));
+    }
+
+    Ok(())
+}
+
+pub fn optional_encrypted_field(
+    name: &str,
+    field: Option<&EncryptedField>,
+    max_len: usize,
+) -> Result<(), ApiError> {
+    field.map_or(Ok(()), |field| encrypted_field(name, field, max_len))
+}
+
+/// Checks that `wrapped` is a content key sealed by a supported client.
+pub fn wrapped_key(name: &str, wrapped: &WrappedKey) -> Result<(), ApiError> {
+    if !SUPPORTED_KEY_WRAP_VERSIONS.contains(&wrapped.version) {
+        return Err(ApiError::unprocessable(
+            "unsupported_encryption_version",
+            format!("{name}: unsupported key wrap version {}", wrapped.version),
+        ));
+    }
+    public_key(
+        &format!("{name}.ephemeral_public_key"),
+        &wrapped.ephemeral_public_key,
+    )?;
+    nonce(name, &wrapped.nonce)?;
+    let ciphertext = decode(
+        name,
+        "ciphertext",
+        "invalid_ciphertext",
+        &wrapped.ciphertext,
+    )?;
+    if ciphertext.len() != KEY_LEN + TAG_LEN {
+        return Err(ApiError::unprocessable(
+            "invalid_ciphertext",
+            format!("{name}: wrapped key must be {} bytes", KEY_LEN + TAG_LEN),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Checks that `value` is a base64 X25519 public key.
+pub fn public_key(name: &str, value: &str) -> Result<(), ApiError> {
+    let key = decode(name, "public key", "invalid_public_key", value)?;
+    if key.len() != KEY_LEN {
+        return Err(ApiError::unprocessable(
+            "invalid_public_key",
+            format!("{name}: public key must be {KEY_LEN} bytes"),
+        ));
+    }
+
+    Ok(())
+}
+
+fn nonce(name: &str, value: &str) -> Result<(), ApiError> {
+    let nonce = decode(name, "nonce", "invalid_nonce", value)?;
+    if nonce.len() != NONCE_LEN {
+        return Err(ApiError::unprocessable(
+            "invalid_nonce",
+            format!("{name}: nonce must be {NONCE_LEN} bytes"),
+        ));
+    }
+
+    Ok(())
+}
+
+fn decode(name: &str, part: &str, code: &'static str, value: &str) -> Result<Vec<u8>, ApiError> {
+    URL_SAFE_NO_PAD
+        .decode(value.as_bytes())
+        .map_err(|_| ApiError::unprocessable(code, format!("{name}: {part} is not valid base64")))
+}
@@ -0,0 +1,217 @@
+use std::{path::PathBuf, sync::Arc, time::Duration};
+
+use sqlx::migrate::Migrator;
+use todo_api::{backup, db::Database};
+use todo_client::EncryptedField;
+
+static MIGRATOR: Migrator = sqlx::migrate!("./migrations");
+
+const WRITERS: usize = 4;
+const WORKSPACES_PER_WRITER: usize = 40;
+const TASKS_PER_WORKSPACE: usize = 5;
+
+/// A scratch directory removed when the test finishes.
+struct ScratchDir(PathBuf);
+
+impl ScratchDir {
+    fn new(name: &str) -> Self {
+        let path = std::env::temp_dir().join(format!(
+            "todo-api-{name}-{}-{}",
+            std::process::id(),
+            chrono::Utc::now().timestamp_nanos_opt().unwrap_or_default()
+        ));
+        std::fs::create_dir_all(&path).unwrap();
+        Self(path)
+    }
+
+    fn url(&self, file: &str) -> String {
+        format!("sqlite://{}?mode=rwc", self.0.join(file).display())
+    }
+}
+
+impl Drop for ScratchDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+fn field(text: &str) -> EncryptedField {
+    EncryptedField {
+        ciphertext: text.to_string(),
+        nonce: "AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA".to_string(),
+        version: 1,
+    }
+}
+
+async fn open_migrated(url: &str) -> Database {
+    let db = Database::open(url).await.unwrap();
+    db.run_migrations(&MIGRATOR).await.unwrap();
+    db
+}
+
+/// Creates workspaces with tasks and subtasks as one user, as fast as it can.
+async fn write_workload(db: Arc<Database>, writer: usize) {
+    let user_id = format!("{writer:064x}");
+    db.ensure_user(&user_id).await.unwrap();
+    for workspace in 0..WORKSPACES_PER_WRITER {
+        let workspace_id = db
+            .create_workspace(&user_id, &field(&format!("ws-{writer}-{workspace}")), None)
+            .await
+            .unwrap();
+        let parent = db
+            .create_task(&user_id, &field("parent"), workspace_id)
+            .await
+            .unwrap();
+        for task in 1..TASKS_PER_WORKSPACE {
+            db.create_subtask(&user_id, &field(&format!("t{task}")), workspace_id, parent)
+                .await
+                .unwrap();
+        }
+    }
+}
+
+#[tokio::test]
+async fn snapshot_taken_under_concurrent_writes_restores_cleanly() {
+    let dir = ScratchDir::new("backup");
+    let live = Arc::new(open_migrated(&dir.url("live.db")).await);
+
+    let writers = (0..WRITERS)
+        .map(|writer| tokio::spawn(write_workload(Arc::clone(&live), writer)))
+        .collect::<Vec<_>>();
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    let snapshot = backup::snapshot(&live, &dir.0.join("snapshots"))
+        .await
+        .unwrap();
+    for writer in writers {
+        writer.await.unwrap();
+    }
+    let final_stats = live.instance_stats().await.unwrap();
+    live.close().await;
+
+    let target = dir.0.join("restored.db");
+    let previous = backup::restore(&snapshot.path, &target, &MIGRATOR)
+        .await
+        .unwrap();
+    assert!(previous.is_none());
+
+    let restored = Database::open(&dir.url("restored.db")).await.unwrap();
+    assert!(restored.integrity_problems().await.unwrap().is_empty());
+    assert!(
+        restored
+            .pending_migrations(&MIGRATOR)
+            .await
+            .unwrap()
+            .is_empty()
+    );
+    let stats = restored.instance_stats().await.unwrap();
+    assert!(stats.workspaces <= final_stats.workspaces);
+    assert!(stats.tasks <= final_stats.tasks);
+    // Every workspace in the snapshot has its owner membership, whatever
+    // point of the workload the snapshot caught.
+    for writer in 0..WRITERS {
+        let user_id = format!("{writer:064x}");
+        let workspaces = restored.get_workspaces(&user_id).await.unwrap();
+        assert_eq!(
+            workspaces.len() as u64,
+            restored.user_usage(&user_id).await.unwrap().workspaces
+        );
+    }
+}
+
+#[tokio::test]
+async fn restore_keeps_the_replaced_database() {
+    let dir = ScratchDir::new("restore");
+    let live = open_migrated(&dir.url("live.db")).await;
+    live.ensure_user(&"a".repeat(64)).await.unwrap();
+    let snapshot = backup::snapshot(&live, &dir.0.join("snapshots"))
+        .await
+        .unwrap();
+    live.ensure_user(&"b".repeat(64)).await.unwrap();
+    live.close().await;
+
+    let previous = backup::restore(&snapshot.path, &dir.0.join("live.db"), &MIGRATOR)
+        .await
+        .unwrap()
+        .unwrap();
+
+    let restored = Database::open(&dir.url("live.db")).await.unwrap();
+    assert_eq!(restored.instance_stats().await.unwrap().users, 1);
+    let replaced = Database::open(&format!("sqlite://{}", previous.display()))
+        .await
+        .unwrap();
+    assert_eq!(replaced.instance_stats().await.unwrap().users, 2);
+}
+
+#[tokio::test]
+async fn restore_rejects_snapshots_from_unknown_migrations() {
+    let dir = ScratchDir::new("unknown-migration");
+    let live = open_migrated(&dir.url("live.db")).await;
+    let snapshot = backup::snapshot(&live, &dir.0.join("snapshots"))
+        .await
+        .unwrap();
+    live.close().await;
+
+    let tampered = sqlx::SqlitePool::connect(&format!("sqlite://{}", snapshot.path.display()))
+        .await
+        .unwrap();
+    sqlx::query(
+        "INSERT INTO _sqlx_migrations (version, description, success, checksum, execution_time)
+         VALUES (99990101000000, 'from the future', 1, x'00', 0)",
+    )
+    .execute(&tampered)
+    .await
+    .unwrap();
+    tampered.close().await;
+
+    let result = backup::restore(&snapshot.path, &dir.0.join("live.db"), &MIGRATOR).await;
+
+    assert!(result.is_err());
+    let untouched = Database::open(&dir.url("live.db")).await.unwrap();
+    assert!(
+        untouched
+            .pending_migrations(&MIGRATOR)
+            .await
+            .unwrap()
+            .is_empty()
+    );
+}
+
+#[tokio::test]
+async fn prune_keeps_recent_and_daily_snapshots() {
+    let dir = ScratchDir::new("prune");
+    let names = [
+        "todo-20261001T120000.000Z.db",
+        "todo-20261002T080000.000Z.db",
+        "todo-20261002T200000.000Z.db",
+        "todo-20261003T090000.000Z.db",
+        "todo-20261003T100000.000Z.db",
+        "todo-20261003T110000.000Z.db",
+    ];
+    for name in names {
+        std::fs::write(dir.0.join(name), b"").unwrap();
+    }
+
+    let removed = backup::prune(&dir.0, 2, 2).unwrap();
+
+    let kept = backup::list_snapshots(&dir.0)
+        .unwrap()
+        .into_iter()
+        .map(|snapshot| {
+            snapshot
+                .path
+                .file_name()
+                .unwrap()
+                .to_string_lossy()
+                .into_owned()
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        kept,
+        [
+            "todo-20261002T200000.000Z.db",
+            "todo-20261003T100000.000Z.db",
+            "todo-20261003T110000.000Z.db",
+        ]
+    );
+    assert_eq!(removed.len(), 3);
+}
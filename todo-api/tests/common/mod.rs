@@ -0,0 +1,87 @@
+//! A real server on a scratch database, for tests that talk to it over HTTP.
+
+// Each test binary uses only some of these.
+#![allow(dead_code)]
+
+use std::{net::SocketAddr, path::PathBuf, sync::Arc};
+
+use reqwest::StatusCode;
+use sqlx::migrate::Migrator;
+use todo_api::{AppState, app, db::Database};
+use todo_client::{Client, CryptoKey, RegistrationPolicy};
+
+static MIGRATOR: Migrator = sqlx::migrate!("./migrations");
+
+/// A running server and the scratch directory holding its database, removed
+/// when the test finishes.
+pub struct TestServer {
+    pub addr: SocketAddr,
+    pub db: Arc<Database>,
+    dir: PathBuf,
+}
+
+impl TestServer {
+    pub async fn start(registration: RegistrationPolicy) -> Self {
+        let dir = std::env::temp_dir().join(format!(
+            "todo-api-server-{}-{}",
+            std::process::id(),
+            chrono::Utc::now().timestamp_nanos_opt().unwrap_or_default()
+        ));
+        std::fs::create_dir_all(&dir).unwrap();
+        let url = format!("sqlite://{}?mode=rwc", dir.join("todo.db").display());
+        let db = Arc::new(Database::open(&url).await.unwrap());
+        db.run_migrations(&MIGRATOR).await.unwrap();
+
+        let app = app::router(AppState::new(Arc::clone(&db)).with_registration(registration));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(
+                listener,
+                app.into_make_service_with_connect_info::<SocketAddr>(),
+            )
+            .await
+            .unwrap()
+        });
+
+        Self { addr, db, dir }
+    }
+
+    pub fn url(&self, path: &str) -> String {
+        format!("http://{}{path}", self.addr)
+    }
+
+    /// A client for the identity derived from `phrase`, after the handshake.
+    pub async fn client(&self, phrase: &str) -> Client {
+        let client = Client::new(
+            format!("http://{}", self.addr),
+            CryptoKey::from_recovery_phrase(phrase),
+        );
+        client.handshake().await.unwrap();
+        client
+    }
+
+    /// Like [`TestServer::client`], registered and with its public key
+    /// published.
+    pub async fn user(&self, phrase: &str) -> Client {
+        let client = self.client(phrase).await;
+        client.register(None).await.unwrap();
+        client.register_public_key().await.unwrap();
+        client
+    }
+}
+
+impl Drop for TestServer {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.dir);
+    }
+}
+
+/// The HTTP status a client call failed with.
+pub fn status(result: anyhow::Result<impl Sized>) -> Option<StatusCode> {
+    result
+        .err()?
+        .chain()
+        .find_map(|err| err.downcast_ref::<reqwest::Error>())
+        .and_then(reqwest::Error::status)
+}
@@ -0,0 +1,73 @@
+mod common;
+
+use common::{TestServer, status};
+use reqwest::StatusCode;
+use todo_client::{RegistrationPolicy, WorkspaceRole};
+
+#[tokio::test]
+async fn members_get_the_access_their_role_allows() {
+    let server = TestServer::start(RegistrationPolicy::Open).await;
+    let owner = server.user("owner").await;
+    let editor = server.user("editor").await;
+    let viewer = server.user("viewer").await;
+    let stranger = server.user("stranger").await;
+
+    let workspace_id = owner.create_workspace("groceries").await.unwrap();
+    let task_id = owner.create_task("buy milk", workspace_id).await.unwrap();
+    for (member, role) in [
+        (&editor, WorkspaceRole::Write),
+        (&viewer, WorkspaceRole::Read),
+    ] {
+        owner
+            .invite_member(workspace_id, member.user_id(), role)
+            .await
+            .unwrap();
+        member.accept_invite(workspace_id).await.unwrap();
+    }
+
+    // Both members can open the key wrapped to them and read the tasks.
+    for member in [&editor, &viewer] {
+        let tasks = member.get_tasks_for_workspace(workspace_id).await.unwrap();
+        assert_eq!(tasks.items[0].title, "buy milk");
+    }
+
+    editor.create_task("buy eggs", workspace_id).await.unwrap();
+    editor.toggle_task_completion(task_id).await.unwrap();
+    assert_eq!(
+        status(
+            editor
+                .invite_member(workspace_id, stranger.user_id(), WorkspaceRole::Read)
+                .await
+        ),
+        Some(StatusCode::FORBIDDEN)
+    );
+    assert_eq!(
+        status(editor.delete_workspace(workspace_id).await),
+        Some(StatusCode::FORBIDDEN)
+    );
+
+    assert_eq!(
+        status(viewer.create_task("buy bread", workspace_id).await),
+        Some(StatusCode::FORBIDDEN)
+    );
+    assert_eq!(
+        status(viewer.toggle_task_completion(task_id).await),
+        Some(StatusCode::FORBIDDEN)
+    );
+    assert_eq!(
+        status(viewer.delete_task(task_id).await),
+        Some(StatusCode::FORBIDDEN)
+    );
+
+    assert_eq!(
+        status(stranger.toggle_task_completion(task_id).await),
+        Some(StatusCode::NOT_FOUND)
+    );
+    assert_eq!(
+        status(stranger.delete_workspace(workspace_id).await),
+        Some(StatusCode::NOT_FOUND)
+    );
+    assert!(stranger.get_workspaces().await.unwrap().items.is_empty());
+
+    owner.delete_workspace(workspace_id).await.unwrap();
+}
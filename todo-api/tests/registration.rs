@@ -0,0 +1,91 @@
+mod common;
+
+use chrono::{Duration, Utc};
+use common::{TestServer, status};
+use reqwest::StatusCode;
+use todo_client::{RegistrationPolicy, RegistrationStatus};
+
+#[tokio::test]
+async fn invite_only_servers_turn_away_unknown_users() {
+    let server = TestServer::start(RegistrationPolicy::InviteOnly).await;
+    let stranger = server.client("stranger").await;
+
+    assert_eq!(
+        status(stranger.get_workspaces().await),
+        Some(StatusCode::FORBIDDEN)
+    );
+    assert_eq!(
+        status(stranger.create_workspace("home").await),
+        Some(StatusCode::FORBIDDEN)
+    );
+    assert_eq!(
+        stranger.register(None).await.unwrap(),
+        RegistrationStatus::InviteRequired
+    );
+    assert_eq!(
+        stranger.register(Some("made-up")).await.unwrap(),
+        RegistrationStatus::InvalidInvite
+    );
+}
+
+#[tokio::test]
+async fn invite_codes_register_exactly_one_user() {
+    let server = TestServer::start(RegistrationPolicy::InviteOnly).await;
+    server
+        .db
+        .create_registration_invite("welcome", None)
+        .await
+        .unwrap();
+
+    let first = server.client("first").await;
+    assert_eq!(
+        first.register(Some(" welcome ")).await.unwrap(),
+        RegistrationStatus::Registered
+    );
+    first.create_workspace("home").await.unwrap();
+    assert_eq!(
+        first.register(None).await.unwrap(),
+        RegistrationStatus::Registered
+    );
+
+    let second = server.client("second").await;
+    assert_eq!(
+        second.register(Some("welcome")).await.unwrap(),
+        RegistrationStatus::InvalidInvite
+    );
+    assert_eq!(
+        status(second.get_workspaces().await),
+        Some(StatusCode::FORBIDDEN)
+    );
+}
+
+#[tokio::test]
+async fn expired_invite_codes_are_refused() {
+    let server = TestServer::start(RegistrationPolicy::InviteOnly).await;
+    server
+        .db
+        .create_registration_invite("stale", Some(Utc::now() - Duration::hours(1)))
+        .await
+        .unwrap();
+
+    let user = server.client("late").await;
+    assert_eq!(
+        user.register(Some("stale")).await.unwrap(),
+        RegistrationStatus::InvalidInvite
+    );
+}
+
+#[tokio::test]
+async fn closed_servers_register_nobody() {
+    let server = TestServer::start(RegistrationPolicy::Closed).await;
+    let user = server.client("anyone").await;
+
+    assert_eq!(
+        user.register(None).await.unwrap(),
+        RegistrationStatus::Closed
+    );
+    assert_eq!(
+        status(user.get_workspaces().await),
+        Some(StatusCode::FORBIDDEN)
+    );
+}
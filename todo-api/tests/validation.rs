@@ -0,0 +1,90 @@
+mod common;
+
+use common::TestServer;
+use reqwest::StatusCode;
+use serde_json::{Value, json};
+use todo_api::validation::{DEFAULT_BODY_LIMIT, MAX_NAME_LEN};
+use todo_client::RegistrationPolicy;
+
+const USER_ID: &str = "0000000000000000000000000000000000000000000000000000000000000001";
+/// 24 bytes, base64 encoded.
+const NONCE: &str = "AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA";
+/// 16 bytes, as long as the authentication tag alone.
+const CIPHERTEXT: &str = "AAAAAAAAAAAAAAAAAAAAAA";
+
+/// Creates a workspace named by `name` and returns the status and error code.
+async fn create_workspace(server: &TestServer, name: Value) -> (StatusCode, Option<String>) {
+    let response = reqwest::Client::new()
+        .post(server.url("/api/v1/workspaces"))
+        .header("x-user-id", USER_ID)
+        .json(&json!({ "name": name }))
+        .send()
+        .await
+        .unwrap();
+    let status = response.status();
+    let body: Value = response.json().await.unwrap_or_default();
+    (status, body["code"].as_str().map(str::to_string))
+}
+
+fn field(ciphertext: &str, nonce: &str, version: u8) -> Value {
+    json!({ "ciphertext": ciphertext, "nonce": nonce, "version": version })
+}
+
+fn rejected(status: StatusCode, code: &str) -> (StatusCode, Option<String>) {
+    (status, Some(code.to_string()))
+}
+
+#[tokio::test]
+async fn well_formed_fields_are_stored() {
+    let server = TestServer::start(RegistrationPolicy::Open).await;
+
+    let (status, _) = create_workspace(&server, field(CIPHERTEXT, NONCE, 1)).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = create_workspace(&server, field(CIPHERTEXT, NONCE, 2)).await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn oversized_fields_and_bodies_are_rejected() {
+    let server = TestServer::start(RegistrationPolicy::Open).await;
+
+    let long = "A".repeat(MAX_NAME_LEN + 4);
+    assert_eq!(
+        create_workspace(&server, field(&long, NONCE, 1)).await,
+        rejected(StatusCode::PAYLOAD_TOO_LARGE, "field_too_large")
+    );
+
+    let huge = "A".repeat(DEFAULT_BODY_LIMIT + 4);
+    let (status, _) = create_workspace(&server, field(&huge, NONCE, 1)).await;
+    assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE);
+}
+
+#[tokio::test]
+async fn malformed_fields_are_unprocessable() {
+    let server = TestServer::start(RegistrationPolicy::Open).await;
+
+    for nonce in ["AAAA", "not base64!", ""] {
+        assert_eq!(
+            create_workspace(&server, field(CIPHERTEXT, nonce, 1)).await,
+            rejected(StatusCode::UNPROCESSABLE_ENTITY, "invalid_nonce"),
+            "nonce {nonce:?}"
+        );
+    }
+    for ciphertext in ["AAAA", "not base64!"] {
+        assert_eq!(
+            create_workspace(&server, field(ciphertext, NONCE, 1)).await,
+            rejected(StatusCode::UNPROCESSABLE_ENTITY, "invalid_ciphertext"),
+            "ciphertext {ciphertext:?}"
+        );
+    }
+    for version in [0, 3] {
+        assert_eq!(
+            create_workspace(&server, field(CIPHERTEXT, NONCE, version)).await,
+            rejected(
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "unsupported_encryption_version"
+            ),
+            "version {version}"
+        );
+    }
+}
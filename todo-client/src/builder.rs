@@ -0,0 +1,165 @@
+use std::{sync::Arc, time::Duration};
+
+use crate::{Client, CryptoKey, RetryPolicy, client::normalize_endpoint_url};
+
+const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+const DEFAULT_REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+/// Longest silence tolerated on the event stream; the server sends keep-alives
+/// well within this.
+const EVENTS_READ_TIMEOUT: Duration = Duration::from_secs(45);
+const DEFAULT_USER_AGENT: &str = concat!("todo-client/", env!("CARGO_PKG_VERSION"));
+
+/// Configures the HTTP transport of a [`Client`].
+pub struct ClientBuilder {
+    endpoint_url: String,
+    crypto: CryptoKey,
+    retry: RetryPolicy,
+    transport: Transport,
+}
+
+impl ClientBuilder {
+    pub(crate) fn new(endpoint_url: String, crypto: CryptoKey) -> Self {
+        Self {
+            endpoint_url,
+            crypto,
+            retry: RetryPolicy::default(),
+            transport: Transport::default(),
+        }
+    }
+
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.transport = self.transport.connect_timeout(timeout);
+        self
+    }
+
+    /// Limits the whole request, from connecting until the body is read.
+    pub fn request_timeout(mut self, timeout: Duration) -> Self {
+        self.transport = self.transport.request_timeout(timeout);
+        self
+    }
+
+    pub fn retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Trusts the PEM encoded certificates in addition to the built-in roots.
+    pub fn root_certificate_pem(mut self, pem: impl Into<Vec<u8>>) -> Self {
+        self.transport = self.transport.root_certificate_pem(pem);
+        self
+    }
+
+    /// Sends all requests through the proxy at `url`.
+    pub fn proxy(mut self, url: impl Into<String>) -> Self {
+        self.transport = self.transport.proxy(url);
+        self
+    }
+
+    /// Prefixes the default `todo-client/<version>` user agent.
+    pub fn user_agent(mut self, user_agent: &str) -> Self {
+        self.transport = self.transport.user_agent(user_agent);
+        self
+    }
+
+    /// Replaces all transport settings at once.
+    pub fn transport(mut self, transport: Transport) -> Self {
+        self.transport = transport;
+        self
+    }
+
+    pub fn build(self) -> anyhow::Result<Client> {
+        // The event stream stays open indefinitely, so it gets its own
+        // client that only limits the gaps between reads.
+        Ok(Client {
+            endpoint_url: normalize_endpoint_url(&self.endpoint_url),
+            http: self.transport.http()?,
+            events_http: self
+                .transport
+                .reqwest_builder()?
+                .read_timeout(EVENTS_READ_TIMEOUT)
+                .build()?,
+            retry: self.retry,
+            crypto: Arc::new(self.crypto),
+            workspace_keys: Arc::default(),
+            server: Arc::default(),
+        })
+    }
+}
+
+/// Timeouts, proxy, extra root certificates and user agent, for a
+/// [`Client`] or for requests made without one such as
+/// [`open_share_link`](crate::open_share_link).
+#[derive(Clone)]
+pub struct Transport {
+    connect_timeout: Duration,
+    request_timeout: Duration,
+    root_certificates: Vec<Vec<u8>>,
+    proxy: Option<String>,
+    user_agent: String,
+}
+
+impl Default for Transport {
+    fn default() -> Self {
+        Self {
+            connect_timeout: DEFAULT_CONNECT_TIMEOUT,
+            request_timeout: DEFAULT_REQUEST_TIMEOUT,
+            root_certificates: Vec::new(),
+            proxy: None,
+            user_agent: DEFAULT_USER_AGENT.to_string(),
+        }
+    }
+}
+
+impl Transport {
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = timeout;
+        self
+    }
+
+    /// Limits the whole request, from connecting until the body is read.
+    pub fn request_timeout(mut self, timeout: Duration) -> Self {
+        self.request_timeout = timeout;
+        self
+    }
+
+    /// Trusts the PEM encoded certificates in addition to the built-in roots.
+    pub fn root_certificate_pem(mut self, pem: impl Into<Vec<u8>>) -> Self {
+        self.root_certificates.push(pem.into());
+        self
+    }
+
+    /// Sends all requests through the proxy at `url`.
+    pub fn proxy(mut self, url: impl Into<String>) -> Self {
+        self.proxy = Some(url.into());
+        self
+    }
+
+    /// Prefixes the default `todo-client/<version>` user agent.
+    pub fn user_agent(mut self, user_agent: &str) -> Self {
+        self.user_agent = format!("{user_agent} {DEFAULT_USER_AGENT}");
+        self
+    }
+
+    /// A client for ordinary requests, limited by the request timeout.
+    pub(crate) fn http(&self) -> anyhow::Result<reqwest::Client> {
+        Ok(self
+            .reqwest_builder()?
+            .timeout(self.request_timeout)
+            .build()?)
+    }
+
+    fn reqwest_builder(&self) -> anyhow::Result<reqwest::ClientBuilder> {
+        let mut http = reqwest::Client::builder()
+            .connect_timeout(self.connect_timeout)
+            .user_agent(&self.user_agent);
+
+        for pem in &self.root_certificates {
+            http = http.tls_certs_merge(reqwest::Certificate::from_pem_bundle(pem)?);
+        }
+        if let Some(proxy) = &self.proxy {
+            http = http.proxy(reqwest::Proxy::all(proxy)?);
+        }
+
+        Ok(http)
+    }
+}
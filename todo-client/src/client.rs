@@ -1,85 +1,485 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, OnceLock, RwLock},
+};
+
+use chrono::{DateTime, Utc};
+use futures_util::{Stream, TryStreamExt, stream};
+
 use crate::{
-    CryptoKey, EncryptedField, EncryptedTask, EncryptedWorkspace, Task, Workspace, WorkspaceStats,
+    ClientBuilder, CryptoKey, Decrypted, EncryptedField, EncryptedTask, EncryptedWorkspace,
+    EncryptedWorkspaceInvite, LiveUpdate, Priority, Recurrence, RegistrationStatus, RetryPolicy,
+    ServerInfo, Task, TaskQuery, Workspace, WorkspaceInvite, WorkspaceMember, WorkspaceRole,
+    WorkspaceStats, WrappedKey, events,
+    protocol::{
+        API_VERSION, FEATURE_MEMBERS, FEATURE_PAGINATION, FEATURE_PRIORITY, FEATURE_RECURRENCE,
+        FEATURE_REGISTRATION, FEATURE_SHARES, FEATURE_TAGS, FEATURE_UNARCHIVE,
+        FEATURE_WORKSPACE_KEYS, NEXT_CURSOR_HEADER, versioned_path,
+    },
+    retry,
+    share::{ShareSnapshot, share_link},
+    tags,
 };
 use serde::{Deserialize, Serialize};
 
 const USER_ID_HEADER: &str = "x-user-id";
+const DEFAULT_PAGE_SIZE: u32 = 200;
 
 #[derive(Debug, Clone)]
 pub struct Client {
-    endpoint_url: String,
-    http: reqwest::Client,
-    crypto: CryptoKey,
+    pub(crate) endpoint_url: String,
+    pub(crate) http: reqwest::Client,
+    pub(crate) events_http: reqwest::Client,
+    pub(crate) retry: RetryPolicy,
+    pub(crate) crypto: Arc<CryptoKey>,
+    pub(crate) workspace_keys: Arc<RwLock<HashMap<i64, Arc<CryptoKey>>>>,
+    pub(crate) server: Arc<OnceLock<ServerInfo>>,
 }
 
 impl Client {
+    /// Creates a client with the default timeouts and retry policy.
     pub fn new(endpoint_url: String, crypto: CryptoKey) -> Self {
-        Self {
-            endpoint_url: normalize_endpoint_url(&endpoint_url),
-            http: reqwest::Client::new(),
-            crypto,
+        Self::builder(endpoint_url, crypto)
+            .build()
+            .expect("default HTTP client configuration is valid")
+    }
+
+    pub fn builder(endpoint_url: String, crypto: CryptoKey) -> ClientBuilder {
+        ClientBuilder::new(endpoint_url, crypto)
+    }
+
+    pub fn user_id(&self) -> &str {
+        self.crypto.user_id()
+    }
+
+    /// Fetches what the server supports and refuses servers that do not speak
+    /// our API version or accept our encryption format. Servers from before
+    /// versioning are used through the old routes with optional features off.
+    pub async fn handshake(&self) -> anyhow::Result<ServerInfo> {
+        if let Some(info) = self.server.get() {
+            return Ok(info.clone());
+        }
+
+        let response = self
+            .with_user(self.http.get(format!("{}/api/info", self.endpoint_url)))
+            .send()
+            .await?;
+        let info = if response.status() == reqwest::StatusCode::NOT_FOUND {
+            ServerInfo::legacy()
+        } else {
+            response.error_for_status()?.json::<ServerInfo>().await?
+        };
+
+        if !info.is_legacy() {
+            if !info.api_versions.contains(&API_VERSION) {
+                anyhow::bail!(
+                    "server {} supports API versions {:?}, but this client needs version {API_VERSION}",
+                    info.version,
+                    info.api_versions
+                );
+            }
+            let encryption_version = self.crypto.encryption_version();
+            if !info.encryption_versions.contains(&encryption_version) {
+                anyhow::bail!(
+                    "server {} does not accept encryption version {encryption_version}; disable padding or upgrade the server",
+                    info.version
+                );
+            }
+        }
+
+        Ok(self.server.get_or_init(|| info).clone())
+    }
+
+    /// Whether the server advertised `feature`. Assumed until the handshake
+    /// says otherwise.
+    pub fn supports(&self, feature: &str) -> bool {
+        self.server.get().is_none_or(|info| info.supports(feature))
+    }
+
+    fn require(&self, feature: &str) -> anyhow::Result<()> {
+        if !self.supports(feature) {
+            anyhow::bail!("the server does not support {feature}");
         }
+        Ok(())
     }
 
-    pub async fn get_workspaces(&self) -> anyhow::Result<Vec<Workspace>> {
+    /// Streams changes to our workspaces made by any client, including our
+    /// other devices. The stream never ends; dropped connections are retried
+    /// and followed by [`LiveUpdate::Resync`].
+    pub fn subscribe(&self) -> impl Stream<Item = LiveUpdate> + Send + use<> {
+        events::subscribe(self.clone())
+    }
+
+    /// Registers our user id, consuming `invite_code` if the server needs
+    /// one. Already registered users, and servers without explicit
+    /// registration, report [`RegistrationStatus::Registered`].
+    pub async fn register(&self, invite_code: Option<&str>) -> anyhow::Result<RegistrationStatus> {
+        if !self.supports(FEATURE_REGISTRATION) {
+            return Ok(RegistrationStatus::Registered);
+        }
+
         let response = self
-            .with_user(self.http.get(self.url("/api/workspaces")))
+            .with_user(self.http.post(self.url("/api/register")))
+            .json(&RegisterRequest { invite_code })
             .send()
-            .await?
-            .error_for_status()?
-            .json::<WorkspacesResponse>()
             .await?;
+        if response.status() != reqwest::StatusCode::FORBIDDEN {
+            response.error_for_status()?;
+            return Ok(RegistrationStatus::Registered);
+        }
+
+        let error = response.json::<ErrorBody>().await?;
+        match error.code.as_str() {
+            "invite_required" => Ok(RegistrationStatus::InviteRequired),
+            "invalid_invite" => Ok(RegistrationStatus::InvalidInvite),
+            "registration_closed" => Ok(RegistrationStatus::Closed),
+            _ => anyhow::bail!("registration failed: {}", error.error),
+        }
+    }
 
-        response
+    pub async fn register_public_key(&self) -> anyhow::Result<()> {
+        if !self.supports(FEATURE_WORKSPACE_KEYS) {
+            return Ok(());
+        }
+
+        self.with_user(self.http.put(self.url("/api/me/public-key")))
+            .json(&PublicKeyBody {
+                public_key: self.crypto.public_key(),
+            })
+            .send()
+            .await?
+            .error_for_status()?;
+
+        Ok(())
+    }
+
+    /// Lists workspaces, reporting the ones that fail to decrypt separately
+    /// instead of failing the whole listing.
+    pub async fn get_workspaces(&self) -> anyhow::Result<Decrypted<Workspace>> {
+        let response = self.fetch_workspaces().await?;
+
+        Ok(response
             .workspaces
             .into_iter()
-            .map(|workspace| self.decrypt_workspace(workspace))
-            .collect()
+            .map(|workspace| (workspace.id, self.decrypt_workspace(workspace)))
+            .collect())
     }
 
     pub async fn get_workspace_stats(&self) -> anyhow::Result<Vec<WorkspaceStats>> {
+        let response = self.fetch_workspaces().await?;
+
+        Ok(response.stats)
+    }
+
+    /// Lists the tasks of a workspace, reporting the ones that fail to
+    /// decrypt separately instead of failing the whole listing.
+    pub async fn get_tasks_for_workspace(
+        &self,
+        workspace_id: i64,
+    ) -> anyhow::Result<Decrypted<Task>> {
+        self.task_pages(workspace_id, TaskQuery::default())
+            .try_collect()
+            .await
+    }
+
+    /// Lists the tasks of every workspace we can decrypt, for views that
+    /// cut across workspaces. The server cannot filter encrypted fields, so
+    /// callers filter the decrypted tasks themselves.
+    pub async fn get_tasks_for_all_workspaces(&self) -> anyhow::Result<Decrypted<Task>> {
+        let mut tasks = Decrypted::default();
+        for workspace in self.get_workspaces().await?.items {
+            tasks.extend([self.get_tasks_for_workspace(workspace.id).await?]);
+        }
+        Ok(tasks)
+    }
+
+    /// Streams the tasks matching `query` one page at a time, following the
+    /// server's cursor until the listing is exhausted.
+    pub fn task_pages(
+        &self,
+        workspace_id: i64,
+        query: TaskQuery,
+    ) -> impl Stream<Item = anyhow::Result<Decrypted<Task>>> + Send + use<> {
+        let client = self.clone();
+        stream::try_unfold(Some(None), move |cursor| {
+            let client = client.clone();
+            let query = query.clone();
+            async move {
+                let Some(after) = cursor else {
+                    return Ok(None);
+                };
+
+                let key = client.workspace_key(workspace_id).await?;
+                let (tasks, next) = client.fetch_task_page(workspace_id, &query, after).await?;
+                let page = tasks
+                    .into_iter()
+                    .map(|task| (task.id, decrypt_task(&key, task)))
+                    .collect();
+
+                Ok(Some((page, next.map(Some))))
+            }
+        })
+    }
+
+    pub async fn create_workspace(&self, name: &str) -> anyhow::Result<i64> {
+        // Servers without per-workspace keys would drop the wrapped key, so
+        // fall back to encrypting under our own key.
+        if !self.supports(FEATURE_WORKSPACE_KEYS) {
+            let response = self
+                .with_user(self.http.post(self.url("/api/workspaces")))
+                .json(&CreateWorkspaceRequest {
+                    name: self.crypto.encrypt_field(name)?,
+                    wrapped_key: None,
+                })
+                .send()
+                .await?
+                .error_for_status()?
+                .json::<IdResponse>()
+                .await?;
+
+            return Ok(response.id);
+        }
+
+        let key = self.crypto.generate_workspace_key();
         let response = self
-            .with_user(self.http.get(self.url("/api/workspaces")))
+            .with_user(self.http.post(self.url("/api/workspaces")))
+            .json(&CreateWorkspaceRequest {
+                name: key.encrypt_field(name)?,
+                wrapped_key: Some(key.wrap_for(&self.crypto.public_key())?),
+            })
             .send()
             .await?
             .error_for_status()?
-            .json::<WorkspacesResponse>()
+            .json::<IdResponse>()
             .await?;
 
-        Ok(response.stats)
+        self.cache_workspace_key(response.id, Arc::new(key));
+        Ok(response.id)
     }
 
-    pub async fn get_tasks_for_workspace(&self, workspace_id: i64) -> anyhow::Result<Vec<Task>> {
-        let tasks = self
+    /// Publishes an encrypted read-only snapshot of a workspace and returns
+    /// a link carrying the snapshot key in its fragment.
+    pub async fn publish_share(
+        &self,
+        workspace_id: i64,
+        expires_at: Option<DateTime<Utc>>,
+    ) -> anyhow::Result<String> {
+        self.require(FEATURE_SHARES)?;
+        let workspace = self
+            .get_workspaces()
+            .await?
+            .items
+            .into_iter()
+            .find(|workspace| workspace.id == workspace_id)
+            .ok_or_else(|| anyhow::anyhow!("workspace {workspace_id} not found"))?;
+        let tasks = self.get_tasks_for_workspace(workspace_id).await?.items;
+        let snapshot = ShareSnapshot {
+            name: workspace.name,
+            tasks: tasks.iter().map(Into::into).collect(),
+        };
+
+        let key = self.crypto.generate_share_key();
+        let response = self
+            .with_user(self.http.post(self.url("/api/shares")))
+            .json(&CreateShareRequest {
+                workspace_id,
+                snapshot: key.encrypt_field(&serde_json::to_string(&snapshot)?)?,
+                expires_at,
+            })
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<ShareIdResponse>()
+            .await?;
+
+        Ok(share_link(&self.endpoint_url, &response.id, &key))
+    }
+
+    pub async fn delete_share(&self, share_id: &str) -> anyhow::Result<()> {
+        self.with_user(
+            self.http
+                .delete(self.url(&format!("/api/shares/{share_id}"))),
+        )
+        .send()
+        .await?
+        .error_for_status()?;
+
+        Ok(())
+    }
+
+    pub async fn get_members(&self, workspace_id: i64) -> anyhow::Result<Vec<WorkspaceMember>> {
+        let members = self
             .with_user(
                 self.http
-                    .get(self.url(&format!("/api/workspaces/{workspace_id}/tasks"))),
+                    .get(self.url(&format!("/api/workspaces/{workspace_id}/members"))),
             )
             .send()
             .await?
             .error_for_status()?
-            .json::<Vec<EncryptedTask>>()
+            .json::<Vec<WorkspaceMember>>()
             .await?;
 
-        tasks
-            .into_iter()
-            .map(|task| self.decrypt_task(task))
-            .collect()
+        Ok(members)
     }
 
-    pub async fn create_workspace(&self, name: &str) -> anyhow::Result<i64> {
-        let response = self
-            .with_user(self.http.post(self.url("/api/workspaces")))
-            .json(&CreateWorkspaceRequest {
-                name: self.crypto.encrypt_field(name)?,
-            })
+    /// Shares a workspace with another user by wrapping its key to their
+    /// public key. Workspaces created before per-workspace keys are re-keyed
+    /// first.
+    pub async fn invite_member(
+        &self,
+        workspace_id: i64,
+        user_id: &str,
+        role: WorkspaceRole,
+    ) -> anyhow::Result<()> {
+        self.require(FEATURE_MEMBERS)?;
+        let key = self.shareable_workspace_key(workspace_id).await?;
+        let public_key = self.get_public_key(user_id).await?;
+
+        self.with_user(
+            self.http
+                .post(self.url(&format!("/api/workspaces/{workspace_id}/members"))),
+        )
+        .json(&InviteMemberRequest {
+            user_id: user_id.to_string(),
+            role,
+            wrapped_key: key.wrap_for(&public_key)?,
+        })
+        .send()
+        .await?
+        .error_for_status()?;
+
+        Ok(())
+    }
+
+    pub async fn get_invites(&self) -> anyhow::Result<Vec<WorkspaceInvite>> {
+        let invites = self
+            .with_user(self.http.get(self.url("/api/invites")))
             .send()
             .await?
             .error_for_status()?
-            .json::<IdResponse>()
+            .json::<Vec<EncryptedWorkspaceInvite>>()
             .await?;
 
-        Ok(response.id)
+        invites
+            .into_iter()
+            .map(|invite| {
+                let key = self.crypto.unwrap_workspace_key(&invite.wrapped_key)?;
+                Ok(WorkspaceInvite {
+                    workspace_id: invite.workspace_id,
+                    name: key.decrypt_field(&invite.name)?,
+                    role: invite.role,
+                })
+            })
+            .collect()
+    }
+
+    pub async fn accept_invite(&self, workspace_id: i64) -> anyhow::Result<()> {
+        self.post_empty(&format!("/api/workspaces/{workspace_id}/accept"))
+            .await
+    }
+
+    pub async fn update_member_role(
+        &self,
+        workspace_id: i64,
+        user_id: &str,
+        role: WorkspaceRole,
+    ) -> anyhow::Result<()> {
+        self.with_user(
+            self.http
+                .patch(self.url(&format!("/api/workspaces/{workspace_id}/members/{user_id}"))),
+        )
+        .json(&UpdateMemberRequest { role })
+        .send()
+        .await?
+        .error_for_status()?;
+
+        Ok(())
+    }
+
+    /// Removes a member, or leaves the workspace when `user_id` is our own.
+    pub async fn remove_member(&self, workspace_id: i64, user_id: &str) -> anyhow::Result<()> {
+        self.with_user(
+            self.http
+                .delete(self.url(&format!("/api/workspaces/{workspace_id}/members/{user_id}"))),
+        )
+        .send()
+        .await?
+        .error_for_status()?;
+
+        if user_id == self.crypto.user_id() {
+            self.forget_workspace_key(workspace_id);
+        }
+
+        Ok(())
+    }
+
+    /// Re-encrypts a workspace created before per-workspace keys under a
+    /// fresh content key so it can be shared.
+    pub async fn migrate_workspace_key(&self, workspace_id: i64) -> anyhow::Result<()> {
+        let response = self.fetch_workspaces().await?;
+        let workspace = response
+            .workspaces
+            .into_iter()
+            .find(|workspace| workspace.id == workspace_id)
+            .ok_or_else(|| anyhow::anyhow!("workspace {workspace_id} not found"))?;
+        if workspace.wrapped_key.is_some() {
+            return Ok(());
+        }
+
+        let name = self.crypto.decrypt_field(&workspace.name)?;
+        let tasks = self.fetch_tasks(workspace_id, true).await?;
+        let key = self.crypto.generate_workspace_key();
+        let tasks = tasks
+            .into_iter()
+            .map(|task| {
+                let task = decrypt_task(&self.crypto, task)?;
+                Ok(RekeyedTask {
+                    id: task.id,
+                    title: key.encrypt_field(&task.title)?,
+                    description: task
+                        .description
+                        .as_deref()
+                        .map(|description| key.encrypt_field(description))
+                        .transpose()?,
+                    due_date: task
+                        .due_date
+                        .as_deref()
+                        .map(|due_date| key.encrypt_field(due_date))
+                        .transpose()?,
+                    recurrence: task
+                        .recurrence
+                        .as_deref()
+                        .map(|recurrence| key.encrypt_field(recurrence))
+                        .transpose()?,
+                    priority: task
+                        .priority
+                        .as_deref()
+                        .map(|priority| key.encrypt_field(priority))
+                        .transpose()?,
+                    tags: task
+                        .tags
+                        .as_deref()
+                        .map(|tags| key.encrypt_field(tags))
+                        .transpose()?,
+                })
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        self.with_user(
+            self.http
+                .put(self.url(&format!("/api/workspaces/{workspace_id}/key"))),
+        )
+        .json(&RekeyWorkspaceRequest {
+            name: key.encrypt_field(&name)?,
+            wrapped_key: key.wrap_for(&self.crypto.public_key())?,
+            tasks,
+        })
+        .send()
+        .await?
+        .error_for_status()?;
+
+        self.cache_workspace_key(workspace_id, Arc::new(key));
+        Ok(())
     }
 
     pub async fn create_task(&self, title: &str, workspace_id: i64) -> anyhow::Result<i64> {
@@ -101,18 +501,49 @@ impl Client {
             .await
     }
 
-    pub async fn archive_completed_tasks(&self, workspace_id: i64) -> anyhow::Result<()> {
-        self.post_empty(&format!("/api/workspaces/{workspace_id}/archive-completed"))
-            .await
+    /// Archives the completed tasks of a workspace and returns the ids that
+    /// were archived. Older servers do not report them, so the list is empty.
+    pub async fn archive_completed_tasks(&self, workspace_id: i64) -> anyhow::Result<Vec<i64>> {
+        let response = self
+            .with_user(
+                self.http
+                    .post(self.url(&format!("/api/workspaces/{workspace_id}/archive-completed"))),
+            )
+            .send()
+            .await?
+            .error_for_status()?;
+        if !self.supports(FEATURE_UNARCHIVE) {
+            return Ok(Vec::new());
+        }
+
+        Ok(response.json::<TaskIdsBody>().await?.task_ids)
+    }
+
+    /// Brings archived tasks back into a workspace's task list.
+    pub async fn unarchive_tasks(&self, workspace_id: i64, task_ids: &[i64]) -> anyhow::Result<()> {
+        self.require(FEATURE_UNARCHIVE)?;
+        self.with_user(
+            self.http
+                .post(self.url(&format!("/api/workspaces/{workspace_id}/unarchive"))),
+        )
+        .json(&TaskIdsBody {
+            task_ids: task_ids.to_vec(),
+        })
+        .send()
+        .await?
+        .error_for_status()?;
+
+        Ok(())
     }
 
     pub async fn update_workspace_name(&self, workspace_id: i64, name: &str) -> anyhow::Result<()> {
+        let key = self.workspace_key(workspace_id).await?;
         self.with_user(
             self.http
                 .patch(self.url(&format!("/api/workspaces/{workspace_id}"))),
         )
         .json(&UpdateWorkspaceRequest {
-            name: self.crypto.encrypt_field(name)?,
+            name: key.encrypt_field(name)?,
         })
         .send()
         .await?
@@ -121,13 +552,18 @@ impl Client {
         Ok(())
     }
 
-    pub async fn update_task_name(&self, task_id: i64, title: &str) -> anyhow::Result<()> {
+    pub async fn update_task_name(
+        &self,
+        workspace_id: i64,
+        task_id: i64,
+        title: &str,
+    ) -> anyhow::Result<()> {
+        let key = self.workspace_key(workspace_id).await?;
         self.update_task(
             task_id,
             &UpdateTaskRequest {
-                title: Some(self.crypto.encrypt_field(title)?),
-                due_date: None,
-                due_date_set: false,
+                title: Some(key.encrypt_field(title)?),
+                ..UpdateTaskRequest::default()
             },
         )
         .await
@@ -135,22 +571,183 @@ impl Client {
 
     pub async fn update_task_due_date(
         &self,
+        workspace_id: i64,
         task_id: i64,
         due_date: Option<&str>,
     ) -> anyhow::Result<()> {
+        let key = self.workspace_key(workspace_id).await?;
         self.update_task(
             task_id,
             &UpdateTaskRequest {
-                title: None,
                 due_date: due_date
-                    .map(|due_date| self.crypto.encrypt_field(due_date))
+                    .map(|due_date| key.encrypt_field(due_date))
                     .transpose()?,
                 due_date_set: true,
+                ..UpdateTaskRequest::default()
+            },
+        )
+        .await
+    }
+
+    /// Sets or clears a task's recurrence rule.
+    pub async fn update_task_recurrence(
+        &self,
+        workspace_id: i64,
+        task_id: i64,
+        recurrence: Option<&Recurrence>,
+    ) -> anyhow::Result<()> {
+        self.require(FEATURE_RECURRENCE)?;
+        let key = self.workspace_key(workspace_id).await?;
+        self.update_task(
+            task_id,
+            &UpdateTaskRequest {
+                recurrence: recurrence
+                    .map(|recurrence| key.encrypt_field(&recurrence.to_storage()))
+                    .transpose()?,
+                recurrence_set: true,
+                ..UpdateTaskRequest::default()
+            },
+        )
+        .await
+    }
+
+    /// Sets a task's priority; [`Priority::None`] clears it.
+    pub async fn update_task_priority(
+        &self,
+        workspace_id: i64,
+        task_id: i64,
+        priority: Priority,
+    ) -> anyhow::Result<()> {
+        self.require(FEATURE_PRIORITY)?;
+        let key = self.workspace_key(workspace_id).await?;
+        self.update_task(
+            task_id,
+            &UpdateTaskRequest {
+                priority: (priority != Priority::None)
+                    .then(|| key.encrypt_field(priority.as_str()))
+                    .transpose()?,
+                priority_set: true,
+                ..UpdateTaskRequest::default()
+            },
+        )
+        .await
+    }
+
+    /// Replaces a task's tags; an empty list clears them.
+    pub async fn update_task_tags(
+        &self,
+        workspace_id: i64,
+        task_id: i64,
+        tags: &[String],
+    ) -> anyhow::Result<()> {
+        self.require(FEATURE_TAGS)?;
+        let key = self.workspace_key(workspace_id).await?;
+        self.update_task(
+            task_id,
+            &UpdateTaskRequest {
+                tags: (!tags.is_empty())
+                    .then(|| key.encrypt_field(&tags::to_storage(tags)))
+                    .transpose()?,
+                tags_set: true,
+                ..UpdateTaskRequest::default()
             },
         )
         .await
     }
 
+    /// Creates the next occurrence of a recurring task that was just
+    /// completed and moves the rule onto it, so completing the old task
+    /// again does not repeat it. The new task keeps the priority and tags.
+    /// With `copy_subtasks` the subtasks among `tasks` are copied as well,
+    /// with their priorities and tags but not their due dates, which belong
+    /// to the finished occurrence. Returns the new task's id, or `None` for
+    /// tasks without a rule.
+    ///
+    /// The rule comes off the old task first; if a later step fails, the
+    /// new tasks are deleted and the rule is put back.
+    pub async fn spawn_next_occurrence(
+        &self,
+        task: &Task,
+        tasks: &[Task],
+    ) -> anyhow::Result<Option<i64>> {
+        let Some(recurrence) = task.recurrence() else {
+            return Ok(None);
+        };
+        self.update_task_recurrence(task.workspace_id, task.id, None)
+            .await?;
+
+        let mut created = None;
+        match self
+            .create_next_occurrence(task, tasks, &recurrence, &mut created)
+            .await
+        {
+            Ok(id) => Ok(Some(id)),
+            Err(err) => {
+                // Best effort: the error that stopped us is the one to report.
+                if let Some(id) = created {
+                    let _ = self.delete_task(id).await;
+                }
+                let _ = self
+                    .update_task_recurrence(task.workspace_id, task.id, Some(&recurrence))
+                    .await;
+                Err(err)
+            }
+        }
+    }
+
+    /// The steps of [`Client::spawn_next_occurrence`] after the rule is
+    /// taken off; `created` is set as soon as the new task exists.
+    async fn create_next_occurrence(
+        &self,
+        task: &Task,
+        tasks: &[Task],
+        recurrence: &Recurrence,
+        created: &mut Option<i64>,
+    ) -> anyhow::Result<i64> {
+        let workspace_id = task.workspace_id;
+        let id = self
+            .create_task_request(&task.title, workspace_id, task.parent_task_id)
+            .await?;
+        *created = Some(id);
+        if let Some(due) = recurrence.next_due(task.due(), Utc::now()) {
+            self.update_task_due_date(workspace_id, id, Some(&due.to_string()))
+                .await?;
+        }
+        self.update_task_recurrence(workspace_id, id, Some(recurrence))
+            .await?;
+        self.copy_priority_and_tags(task, id).await?;
+
+        if recurrence.copy_subtasks {
+            let mut pending = vec![(task.id, id)];
+            while let Some((original_parent, copy_parent)) = pending.pop() {
+                for child in tasks
+                    .iter()
+                    .filter(|child| child.parent_task_id == Some(original_parent))
+                {
+                    let copy = self
+                        .create_task_request(&child.title, workspace_id, Some(copy_parent))
+                        .await?;
+                    self.copy_priority_and_tags(child, copy).await?;
+                    pending.push((child.id, copy));
+                }
+            }
+        }
+
+        Ok(id)
+    }
+
+    async fn copy_priority_and_tags(&self, from: &Task, to: i64) -> anyhow::Result<()> {
+        if from.priority() != Priority::None {
+            self.update_task_priority(from.workspace_id, to, from.priority())
+                .await?;
+        }
+        if !from.tags().is_empty() {
+            self.update_task_tags(from.workspace_id, to, &from.tags())
+                .await?;
+        }
+        Ok(())
+    }
+
     pub async fn delete_workspace(&self, workspace_id: i64) -> anyhow::Result<()> {
         self.with_user(
             self.http
@@ -160,6 +757,7 @@ impl Client {
         .await?
         .error_for_status()?;
 
+        self.forget_workspace_key(workspace_id);
         Ok(())
     }
 
@@ -178,13 +776,14 @@ impl Client {
         workspace_id: i64,
         parent_task_id: Option<i64>,
     ) -> anyhow::Result<i64> {
+        let key = self.workspace_key(workspace_id).await?;
         let response = self
             .with_user(
                 self.http
                     .post(self.url(&format!("/api/workspaces/{workspace_id}/tasks"))),
             )
             .json(&CreateTaskRequest {
-                title: self.crypto.encrypt_field(title)?,
+                title: key.encrypt_field(title)?,
                 parent_task_id,
             })
             .send()
@@ -215,48 +814,271 @@ impl Client {
         Ok(())
     }
 
+    pub(crate) async fn connect_events(&self) -> anyhow::Result<reqwest::Response> {
+        let response = self
+            .events_http
+            .get(self.url("/api/events"))
+            .header(USER_ID_HEADER, self.crypto.user_id())
+            .header(reqwest::header::ACCEPT, "text/event-stream")
+            .send()
+            .await?
+            .error_for_status()?;
+
+        Ok(response)
+    }
+
     fn url(&self, path: &str) -> String {
-        format!("{}{}", self.endpoint_url, path)
+        if self.server.get().is_some_and(ServerInfo::is_legacy) {
+            return format!("{}{}", self.endpoint_url, path);
+        }
+        format!("{}{}", self.endpoint_url, versioned_path(path))
     }
 
-    fn with_user(&self, request: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
-        request.header(USER_ID_HEADER, self.crypto.user_id())
+    fn with_user(&self, request: reqwest::RequestBuilder) -> ApiRequest<'_> {
+        ApiRequest {
+            client: self,
+            builder: request.header(USER_ID_HEADER, self.crypto.user_id()),
+        }
+    }
+
+    async fn fetch_workspaces(&self) -> anyhow::Result<WorkspacesResponse> {
+        let response = self
+            .with_user(self.http.get(self.url("/api/workspaces")))
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<WorkspacesResponse>()
+            .await?;
+
+        Ok(response)
+    }
+
+    async fn fetch_tasks(
+        &self,
+        workspace_id: i64,
+        include_archived: bool,
+    ) -> anyhow::Result<Vec<EncryptedTask>> {
+        let query = TaskQuery {
+            include_archived,
+            ..TaskQuery::default()
+        };
+        let mut tasks = Vec::new();
+        let mut after = None;
+        loop {
+            let (page, next) = self.fetch_task_page(workspace_id, &query, after).await?;
+            tasks.extend(page);
+            match next {
+                Some(next) => after = Some(next),
+                None => return Ok(tasks),
+            }
+        }
+    }
+
+    /// Fetches one page of tasks and the cursor of the next one, if any.
+    /// Servers without pagination return everything in a single page.
+    async fn fetch_task_page(
+        &self,
+        workspace_id: i64,
+        query: &TaskQuery,
+        after: Option<i64>,
+    ) -> anyhow::Result<(Vec<EncryptedTask>, Option<i64>)> {
+        if query.has_filters() {
+            self.require(FEATURE_PAGINATION)?;
+        }
+
+        let mut url =
+            reqwest::Url::parse(&self.url(&format!("/api/workspaces/{workspace_id}/tasks")))?;
+        {
+            let mut pairs = url.query_pairs_mut();
+            pairs.append_pair("include_archived", &query.include_archived.to_string());
+            pairs.append_pair(
+                "limit",
+                &query.page_size.unwrap_or(DEFAULT_PAGE_SIZE).to_string(),
+            );
+            if let Some(after) = after {
+                pairs.append_pair("after", &after.to_string());
+            }
+            if let Some(archived) = query.archived {
+                pairs.append_pair("archived", &archived.to_string());
+            }
+            if let Some(completed) = query.completed {
+                pairs.append_pair("completed", &completed.to_string());
+            }
+            if let Some(parent_task_id) = query.parent_task_id {
+                pairs.append_pair("parent_task_id", &parent_task_id.to_string());
+            }
+            for (name, bound) in [
+                ("created_after", query.created_after),
+                ("created_before", query.created_before),
+                ("updated_after", query.updated_after),
+                ("updated_before", query.updated_before),
+            ] {
+                if let Some(bound) = bound {
+                    pairs.append_pair(name, &bound.to_rfc3339());
+                }
+            }
+        }
+
+        let response = self
+            .with_user(self.http.get(url))
+            .send()
+            .await?
+            .error_for_status()?;
+        let next = response
+            .headers()
+            .get(NEXT_CURSOR_HEADER)
+            .map(|value| -> anyhow::Result<i64> { Ok(value.to_str()?.parse()?) })
+            .transpose()?;
+        let tasks = response.json::<Vec<EncryptedTask>>().await?;
+
+        Ok((tasks, next))
+    }
+
+    async fn get_public_key(&self, user_id: &str) -> anyhow::Result<String> {
+        let response = self
+            .with_user(
+                self.http
+                    .get(self.url(&format!("/api/users/{user_id}/public-key"))),
+            )
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<PublicKeyBody>()
+            .await?;
+
+        Ok(response.public_key)
+    }
+
+    /// Looks up the content key for a workspace, refreshing the workspace
+    /// list when it has not been seen yet. Keys of other workspaces that
+    /// cannot be opened are skipped, so they only fail their own lookups.
+    async fn workspace_key(&self, workspace_id: i64) -> anyhow::Result<Arc<CryptoKey>> {
+        if let Some(key) = self.cached_workspace_key(workspace_id) {
+            return Ok(key);
+        }
+
+        let response = self.fetch_workspaces().await?;
+        for workspace in &response.workspaces {
+            match self.open_workspace_key(workspace) {
+                Ok(key) => self.cache_workspace_key(workspace.id, key),
+                Err(err) if workspace.id == workspace_id => {
+                    return Err(err.context(format!(
+                        "could not open the key for workspace {workspace_id}"
+                    )));
+                }
+                Err(_) => {}
+            }
+        }
+
+        self.cached_workspace_key(workspace_id)
+            .ok_or_else(|| anyhow::anyhow!("no key for workspace {workspace_id}"))
+    }
+
+    async fn shareable_workspace_key(&self, workspace_id: i64) -> anyhow::Result<Arc<CryptoKey>> {
+        self.migrate_workspace_key(workspace_id).await?;
+        self.workspace_key(workspace_id).await
+    }
+
+    fn open_workspace_key(&self, workspace: &EncryptedWorkspace) -> anyhow::Result<Arc<CryptoKey>> {
+        match &workspace.wrapped_key {
+            Some(wrapped_key) => Ok(Arc::new(self.crypto.unwrap_workspace_key(wrapped_key)?)),
+            None => Ok(Arc::clone(&self.crypto)),
+        }
+    }
+
+    fn cached_workspace_key(&self, workspace_id: i64) -> Option<Arc<CryptoKey>> {
+        self.workspace_keys
+            .read()
+            .ok()
+            .and_then(|keys| keys.get(&workspace_id).cloned())
+    }
+
+    fn cache_workspace_key(&self, workspace_id: i64, key: Arc<CryptoKey>) {
+        if let Ok(mut keys) = self.workspace_keys.write() {
+            keys.insert(workspace_id, key);
+        }
+    }
+
+    fn forget_workspace_key(&self, workspace_id: i64) {
+        if let Ok(mut keys) = self.workspace_keys.write() {
+            keys.remove(&workspace_id);
+        }
     }
 
     fn decrypt_workspace(&self, workspace: EncryptedWorkspace) -> anyhow::Result<Workspace> {
+        let key = self.open_workspace_key(&workspace)?;
+        let name = key.decrypt_field(&workspace.name)?;
+        self.cache_workspace_key(workspace.id, key);
+
         Ok(Workspace {
             id: workspace.id,
-            name: self.crypto.decrypt_field(&workspace.name)?,
+            name,
+            role: workspace.role,
             created_at: workspace.created_at,
             updated_at: workspace.updated_at,
         })
     }
+}
 
-    fn decrypt_task(&self, task: EncryptedTask) -> anyhow::Result<Task> {
-        Ok(Task {
-            id: task.id,
-            title: self.crypto.decrypt_field(&task.title)?,
-            description: task
-                .description
-                .as_ref()
-                .map(|description| self.crypto.decrypt_field(description))
-                .transpose()?,
-            completed: task.completed,
-            archived: task.archived,
-            due_date: task
-                .due_date
-                .as_ref()
-                .map(|due_date| self.crypto.decrypt_field(due_date))
-                .transpose()?,
-            workspace_id: task.workspace_id,
-            parent_task_id: task.parent_task_id,
-            created_at: task.created_at,
-            updated_at: task.updated_at,
-        })
+fn decrypt_task(key: &CryptoKey, task: EncryptedTask) -> anyhow::Result<Task> {
+    Ok(Task {
+        id: task.id,
+        title: key.decrypt_field(&task.title)?,
+        description: task
+            .description
+            .as_ref()
+            .map(|description| key.decrypt_field(description))
+            .transpose()?,
+        completed: task.completed,
+        archived: task.archived,
+        due_date: task
+            .due_date
+            .as_ref()
+            .map(|due_date| key.decrypt_field(due_date))
+            .transpose()?,
+        recurrence: task
+            .recurrence
+            .as_ref()
+            .map(|recurrence| key.decrypt_field(recurrence))
+            .transpose()?,
+        priority: task
+            .priority
+            .as_ref()
+            .map(|priority| key.decrypt_field(priority))
+            .transpose()?,
+        tags: task
+            .tags
+            .as_ref()
+            .map(|tags| key.decrypt_field(tags))
+            .transpose()?,
+        workspace_id: task.workspace_id,
+        parent_task_id: task.parent_task_id,
+        created_at: task.created_at,
+        updated_at: task.updated_at,
+    })
+}
+
+/// A request to the API that is sent through the client's retry policy.
+struct ApiRequest<'a> {
+    client: &'a Client,
+    builder: reqwest::RequestBuilder,
+}
+
+impl ApiRequest<'_> {
+    fn json<T: Serialize + ?Sized>(self, body: &T) -> Self {
+        Self {
+            builder: self.builder.json(body),
+            ..self
+        }
+    }
+
+    async fn send(self) -> reqwest::Result<reqwest::Response> {
+        let request = self.builder.build()?;
+        retry::send(&self.client.http, &self.client.retry, request).await
     }
 }
 
-fn normalize_endpoint_url(endpoint_url: &str) -> String {
+pub(crate) fn normalize_endpoint_url(endpoint_url: &str) -> String {
     let endpoint_url = endpoint_url.trim().trim_end_matches('/');
     if endpoint_url.starts_with("http://") || endpoint_url.starts_with("https://") {
         endpoint_url.to_string()
@@ -276,9 +1098,75 @@ struct IdResponse {
     id: i64,
 }
 
+#[derive(Serialize, Deserialize)]
+struct TaskIdsBody {
+    task_ids: Vec<i64>,
+}
+
+#[derive(Deserialize)]
+struct ShareIdResponse {
+    id: String,
+}
+
+#[derive(Serialize)]
+struct CreateShareRequest {
+    workspace_id: i64,
+    snapshot: EncryptedField,
+    expires_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize)]
+struct RegisterRequest<'a> {
+    invite_code: Option<&'a str>,
+}
+
+/// The JSON body of an API error response.
+#[derive(Deserialize)]
+struct ErrorBody {
+    #[serde(default)]
+    code: String,
+    error: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct PublicKeyBody {
+    public_key: String,
+}
+
 #[derive(Serialize)]
 struct CreateWorkspaceRequest {
     name: EncryptedField,
+    wrapped_key: Option<WrappedKey>,
+}
+
+#[derive(Serialize)]
+struct InviteMemberRequest {
+    user_id: String,
+    role: WorkspaceRole,
+    wrapped_key: WrappedKey,
+}
+
+#[derive(Serialize)]
+struct UpdateMemberRequest {
+    role: WorkspaceRole,
+}
+
+#[derive(Serialize)]
+struct RekeyWorkspaceRequest {
+    name: EncryptedField,
+    wrapped_key: WrappedKey,
+    tasks: Vec<RekeyedTask>,
+}
+
+#[derive(Serialize)]
+struct RekeyedTask {
+    id: i64,
+    title: EncryptedField,
+    description: Option<EncryptedField>,
+    due_date: Option<EncryptedField>,
+    recurrence: Option<EncryptedField>,
+    priority: Option<EncryptedField>,
+    tags: Option<EncryptedField>,
 }
 
 #[derive(Serialize)]
@@ -292,9 +1180,15 @@ struct CreateTaskRequest {
     parent_task_id: Option<i64>,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, Default)]
 struct UpdateTaskRequest {
     title: Option<EncryptedField>,
     due_date: Option<EncryptedField>,
     due_date_set: bool,
+    recurrence: Option<EncryptedField>,
+    recurrence_set: bool,
+    priority: Option<EncryptedField>,
+    priority_set: bool,
+    tags: Option<EncryptedField>,
+    tags_set: bool,
 }
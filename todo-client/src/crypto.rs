@@ -1,86 +1,563 @@
+use std::{fmt, sync::Arc};
+
 use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
 use bip39::{Language, Mnemonic};
 use chacha20poly1305::{
     XChaCha20Poly1305, XNonce,
     aead::{Aead, KeyInit, OsRng, rand_core::RngCore},
 };
+use secrecy::SecretString;
+use x25519_dalek::{PublicKey, StaticSecret};
+use zeroize::Zeroizing;
 
-use crate::EncryptedField;
+use crate::{EncryptedField, WrappedKey};
 
 const ENCRYPTION_VERSION: u8 = 1;
+const KEY_WRAP_VERSION: u8 = 1;
+const KEY_WRAP_CONTEXT: &str = "todo workspace key wrap v1";
+const PADDED_ENCRYPTION_VERSION: u8 = 2;
 const KEY_LEN: usize = 32;
 const NONCE_LEN: usize = 24;
+const PADDING_HEADER_LEN: usize = 5;
+const MIN_PADDED_LEN: usize = 32;
+
+/// Recovery phrase lengths accepted for new and existing identities.
+pub const SUPPORTED_WORD_COUNTS: [usize; 2] = [12, 24];
+/// Versions of [`EncryptedField`] this crate can read and write.
+pub const SUPPORTED_ENCRYPTION_VERSIONS: [u8; 2] = [ENCRYPTION_VERSION, PADDED_ENCRYPTION_VERSION];
+/// Versions of [`WrappedKey`] this crate can read and write.
+pub const SUPPORTED_KEY_WRAP_VERSIONS: [u8; 1] = [KEY_WRAP_VERSION];
+
+type SecretKey = Zeroizing<[u8; KEY_LEN]>;
+
+/// How plaintexts are padded before encryption so the ciphertext length only
+/// reveals a size bucket instead of the exact plaintext length.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Padding {
+    #[default]
+    None,
+    PowerOfTwo,
+    Block(usize),
+}
+
+impl Padding {
+    /// The field version written when encrypting with this padding.
+    pub fn encryption_version(self) -> u8 {
+        match self {
+            Padding::None => ENCRYPTION_VERSION,
+            _ => PADDED_ENCRYPTION_VERSION,
+        }
+    }
+
+    fn scheme_id(self) -> u8 {
+        match self {
+            Padding::None => 0,
+            Padding::PowerOfTwo => 1,
+            Padding::Block(_) => 2,
+        }
+    }
+
+    /// The padding a field was written with. Block sizes are not recorded,
+    /// and reading a field does not need them.
+    fn from_scheme_id(id: u8) -> Option<Self> {
+        match id {
+            0 => Some(Padding::None),
+            1 => Some(Padding::PowerOfTwo),
+            2 => Some(Padding::Block(0)),
+            _ => None,
+        }
+    }
+
+    fn padded_len(self, len: usize) -> usize {
+        match self {
+            Padding::None => len,
+            Padding::PowerOfTwo => len.max(MIN_PADDED_LEN).next_power_of_two(),
+            Padding::Block(size) => {
+                let size = size.max(1);
+                len.div_ceil(size).max(1) * size
+            }
+        }
+    }
+}
 
-#[derive(Debug, Clone)]
+/// Key material derived from a recovery phrase, or a workspace content key
+/// acting on behalf of the same user. Secrets are zeroized on drop and never
+/// shown by `Debug`; the X25519 identity is shared between derived keys rather
+/// than copied.
 pub struct CryptoKey {
-    key: [u8; KEY_LEN],
+    key: SecretKey,
+    identity: Arc<SecretKey>,
     user_id: String,
+    padding: Padding,
+}
+
+impl fmt::Debug for CryptoKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CryptoKey")
+            .field("key", &"[redacted]")
+            .field("user_id", &self.user_id)
+            .field("padding", &self.padding)
+            .finish_non_exhaustive()
+    }
 }
 
 impl CryptoKey {
     pub fn from_recovery_phrase(phrase: &str) -> Self {
         let normalized = normalize_recovery_phrase(phrase);
-        let key = *blake3::hash(format!("encryption v1:{normalized}").as_bytes()).as_bytes();
-        let user_id = blake3::hash(format!("user id v1:{normalized}").as_bytes())
+        Self::from_key_material("v1", normalized.as_bytes())
+    }
+
+    /// Derives keys from the BIP39 seed of `phrase` and `passphrase`. The
+    /// passphrase acts as a second factor: each passphrase yields a separate
+    /// identity. An empty passphrase keeps the original phrase-only keys so
+    /// existing users are unaffected.
+    pub fn from_recovery_phrase_and_passphrase(
+        phrase: &str,
+        passphrase: &str,
+    ) -> anyhow::Result<Self> {
+        if passphrase.is_empty() {
+            return Ok(Self::from_recovery_phrase(phrase));
+        }
+
+        let phrase = Zeroizing::new(phrase.to_lowercase());
+        let mnemonic = Mnemonic::parse_in(Language::English, phrase.as_str())?;
+        let seed = Zeroizing::new(mnemonic.to_seed(passphrase));
+        Ok(Self::from_key_material("v2", seed.as_ref()))
+    }
+
+    fn from_key_material(version: &str, material: &[u8]) -> Self {
+        let key = derive_secret(&format!("encryption {version}:"), material);
+        let identity = derive_secret(&format!("x25519 {version}:"), material);
+        let user_id = blake3::Hasher::new()
+            .update(format!("user id {version}:").as_bytes())
+            .update(material)
+            .finalize()
             .to_hex()
             .to_string();
 
-        Self { key, user_id }
+        Self {
+            key,
+            identity: Arc::new(identity),
+            user_id,
+            padding: Padding::None,
+        }
+    }
+
+    /// Creates a fresh random key for a share snapshot, padded the same way as
+    /// this key's fields.
+    pub fn generate_share_key(&self) -> ShareKey {
+        ShareKey {
+            key: random_key(),
+            padding: self.padding,
+        }
+    }
+
+    /// Creates a fresh random content key for a workspace, acting on behalf
+    /// of the same user.
+    pub fn generate_workspace_key(&self) -> Self {
+        self.with_key(random_key())
+    }
+
+    pub fn with_padding(mut self, padding: Padding) -> Self {
+        self.padding = padding;
+        self
+    }
+
+    pub fn encryption_version(&self) -> u8 {
+        self.padding.encryption_version()
     }
 
     pub fn user_id(&self) -> &str {
         &self.user_id
     }
 
-    pub fn encrypt_field(&self, plaintext: &str) -> anyhow::Result<EncryptedField> {
-        let cipher = XChaCha20Poly1305::new((&self.key).into());
+    /// The X25519 public key other users wrap workspace keys to.
+    pub fn public_key(&self) -> String {
+        let public_key = PublicKey::from(&StaticSecret::from(**self.identity));
+        URL_SAFE_NO_PAD.encode(public_key.as_bytes())
+    }
+
+    /// Seals this key's content key to `recipient_public_key`.
+    pub fn wrap_for(&self, recipient_public_key: &str) -> anyhow::Result<WrappedKey> {
+        let recipient = decode_public_key(recipient_public_key)?;
+        let ephemeral = StaticSecret::random_from_rng(OsRng);
+        let ephemeral_public = PublicKey::from(&ephemeral);
+        let shared = ephemeral.diffie_hellman(&recipient);
+        let wrapping_key = derive_wrapping_key(shared.as_bytes(), &ephemeral_public, &recipient);
+
+        let cipher = XChaCha20Poly1305::new(wrapping_key.as_ref().into());
         let mut nonce = [0; NONCE_LEN];
         OsRng.fill_bytes(&mut nonce);
-
         let ciphertext = cipher
-            .encrypt(XNonce::from_slice(&nonce), plaintext.as_bytes())
-            .map_err(|err| anyhow::anyhow!("encrypt field: {err}"))?;
+            .encrypt(XNonce::from_slice(&nonce), self.key.as_ref())
+            .map_err(|err| anyhow::anyhow!("wrap key: {err}"))?;
 
-        Ok(EncryptedField {
+        Ok(WrappedKey {
+            ephemeral_public_key: URL_SAFE_NO_PAD.encode(ephemeral_public.as_bytes()),
             ciphertext: URL_SAFE_NO_PAD.encode(ciphertext),
             nonce: URL_SAFE_NO_PAD.encode(nonce),
-            version: ENCRYPTION_VERSION,
+            version: KEY_WRAP_VERSION,
         })
     }
 
+    /// Opens a workspace key that was wrapped to this user's public key.
+    pub fn unwrap_workspace_key(&self, wrapped: &WrappedKey) -> anyhow::Result<Self> {
+        if wrapped.version != KEY_WRAP_VERSION {
+            anyhow::bail!("unsupported wrapped key version {}", wrapped.version);
+        }
+
+        let identity = StaticSecret::from(**self.identity);
+        let ephemeral_public = decode_public_key(&wrapped.ephemeral_public_key)?;
+        let shared = identity.diffie_hellman(&ephemeral_public);
+        let wrapping_key = derive_wrapping_key(
+            shared.as_bytes(),
+            &ephemeral_public,
+            &PublicKey::from(&identity),
+        );
+
+        let nonce = URL_SAFE_NO_PAD.decode(wrapped.nonce.as_bytes())?;
+        if nonce.len() != NONCE_LEN {
+            anyhow::bail!("wrapped key nonce must be {NONCE_LEN} bytes");
+        }
+        let ciphertext = URL_SAFE_NO_PAD.decode(wrapped.ciphertext.as_bytes())?;
+        let cipher = XChaCha20Poly1305::new(wrapping_key.as_ref().into());
+        let key = Zeroizing::new(
+            cipher
+                .decrypt(XNonce::from_slice(&nonce), ciphertext.as_ref())
+                .map_err(|err| anyhow::anyhow!("unwrap key: {err}"))?,
+        );
+
+        Ok(self.with_key(secret_key_from_slice(&key, "wrapped key")?))
+    }
+
+    pub fn encrypt_field(&self, plaintext: &str) -> anyhow::Result<EncryptedField> {
+        encrypt_with_key(&self.key, self.padding, plaintext)
+    }
+
     pub fn decrypt_field(&self, field: &EncryptedField) -> anyhow::Result<String> {
-        if field.version != ENCRYPTION_VERSION {
-            anyhow::bail!("unsupported encrypted field version {}", field.version);
+        decrypt_with_key(&self.key, field)
+    }
+
+    fn with_key(&self, key: SecretKey) -> Self {
+        Self {
+            key,
+            identity: Arc::clone(&self.identity),
+            user_id: self.user_id.clone(),
+            padding: self.padding,
         }
+    }
+}
 
-        let nonce = URL_SAFE_NO_PAD.decode(field.nonce.as_bytes())?;
-        let ciphertext = URL_SAFE_NO_PAD.decode(field.ciphertext.as_bytes())?;
-        let cipher = XChaCha20Poly1305::new((&self.key).into());
+/// A standalone content key for a published share snapshot. It travels in the
+/// share link's URL fragment and is never sent to the server.
+pub struct ShareKey {
+    key: SecretKey,
+    padding: Padding,
+}
+
+impl fmt::Debug for ShareKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ShareKey")
+            .field("key", &"[redacted]")
+            .finish_non_exhaustive()
+    }
+}
 
-        let plaintext = cipher
-            .decrypt(XNonce::from_slice(&nonce), ciphertext.as_ref())
-            .map_err(|err| anyhow::anyhow!("decrypt field: {err}"))?;
+impl ShareKey {
+    pub fn decode(encoded: &str) -> anyhow::Result<Self> {
+        let key = Zeroizing::new(URL_SAFE_NO_PAD.decode(encoded.as_bytes())?);
 
-        String::from_utf8(plaintext).map_err(Into::into)
+        Ok(Self {
+            key: secret_key_from_slice(&key, "share key")?,
+            padding: Padding::None,
+        })
+    }
+
+    pub fn encode(&self) -> String {
+        URL_SAFE_NO_PAD.encode(self.key.as_ref())
     }
+
+    pub fn encrypt_field(&self, plaintext: &str) -> anyhow::Result<EncryptedField> {
+        encrypt_with_key(&self.key, self.padding, plaintext)
+    }
+
+    pub fn decrypt_field(&self, field: &EncryptedField) -> anyhow::Result<String> {
+        decrypt_with_key(&self.key, field)
+    }
+}
+
+fn encrypt_with_key(
+    key: &[u8; KEY_LEN],
+    padding: Padding,
+    plaintext: &str,
+) -> anyhow::Result<EncryptedField> {
+    let cipher = XChaCha20Poly1305::new(key.into());
+    let mut nonce = [0; NONCE_LEN];
+    OsRng.fill_bytes(&mut nonce);
+
+    let (version, plaintext) = match padding {
+        Padding::None => (
+            ENCRYPTION_VERSION,
+            Zeroizing::new(plaintext.as_bytes().to_vec()),
+        ),
+        padding => (
+            PADDED_ENCRYPTION_VERSION,
+            Zeroizing::new(pad_plaintext(plaintext.as_bytes(), padding)?),
+        ),
+    };
+
+    let ciphertext = cipher
+        .encrypt(XNonce::from_slice(&nonce), plaintext.as_slice())
+        .map_err(|err| anyhow::anyhow!("encrypt field: {err}"))?;
+
+    Ok(EncryptedField {
+        ciphertext: URL_SAFE_NO_PAD.encode(ciphertext),
+        nonce: URL_SAFE_NO_PAD.encode(nonce),
+        version,
+    })
+}
+
+fn decrypt_with_key(key: &[u8; KEY_LEN], field: &EncryptedField) -> anyhow::Result<String> {
+    if field.version != ENCRYPTION_VERSION && field.version != PADDED_ENCRYPTION_VERSION {
+        anyhow::bail!("unsupported encrypted field version {}", field.version);
+    }
+
+    let nonce = URL_SAFE_NO_PAD.decode(field.nonce.as_bytes())?;
+    if nonce.len() != NONCE_LEN {
+        anyhow::bail!("encrypted field nonce must be {NONCE_LEN} bytes");
+    }
+    let ciphertext = URL_SAFE_NO_PAD.decode(field.ciphertext.as_bytes())?;
+    let cipher = XChaCha20Poly1305::new(key.into());
+
+    let plaintext = cipher
+        .decrypt(XNonce::from_slice(&nonce), ciphertext.as_ref())
+        .map_err(|err| anyhow::anyhow!("decrypt field: {err}"))?;
+    let plaintext = if field.version == PADDED_ENCRYPTION_VERSION {
+        unpad_plaintext(plaintext)?
+    } else {
+        plaintext
+    };
+
+    String::from_utf8(plaintext).map_err(Into::into)
+}
+
+fn derive_secret(label: &str, material: &[u8]) -> SecretKey {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(label.as_bytes());
+    hasher.update(material);
+    Zeroizing::new(*hasher.finalize().as_bytes())
+}
+
+fn random_key() -> SecretKey {
+    let mut key = Zeroizing::new([0; KEY_LEN]);
+    OsRng.fill_bytes(key.as_mut());
+    key
+}
+
+fn secret_key_from_slice(bytes: &[u8], what: &str) -> anyhow::Result<SecretKey> {
+    if bytes.len() != KEY_LEN {
+        anyhow::bail!("{what} must be {KEY_LEN} bytes");
+    }
+    let mut key = Zeroizing::new([0; KEY_LEN]);
+    key.copy_from_slice(bytes);
+    Ok(key)
+}
+
+fn decode_public_key(value: &str) -> anyhow::Result<PublicKey> {
+    let bytes: [u8; KEY_LEN] = URL_SAFE_NO_PAD
+        .decode(value.as_bytes())?
+        .try_into()
+        .map_err(|_| anyhow::anyhow!("public key must be {KEY_LEN} bytes"))?;
+    Ok(PublicKey::from(bytes))
+}
+
+fn derive_wrapping_key(
+    shared_secret: &[u8; KEY_LEN],
+    ephemeral_public: &PublicKey,
+    recipient_public: &PublicKey,
+) -> SecretKey {
+    let mut material = Zeroizing::new(Vec::with_capacity(KEY_LEN * 3));
+    material.extend_from_slice(shared_secret);
+    material.extend_from_slice(ephemeral_public.as_bytes());
+    material.extend_from_slice(recipient_public.as_bytes());
+    Zeroizing::new(blake3::derive_key(KEY_WRAP_CONTEXT, &material))
+}
+
+/// Frames `plaintext` as `[scheme][len: u32 le][plaintext][zeros]`, padded to
+/// the bucket chosen by `padding`.
+fn pad_plaintext(plaintext: &[u8], padding: Padding) -> anyhow::Result<Vec<u8>> {
+    let len =
+        u32::try_from(plaintext.len()).map_err(|_| anyhow::anyhow!("field is too large to pad"))?;
+    let framed_len = PADDING_HEADER_LEN + plaintext.len();
+
+    let mut framed = Vec::with_capacity(padding.padded_len(framed_len));
+    framed.push(padding.scheme_id());
+    framed.extend_from_slice(&len.to_le_bytes());
+    framed.extend_from_slice(plaintext);
+    framed.resize(padding.padded_len(framed_len), 0);
+
+    Ok(framed)
 }
 
-pub fn generate_recovery_phrase() -> String {
-    Mnemonic::generate_in(Language::English, 12)
-        .expect("word count is valid")
+fn unpad_plaintext(mut framed: Vec<u8>) -> anyhow::Result<Vec<u8>> {
+    let Some((&scheme, rest)) = framed.split_first() else {
+        anyhow::bail!("padded field is missing its header");
+    };
+    if Padding::from_scheme_id(scheme).is_none() {
+        anyhow::bail!("unsupported padding scheme {scheme}");
+    }
+    let Some(len_bytes) = rest.get(..4) else {
+        anyhow::bail!("padded field is missing its header");
+    };
+    let len = u32::from_le_bytes(len_bytes.try_into()?) as usize;
+    if PADDING_HEADER_LEN + len > framed.len() {
+        anyhow::bail!("padded field length is out of range");
+    }
+
+    framed.truncate(PADDING_HEADER_LEN + len);
+    framed.drain(..PADDING_HEADER_LEN);
+    Ok(framed)
+}
+
+pub fn generate_recovery_phrase(word_count: usize) -> anyhow::Result<SecretString> {
+    if !SUPPORTED_WORD_COUNTS.contains(&word_count) {
+        anyhow::bail!("recovery phrases must have 12 or 24 words, not {word_count}");
+    }
+
+    Ok(Mnemonic::generate_in(Language::English, word_count)?
         .to_string()
+        .into())
+}
+
+pub fn normalize_phrase_for_storage(phrase: &str) -> Option<SecretString> {
+    let phrase = Zeroizing::new(phrase.to_lowercase());
+    let mnemonic = Mnemonic::parse_in(Language::English, phrase.as_str()).ok()?;
+    SUPPORTED_WORD_COUNTS
+        .contains(&mnemonic.word_count())
+        .then(|| mnemonic.to_string().into())
 }
 
-pub fn normalize_phrase_for_storage(phrase: &str) -> Option<String> {
-    let phrase = phrase.to_lowercase();
-    let mnemonic = Mnemonic::parse_in(Language::English, phrase).ok()?;
-    (mnemonic.word_count() == 12).then(|| mnemonic.to_string())
+fn normalize_recovery_phrase(phrase: &str) -> Zeroizing<String> {
+    Zeroizing::new(
+        phrase
+            .chars()
+            .filter(|ch| !ch.is_whitespace())
+            .flat_map(char::to_lowercase)
+            .collect(),
+    )
 }
 
-fn normalize_recovery_phrase(phrase: &str) -> String {
-    phrase
-        .chars()
-        .filter(|ch| !ch.is_whitespace())
-        .flat_map(char::to_lowercase)
-        .collect()
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PHRASE: &str = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+    const SCHEMES: [Padding; 4] = [
+        Padding::None,
+        Padding::PowerOfTwo,
+        Padding::Block(64),
+        Padding::Block(1),
+    ];
+
+    #[test]
+    fn padding_roundtrips_for_every_scheme() {
+        for padding in SCHEMES {
+            for plaintext in ["", "a", "buy milk", &"x".repeat(300)] {
+                let framed = pad_plaintext(plaintext.as_bytes(), padding).unwrap();
+                assert_eq!(
+                    framed.len(),
+                    padding.padded_len(PADDING_HEADER_LEN + plaintext.len())
+                );
+                assert_eq!(
+                    Padding::from_scheme_id(framed[0]).map(Padding::scheme_id),
+                    Some(padding.scheme_id())
+                );
+                assert_eq!(unpad_plaintext(framed).unwrap(), plaintext.as_bytes());
+            }
+        }
+    }
+
+    #[test]
+    fn padded_lengths_fall_into_buckets() {
+        assert_eq!(Padding::None.padded_len(13), 13);
+        assert_eq!(Padding::PowerOfTwo.padded_len(1), MIN_PADDED_LEN);
+        assert_eq!(Padding::PowerOfTwo.padded_len(32), 32);
+        assert_eq!(Padding::PowerOfTwo.padded_len(33), 64);
+        assert_eq!(Padding::PowerOfTwo.padded_len(1000), 1024);
+        assert_eq!(Padding::Block(64).padded_len(0), 64);
+        assert_eq!(Padding::Block(64).padded_len(64), 64);
+        assert_eq!(Padding::Block(64).padded_len(65), 128);
+        assert_eq!(Padding::Block(0).padded_len(7), 7);
+    }
+
+    #[test]
+    fn fields_of_similar_length_encrypt_to_the_same_size() {
+        let key = CryptoKey::from_recovery_phrase(PHRASE).with_padding(Padding::PowerOfTwo);
+        let short = key.encrypt_field("milk").unwrap();
+        let long = key.encrypt_field("buy milk and eggs").unwrap();
+        assert_eq!(short.version, PADDED_ENCRYPTION_VERSION);
+        assert_eq!(short.ciphertext.len(), long.ciphertext.len());
+        assert_eq!(key.decrypt_field(&long).unwrap(), "buy milk and eggs");
+    }
+
+    #[test]
+    fn rejects_broken_padding_headers() {
+        assert!(unpad_plaintext(Vec::new()).is_err());
+        assert!(unpad_plaintext(vec![1, 0, 0]).is_err());
+
+        let mut framed = pad_plaintext(b"milk", Padding::PowerOfTwo).unwrap();
+        framed[0] = 3;
+        assert!(unpad_plaintext(framed).is_err());
+
+        let mut framed = pad_plaintext(b"milk", Padding::PowerOfTwo).unwrap();
+        let len = u32::try_from(framed.len()).unwrap();
+        framed[1..PADDING_HEADER_LEN].copy_from_slice(&len.to_le_bytes());
+        assert!(unpad_plaintext(framed).is_err());
+    }
+
+    #[test]
+    fn wrapped_workspace_keys_open_for_the_recipient() {
+        let owner = CryptoKey::from_recovery_phrase(PHRASE);
+        let member = CryptoKey::from_recovery_phrase_and_passphrase(PHRASE, "member").unwrap();
+        let workspace = owner.generate_workspace_key();
+        let field = workspace.encrypt_field("groceries").unwrap();
+
+        let wrapped = workspace.wrap_for(&member.public_key()).unwrap();
+        let opened = member.unwrap_workspace_key(&wrapped).unwrap();
+
+        assert_eq!(opened.decrypt_field(&field).unwrap(), "groceries");
+        assert_eq!(opened.user_id(), member.user_id());
+    }
+
+    #[test]
+    fn wrapped_workspace_keys_do_not_open_for_others() {
+        let owner = CryptoKey::from_recovery_phrase(PHRASE);
+        let member = CryptoKey::from_recovery_phrase_and_passphrase(PHRASE, "member").unwrap();
+        let stranger = CryptoKey::from_recovery_phrase_and_passphrase(PHRASE, "other").unwrap();
+        let wrapped = owner
+            .generate_workspace_key()
+            .wrap_for(&member.public_key())
+            .unwrap();
+
+        assert!(stranger.unwrap_workspace_key(&wrapped).is_err());
+        assert!(owner.unwrap_workspace_key(&wrapped).is_err());
+        assert!(
+            member
+                .unwrap_workspace_key(&WrappedKey {
+                    version: KEY_WRAP_VERSION + 1,
+                    ..wrapped
+                })
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn v1_fields_still_decrypt_with_padding_enabled() {
+        let unpadded = CryptoKey::from_recovery_phrase(PHRASE);
+        let field = unpadded.encrypt_field("buy milk").unwrap();
+        assert_eq!(field.version, ENCRYPTION_VERSION);
+
+        let padded = CryptoKey::from_recovery_phrase(PHRASE).with_padding(Padding::Block(64));
+        assert_eq!(padded.decrypt_field(&field).unwrap(), "buy milk");
+    }
 }
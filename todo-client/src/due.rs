@@ -0,0 +1,86 @@
+//! Due dates as stored inside the encrypted `due_date` field: either a plain
+//! `YYYY-MM-DD` day, as every client before due times wrote, or an RFC 3339
+//! timestamp carrying the offset it was entered in.
+
+use std::fmt;
+
+use chrono::{
+    DateTime, Days, FixedOffset, Local, NaiveDate, NaiveDateTime, SecondsFormat, TimeZone, Utc,
+};
+
+const DATE_FORMAT: &str = "%Y-%m-%d";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Due {
+    /// Due some time on this day, wherever the user happens to be.
+    Date(NaiveDate),
+    /// Due at a fixed instant.
+    At(DateTime<FixedOffset>),
+}
+
+impl Due {
+    /// Parses a stored value; `None` for anything no client writes.
+    pub fn parse(value: &str) -> Option<Self> {
+        let value = value.trim();
+        if let Ok(at) = DateTime::parse_from_rfc3339(value) {
+            return Some(Self::At(at));
+        }
+        NaiveDate::parse_from_str(value, DATE_FORMAT)
+            .ok()
+            .map(Self::Date)
+    }
+
+    /// A wall-clock time in the local zone. Times skipped by a daylight
+    /// saving change resolve to `None`.
+    pub fn local(at: NaiveDateTime) -> Option<Self> {
+        let at = Local.from_local_datetime(&at).earliest()?;
+        Some(Self::At(at.fixed_offset()))
+    }
+
+    /// The instant after which the task is overdue. A date-only task is due
+    /// until the end of that day in the local zone.
+    pub fn deadline(&self) -> DateTime<Utc> {
+        match self {
+            Self::Date(date) => {
+                let end_of_day = date
+                    .checked_add_days(Days::new(1))
+                    .unwrap_or(*date)
+                    .and_time(chrono::NaiveTime::MIN);
+                Local
+                    .from_local_datetime(&end_of_day)
+                    .earliest()
+                    .map_or_else(|| end_of_day.and_utc(), |at| at.with_timezone(&Utc))
+            }
+            Self::At(at) => at.with_timezone(&Utc),
+        }
+    }
+
+    /// The day this is due on in the local zone.
+    pub fn local_date(&self) -> NaiveDate {
+        match self {
+            Self::Date(date) => *date,
+            Self::At(at) => at.with_timezone(&Local).date_naive(),
+        }
+    }
+
+    /// Formats for display in the local zone, e.g. `2026-10-20 14:00`.
+    pub fn format_local(&self) -> String {
+        match self {
+            Self::Date(date) => date.format(DATE_FORMAT).to_string(),
+            Self::At(at) => at
+                .with_timezone(&Local)
+                .format("%Y-%m-%d %H:%M")
+                .to_string(),
+        }
+    }
+}
+
+/// The stored form.
+impl fmt::Display for Due {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Date(date) => write!(f, "{}", date.format(DATE_FORMAT)),
+            Self::At(at) => f.write_str(&at.to_rfc3339_opts(SecondsFormat::Secs, false)),
+        }
+    }
+}
@@ -0,0 +1,110 @@
+use std::collections::VecDeque;
+
+use futures_util::{Stream, stream};
+
+use crate::{ChangeEvent, Client, LiveUpdate};
+
+const EVENT_SEPARATOR: &[u8] = b"\n\n";
+
+/// Turns the server-sent event stream into [`LiveUpdate`]s, reconnecting
+/// with the client's backoff whenever the connection drops.
+pub(crate) fn subscribe(client: Client) -> impl Stream<Item = LiveUpdate> + Send {
+    stream::unfold(Subscription::new(client), |mut subscription| async move {
+        let update = subscription.next().await;
+        Some((update, subscription))
+    })
+}
+
+struct Subscription {
+    client: Client,
+    response: Option<reqwest::Response>,
+    buffer: Vec<u8>,
+    pending: VecDeque<LiveUpdate>,
+    connected: bool,
+    /// Connection attempts since data last arrived.
+    failures: u32,
+}
+
+impl Subscription {
+    fn new(client: Client) -> Self {
+        Self {
+            client,
+            response: None,
+            buffer: Vec::new(),
+            pending: VecDeque::new(),
+            connected: false,
+            failures: 0,
+        }
+    }
+
+    async fn next(&mut self) -> LiveUpdate {
+        loop {
+            if let Some(update) = self.pending.pop_front() {
+                return update;
+            }
+
+            let Some(response) = &mut self.response else {
+                self.reconnect().await;
+                continue;
+            };
+            match response.chunk().await {
+                Ok(Some(chunk)) => {
+                    self.failures = 0;
+                    self.buffer.extend_from_slice(&chunk);
+                    self.parse_events();
+                }
+                Ok(None) | Err(_) => self.response = None,
+            }
+        }
+    }
+
+    async fn reconnect(&mut self) {
+        if let Some(retry) = self.failures.checked_sub(1) {
+            tokio::time::sleep(self.client.retry.delay(retry)).await;
+        }
+        self.failures = self.failures.saturating_add(1);
+
+        let Ok(response) = self.client.connect_events().await else {
+            return;
+        };
+        self.response = Some(response);
+        self.buffer.clear();
+        // Anything that happened while we were disconnected is lost.
+        if std::mem::replace(&mut self.connected, true) {
+            self.pending.push_back(LiveUpdate::Resync);
+        }
+    }
+
+    fn parse_events(&mut self) {
+        while let Some(end) = self
+            .buffer
+            .windows(EVENT_SEPARATOR.len())
+            .position(|window| window == EVENT_SEPARATOR)
+        {
+            let block: Vec<u8> = self.buffer.drain(..end + EVENT_SEPARATOR.len()).collect();
+            if let Some(event) = parse_event(&String::from_utf8_lossy(&block)) {
+                self.pending.push_back(LiveUpdate::Change(event));
+            }
+        }
+    }
+}
+
+fn parse_event(block: &str) -> Option<ChangeEvent> {
+    let mut name = "message";
+    let mut data = String::new();
+    for line in block.lines() {
+        if let Some(value) = line.strip_prefix("event:") {
+            name = value.trim();
+        } else if let Some(value) = line.strip_prefix("data:") {
+            if !data.is_empty() {
+                data.push('\n');
+            }
+            data.push_str(value.strip_prefix(' ').unwrap_or(value));
+        }
+    }
+
+    if name != "change" {
+        return None;
+    }
+    serde_json::from_str(&data).ok()
+}
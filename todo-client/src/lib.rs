@@ -1,9 +1,30 @@
+pub mod builder;
 pub mod client;
 pub mod crypto;
+pub mod due;
+mod events;
 pub mod models;
+pub mod priority;
+pub mod protocol;
+pub mod recurrence;
+pub mod retry;
+pub mod share;
+pub mod tags;
 
+pub use builder::{ClientBuilder, Transport};
 pub use client::Client;
-pub use crypto::{CryptoKey, generate_recovery_phrase, normalize_phrase_for_storage};
+pub use crypto::{
+    CryptoKey, Padding, SUPPORTED_ENCRYPTION_VERSIONS, SUPPORTED_KEY_WRAP_VERSIONS,
+    SUPPORTED_WORD_COUNTS, ShareKey, generate_recovery_phrase, normalize_phrase_for_storage,
+};
+pub use due::Due;
 pub use models::{
-    EncryptedField, EncryptedTask, EncryptedWorkspace, Task, Workspace, WorkspaceStats,
+    ChangeEvent, ChangeKind, Decrypted, DecryptionFailure, EncryptedField, EncryptedShare,
+    EncryptedTask, EncryptedWorkspace, EncryptedWorkspaceInvite, LiveUpdate, RegistrationPolicy,
+    RegistrationStatus, ServerInfo, SharedTask, SharedWorkspace, Task, TaskQuery, Workspace,
+    WorkspaceInvite, WorkspaceMember, WorkspaceRole, WorkspaceStats, WrappedKey,
 };
+pub use priority::Priority;
+pub use recurrence::{Frequency, Recurrence};
+pub use retry::RetryPolicy;
+pub use share::open_share_link;
@@ -3,6 +3,8 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
+use crate::{due::Due, priority::Priority, recurrence::Recurrence, tags};
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct EncryptedField {
     pub ciphertext: String,
@@ -10,10 +12,51 @@ pub struct EncryptedField {
     pub version: u8,
 }
 
+/// A workspace content key sealed to one member's X25519 public key.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WrappedKey {
+    pub ephemeral_public_key: String,
+    pub ciphertext: String,
+    pub nonce: String,
+    pub version: u8,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WorkspaceRole {
+    Read,
+    Write,
+    Owner,
+}
+
+impl WorkspaceRole {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            WorkspaceRole::Read => "read",
+            WorkspaceRole::Write => "write",
+            WorkspaceRole::Owner => "owner",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "read" => Some(WorkspaceRole::Read),
+            "write" => Some(WorkspaceRole::Write),
+            "owner" => Some(WorkspaceRole::Owner),
+            _ => None,
+        }
+    }
+
+    pub fn allows(self, required: WorkspaceRole) -> bool {
+        self >= required
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Workspace {
     pub id: i64,
     pub name: String,
+    pub role: WorkspaceRole,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
@@ -22,10 +65,34 @@ pub struct Workspace {
 pub struct EncryptedWorkspace {
     pub id: i64,
     pub name: EncryptedField,
+    pub role: WorkspaceRole,
+    pub wrapped_key: Option<WrappedKey>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkspaceMember {
+    pub user_id: String,
+    pub role: WorkspaceRole,
+    pub accepted: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkspaceInvite {
+    pub workspace_id: i64,
+    pub name: String,
+    pub role: WorkspaceRole,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EncryptedWorkspaceInvite {
+    pub workspace_id: i64,
+    pub name: EncryptedField,
+    pub role: WorkspaceRole,
+    pub wrapped_key: WrappedKey,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct WorkspaceStats {
     pub workspace_id: i64,
@@ -41,12 +108,47 @@ pub struct Task {
     pub completed: bool,
     pub archived: bool,
     pub due_date: Option<String>,
+    /// The stored recurrence rule, see [`Task::recurrence`].
+    pub recurrence: Option<String>,
+    /// The stored priority, see [`Task::priority`].
+    pub priority: Option<String>,
+    /// The stored tags, see [`Task::tags`].
+    pub tags: Option<String>,
     pub workspace_id: i64,
     pub parent_task_id: Option<i64>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
 
+impl Task {
+    /// The parsed due date; `None` when unset or unreadable.
+    pub fn due(&self) -> Option<Due> {
+        self.due_date.as_deref().and_then(Due::parse)
+    }
+
+    /// The parsed recurrence rule; `None` when unset or unreadable.
+    pub fn recurrence(&self) -> Option<Recurrence> {
+        self.recurrence.as_deref().and_then(Recurrence::parse)
+    }
+
+    /// The parsed priority; [`Priority::None`] when unset or unreadable.
+    pub fn priority(&self) -> Priority {
+        self.priority
+            .as_deref()
+            .and_then(Priority::parse)
+            .unwrap_or_default()
+    }
+
+    /// The task's tags, normalized as in [`tags::normalize`].
+    pub fn tags(&self) -> Vec<String> {
+        self.tags.as_deref().map(tags::parse).unwrap_or_default()
+    }
+
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags().iter().any(|own| own == tag)
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct EncryptedTask {
     pub id: i64,
@@ -55,8 +157,242 @@ pub struct EncryptedTask {
     pub completed: bool,
     pub archived: bool,
     pub due_date: Option<EncryptedField>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub recurrence: Option<EncryptedField>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub priority: Option<EncryptedField>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tags: Option<EncryptedField>,
     pub workspace_id: i64,
     pub parent_task_id: Option<i64>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
+
+/// Which tasks to list, filtered on the metadata the server stores in
+/// plaintext.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TaskQuery {
+    pub include_archived: bool,
+    /// Only archived or only live tasks; overrides `include_archived`.
+    pub archived: Option<bool>,
+    pub completed: Option<bool>,
+    pub parent_task_id: Option<i64>,
+    pub created_after: Option<DateTime<Utc>>,
+    pub created_before: Option<DateTime<Utc>>,
+    pub updated_after: Option<DateTime<Utc>>,
+    pub updated_before: Option<DateTime<Utc>>,
+    /// Tasks per request; the server caps this.
+    pub page_size: Option<u32>,
+}
+
+impl TaskQuery {
+    pub fn has_filters(&self) -> bool {
+        self.archived.is_some()
+            || self.completed.is_some()
+            || self.parent_task_id.is_some()
+            || self.created_after.is_some()
+            || self.created_before.is_some()
+            || self.updated_after.is_some()
+            || self.updated_before.is_some()
+    }
+}
+
+/// What a server supports, as reported by `GET /api/info`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ServerInfo {
+    pub version: String,
+    pub api_versions: Vec<u32>,
+    pub encryption_versions: Vec<u8>,
+    pub features: Vec<String>,
+    #[serde(default)]
+    pub registration: RegistrationPolicy,
+}
+
+impl ServerInfo {
+    /// Stands in for servers from before versioning, which have no info
+    /// endpoint and only the unversioned routes.
+    pub fn legacy() -> Self {
+        Self {
+            version: "unknown".to_string(),
+            api_versions: Vec::new(),
+            encryption_versions: Vec::new(),
+            features: Vec::new(),
+            registration: RegistrationPolicy::Open,
+        }
+    }
+
+    pub fn is_legacy(&self) -> bool {
+        self.api_versions.is_empty()
+    }
+
+    pub fn supports(&self, feature: &str) -> bool {
+        self.features.iter().any(|supported| supported == feature)
+    }
+}
+
+/// Who may create an account on a server.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RegistrationPolicy {
+    /// Any new user id is registered on first use.
+    #[default]
+    Open,
+    /// New users need a single-use invite code.
+    InviteOnly,
+    /// Only existing users are served.
+    Closed,
+}
+
+impl RegistrationPolicy {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            RegistrationPolicy::Open => "open",
+            RegistrationPolicy::InviteOnly => "invite-only",
+            RegistrationPolicy::Closed => "closed",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "open" => Some(RegistrationPolicy::Open),
+            "invite-only" => Some(RegistrationPolicy::InviteOnly),
+            "closed" => Some(RegistrationPolicy::Closed),
+            _ => None,
+        }
+    }
+}
+
+/// The outcome of [`crate::Client::register`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RegistrationStatus {
+    Registered,
+    InviteRequired,
+    InvalidInvite,
+    Closed,
+}
+
+/// What changed in a [`ChangeEvent`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeKind {
+    WorkspaceCreated,
+    WorkspaceUpdated,
+    WorkspaceDeleted,
+    MembersChanged,
+    TaskCreated,
+    TaskUpdated,
+    TaskDeleted,
+    TasksArchived,
+}
+
+/// A change pushed to every member of a workspace. Carries ids only, never
+/// any content.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChangeEvent {
+    pub kind: ChangeKind,
+    pub workspace_id: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub task_id: Option<i64>,
+}
+
+/// An item of [`crate::Client::subscribe`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LiveUpdate {
+    Change(ChangeEvent),
+    /// The connection was re-established and changes may have been missed,
+    /// so everything should be reloaded.
+    Resync,
+}
+
+/// An item returned by the server that could not be decrypted, for example
+/// because it is corrupted or encrypted under a key we do not hold.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecryptionFailure {
+    pub id: i64,
+    pub reason: String,
+}
+
+/// The items of a listing that decrypted, alongside the ones that did not.
+#[derive(Debug, Clone)]
+pub struct Decrypted<T> {
+    pub items: Vec<T>,
+    pub failures: Vec<DecryptionFailure>,
+}
+
+impl<T> Default for Decrypted<T> {
+    fn default() -> Self {
+        Self {
+            items: Vec::new(),
+            failures: Vec::new(),
+        }
+    }
+}
+
+impl<T> Extend<Decrypted<T>> for Decrypted<T> {
+    fn extend<I: IntoIterator<Item = Decrypted<T>>>(&mut self, iter: I) {
+        for decrypted in iter {
+            self.items.extend(decrypted.items);
+            self.failures.extend(decrypted.failures);
+        }
+    }
+}
+
+impl<T> FromIterator<(i64, anyhow::Result<T>)> for Decrypted<T> {
+    fn from_iter<I: IntoIterator<Item = (i64, anyhow::Result<T>)>>(iter: I) -> Self {
+        let mut decrypted = Self::default();
+        for (id, result) in iter {
+            match result {
+                Ok(item) => decrypted.items.push(item),
+                Err(err) => decrypted.failures.push(DecryptionFailure {
+                    id,
+                    reason: format!("{err:#}"),
+                }),
+            }
+        }
+        decrypted
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EncryptedShare {
+    pub snapshot: EncryptedField,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+/// A read-only copy of a workspace opened from a share link.
+#[derive(Debug, Clone)]
+pub struct SharedWorkspace {
+    pub name: String,
+    pub tasks: Vec<SharedTask>,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SharedTask {
+    pub id: i64,
+    pub title: String,
+    pub completed: bool,
+    pub due_date: Option<String>,
+    pub parent_task_id: Option<i64>,
+}
+
+impl SharedTask {
+    pub fn due(&self) -> Option<Due> {
+        self.due_date.as_deref().and_then(Due::parse)
+    }
+}
+
+impl From<&Task> for SharedTask {
+    fn from(task: &Task) -> Self {
+        Self {
+            id: task.id,
+            title: task.title.clone(),
+            completed: task.completed,
+            due_date: task.due_date.clone(),
+            parent_task_id: task.parent_task_id,
+        }
+    }
+}
@@ -0,0 +1,64 @@
+//! Task priorities, stored by name inside a task's encrypted `priority`
+//! field. Tasks without one have [`Priority::None`].
+
+use std::fmt;
+
+/// Ordered from least to most important.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Priority {
+    #[default]
+    None,
+    Low,
+    Medium,
+    High,
+    Urgent,
+}
+
+impl Priority {
+    pub const ALL: [Self; 5] = [
+        Self::None,
+        Self::Low,
+        Self::Medium,
+        Self::High,
+        Self::Urgent,
+    ];
+
+    /// Parses a stored value; `None` for anything no client writes.
+    pub fn parse(value: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|priority| priority.as_str() == value.trim())
+    }
+
+    /// The stored form.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::None => "none",
+            Self::Low => "low",
+            Self::Medium => "medium",
+            Self::High => "high",
+            Self::Urgent => "urgent",
+        }
+    }
+
+    /// One step more important, staying at [`Priority::Urgent`].
+    pub fn raised(self) -> Self {
+        Self::ALL
+            .get(self as usize + 1)
+            .copied()
+            .unwrap_or(Self::Urgent)
+    }
+
+    /// One step less important, staying at [`Priority::None`].
+    pub fn lowered(self) -> Self {
+        (self as usize)
+            .checked_sub(1)
+            .map_or(Self::None, |index| Self::ALL[index])
+    }
+}
+
+impl fmt::Display for Priority {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
@@ -0,0 +1,37 @@
+/// The API version this crate speaks, served under `/api/v1`.
+pub const API_VERSION: u32 = 1;
+
+/// Per-workspace content keys wrapped to members' public keys.
+pub const FEATURE_WORKSPACE_KEYS: &str = "workspace-keys";
+/// Inviting other users into a workspace.
+pub const FEATURE_MEMBERS: &str = "members";
+/// Read-only encrypted share links.
+pub const FEATURE_SHARES: &str = "shares";
+/// Live change events over server-sent events.
+pub const FEATURE_EVENTS: &str = "events";
+/// Cursor pagination and metadata filters on task listings.
+pub const FEATURE_PAGINATION: &str = "pagination";
+/// Explicit registration, possibly gated by invite codes.
+pub const FEATURE_REGISTRATION: &str = "registration";
+/// Archiving reports the archived task ids, which can be restored again.
+pub const FEATURE_UNARCHIVE: &str = "unarchive";
+/// Tasks store an encrypted recurrence rule.
+pub const FEATURE_RECURRENCE: &str = "recurrence";
+/// Tasks store an encrypted priority.
+pub const FEATURE_PRIORITY: &str = "priority";
+/// Tasks store an encrypted list of tags.
+pub const FEATURE_TAGS: &str = "tags";
+
+/// Set on a paginated listing when more items follow; pass its value as
+/// `after` to fetch the next page.
+pub const NEXT_CURSOR_HEADER: &str = "x-next-cursor";
+/// Largest page the server returns, whatever `limit` asks for.
+pub const MAX_PAGE_SIZE: u32 = 500;
+
+/// Rewrites an `/api/...` path to the versioned route.
+pub(crate) fn versioned_path(path: &str) -> String {
+    match path.strip_prefix("/api/") {
+        Some(rest) => format!("/api/v{API_VERSION}/{rest}"),
+        None => path.to_string(),
+    }
+}
@@ -0,0 +1,102 @@
+//! Recurrence rules, stored as JSON inside a task's encrypted `recurrence`
+//! field. The server never sees them, so the next occurrence of a completed
+//! task is created by the client that completed it.
+
+use chrono::{DateTime, Datelike, Days, Local, Months, NaiveDate, Utc, Weekday};
+use serde::{Deserialize, Serialize};
+
+use crate::due::Due;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Frequency {
+    Daily,
+    Weekly,
+    Monthly,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Recurrence {
+    pub frequency: Frequency,
+    /// Repeat every `interval` days, weeks or months.
+    #[serde(default = "default_interval")]
+    pub interval: u32,
+    /// For weekly rules, the days of the week it falls on. Empty means the
+    /// weekday of the due date.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub weekdays: Vec<Weekday>,
+    /// Count from the day the task was completed rather than from its due
+    /// date.
+    #[serde(default)]
+    pub after_completion: bool,
+    /// Copy the task's subtasks into each new occurrence.
+    #[serde(default)]
+    pub copy_subtasks: bool,
+}
+
+fn default_interval() -> u32 {
+    1
+}
+
+impl Recurrence {
+    pub fn parse(value: &str) -> Option<Self> {
+        serde_json::from_str(value).ok()
+    }
+
+    /// The stored form.
+    pub fn to_storage(&self) -> String {
+        serde_json::to_string(self).expect("recurrence rules always serialize")
+    }
+
+    /// The due date of the occurrence after one due at `due` and completed
+    /// at `completed_at`. On a fixed schedule, occurrences that already
+    /// passed while the task was open are skipped.
+    pub fn next_due(&self, due: Option<Due>, completed_at: DateTime<Utc>) -> Option<Due> {
+        let completed_on = completed_at.with_timezone(&Local).date_naive();
+        let anchor = match due {
+            Some(due) if !self.after_completion => due.local_date(),
+            _ => completed_on,
+        };
+
+        let mut date = self.step(anchor, anchor)?;
+        if !self.after_completion {
+            while date <= completed_on {
+                date = self.step(date, anchor)?;
+            }
+        }
+
+        match due {
+            Some(Due::At(at)) => Due::local(date.and_time(at.with_timezone(&Local).time())),
+            _ => Some(Due::Date(date)),
+        }
+    }
+
+    /// The first date after `date` on the schedule counted from `anchor`.
+    fn step(&self, date: NaiveDate, anchor: NaiveDate) -> Option<NaiveDate> {
+        let interval = self.interval.max(1);
+        match self.frequency {
+            Frequency::Daily => date.checked_add_days(Days::new(interval.into())),
+            Frequency::Weekly if self.weekdays.is_empty() => {
+                date.checked_add_days(Days::new(u64::from(interval) * 7))
+            }
+            Frequency::Weekly => {
+                let anchor_week = anchor.week(Weekday::Mon).first_day();
+                (1..=7 * u64::from(interval))
+                    .filter_map(|days| date.checked_add_days(Days::new(days)))
+                    .find(|candidate| {
+                        let weeks =
+                            (candidate.week(Weekday::Mon).first_day() - anchor_week).num_weeks();
+                        weeks % i64::from(interval) == 0
+                            && self.weekdays.contains(&candidate.weekday())
+                    })
+            }
+            // Counted from the anchor so that the 31st does not drift to the
+            // 28th after February.
+            Frequency::Monthly => (1u32..)
+                .map_while(|step| interval.checked_mul(step))
+                .map(|months| anchor.checked_add_months(Months::new(months)))
+                .find(|candidate| candidate.is_none_or(|candidate| candidate > date))
+                .flatten(),
+        }
+    }
+}
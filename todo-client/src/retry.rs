@@ -0,0 +1,123 @@
+use std::time::Duration;
+
+use rand::Rng;
+use reqwest::{Method, Request, Response, StatusCode, header::RETRY_AFTER};
+
+/// How idempotent requests are retried after connection failures, timeouts
+/// and transient server errors, and how any request is retried after the
+/// server rate limited it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Retries after the first attempt; zero disables retrying.
+    pub max_retries: u32,
+    /// Delay before the first retry, doubled for every following one.
+    pub base_delay: Duration,
+    /// Upper bound for a single delay before jitter is applied.
+    pub max_delay: Duration,
+    /// Longest `Retry-After` the client waits out on a 429; longer waits are
+    /// returned to the caller as the error response.
+    pub max_retry_after: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            base_delay: Duration::from_millis(200),
+            max_delay: Duration::from_secs(5),
+            max_retry_after: Duration::from_secs(30),
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub fn none() -> Self {
+        Self {
+            max_retries: 0,
+            ..Self::default()
+        }
+    }
+
+    /// Exponential backoff with jitter: a random delay between half and all
+    /// of the capped exponential delay for this retry.
+    pub(crate) fn delay(&self, retry: u32) -> Duration {
+        let delay = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(retry))
+            .min(self.max_delay);
+        let half = delay / 2;
+        half + rand::rng().random_range(Duration::ZERO..=delay - half)
+    }
+}
+
+/// Sends a request, retrying it under `policy` when it is safe to repeat.
+/// Rate limited requests were never processed, so they are retried whatever
+/// their method once the server's `Retry-After` has passed.
+pub(crate) async fn send(
+    http: &reqwest::Client,
+    policy: &RetryPolicy,
+    request: Request,
+) -> reqwest::Result<Response> {
+    let idempotent = is_idempotent(request.method());
+
+    let mut retry = 0;
+    loop {
+        let Some(next) = (retry < policy.max_retries)
+            .then(|| request.try_clone())
+            .flatten()
+        else {
+            return http.execute(request).await;
+        };
+
+        let result = http.execute(next).await;
+        let delay = match &result {
+            Ok(response) if response.status() == StatusCode::TOO_MANY_REQUESTS => {
+                match retry_after(response) {
+                    Some(delay) if delay > policy.max_retry_after => return result,
+                    Some(delay) => delay,
+                    None => policy.delay(retry),
+                }
+            }
+            Ok(response) if idempotent && is_transient_status(response.status()) => {
+                policy.delay(retry)
+            }
+            Err(err) if idempotent && is_transient_error(err) => policy.delay(retry),
+            _ => return result,
+        };
+
+        tokio::time::sleep(delay).await;
+        retry += 1;
+    }
+}
+
+/// The delay a 429 response asks for, in the delta-seconds form.
+fn retry_after(response: &Response) -> Option<Duration> {
+    let seconds = response
+        .headers()
+        .get(RETRY_AFTER)?
+        .to_str()
+        .ok()?
+        .trim()
+        .parse()
+        .ok()?;
+
+    Some(Duration::from_secs(seconds))
+}
+
+fn is_idempotent(method: &Method) -> bool {
+    matches!(
+        *method,
+        Method::GET | Method::HEAD | Method::PUT | Method::DELETE | Method::PATCH | Method::OPTIONS
+    )
+}
+
+fn is_transient_status(status: StatusCode) -> bool {
+    matches!(
+        status,
+        StatusCode::BAD_GATEWAY | StatusCode::SERVICE_UNAVAILABLE | StatusCode::GATEWAY_TIMEOUT
+    )
+}
+
+fn is_transient_error(err: &reqwest::Error) -> bool {
+    err.is_connect() || err.is_timeout() || err.is_request()
+}
@@ -0,0 +1,55 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    EncryptedShare, ShareKey, SharedTask, SharedWorkspace, Transport,
+    client::normalize_endpoint_url, protocol::versioned_path,
+};
+
+/// Unversioned so links minted before versioning still open; new links use
+/// the versioned form of this path.
+const SHARE_PATH: &str = "/api/shares/";
+
+/// The plaintext published under a share key.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct ShareSnapshot {
+    pub name: String,
+    pub tasks: Vec<SharedTask>,
+}
+
+pub(crate) fn share_link(endpoint_url: &str, share_id: &str, key: &ShareKey) -> String {
+    format!(
+        "{endpoint_url}{}{share_id}#{}",
+        versioned_path(SHARE_PATH),
+        key.encode()
+    )
+}
+
+/// Fetches and decrypts a share link over `transport`. The key in the URL
+/// fragment is only used locally and never sent to the server.
+pub async fn open_share_link(transport: &Transport, link: &str) -> anyhow::Result<SharedWorkspace> {
+    let (url, key) = link
+        .trim()
+        .split_once('#')
+        .ok_or_else(|| anyhow::anyhow!("share link is missing its key"))?;
+    if !url.contains(SHARE_PATH) && !url.contains(&versioned_path(SHARE_PATH)) {
+        anyhow::bail!("not a share link");
+    }
+    let key = ShareKey::decode(key)?;
+
+    let share = transport
+        .http()?
+        .get(normalize_endpoint_url(url))
+        .send()
+        .await?
+        .error_for_status()?
+        .json::<EncryptedShare>()
+        .await?;
+    let snapshot: ShareSnapshot = serde_json::from_str(&key.decrypt_field(&share.snapshot)?)?;
+
+    Ok(SharedWorkspace {
+        name: snapshot.name,
+        tasks: snapshot.tasks,
+        created_at: share.created_at,
+        expires_at: share.expires_at,
+    })
+}
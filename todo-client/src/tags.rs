@@ -0,0 +1,42 @@
+//! Task tags, stored as a JSON list inside a task's encrypted `tags` field.
+//! Tags are kept lowercase without the `#` they are typed with; contexts
+//! keep their `@` (`@home`) so the two stay apart.
+
+/// Normalizes a tag as typed (`#Project`, `@Home`, `errands`); `None` for
+/// input that is not a tag.
+pub fn normalize(tag: &str) -> Option<String> {
+    let tag = tag.trim();
+    let tag = tag.strip_prefix('#').unwrap_or(tag).to_lowercase();
+    let name = tag.strip_prefix('@').unwrap_or(&tag);
+    if name.is_empty()
+        || name.starts_with(['#', '@'])
+        || name.chars().any(|c| c.is_whitespace() || c == ',')
+    {
+        return None;
+    }
+    Some(tag)
+}
+
+/// How a tag is written: `#project` or `@home`.
+pub fn label(tag: &str) -> String {
+    if tag.starts_with('@') {
+        tag.to_string()
+    } else {
+        format!("#{tag}")
+    }
+}
+
+/// Parses a stored value; unreadable values have no tags.
+pub fn parse(value: &str) -> Vec<String> {
+    serde_json::from_str::<Vec<String>>(value)
+        .map(|tags| tags.iter().filter_map(|tag| normalize(tag)).collect())
+        .unwrap_or_default()
+}
+
+/// The stored form: normalized, sorted and without duplicates.
+pub fn to_storage(tags: &[String]) -> String {
+    let mut tags: Vec<String> = tags.iter().filter_map(|tag| normalize(tag)).collect();
+    tags.sort();
+    tags.dedup();
+    serde_json::to_string(&tags).expect("tag lists always serialize")
+}
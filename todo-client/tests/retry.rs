@@ -0,0 +1,196 @@
+use std::{
+    net::SocketAddr,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicUsize, Ordering},
+    },
+    time::Duration,
+};
+
+use axum::{
+    Json, Router,
+    extract::State,
+    http::{
+        HeaderMap, StatusCode,
+        header::{RETRY_AFTER, USER_AGENT},
+    },
+    response::{IntoResponse, Response},
+    routing::{get, post},
+};
+use serde_json::json;
+use todo_client::{Client, CryptoKey, RetryPolicy};
+
+const PHRASE: &str = "legal winner thank year wave sausage worth useful legal winner thank yellow";
+
+/// A stand-in API that answers 503 to the first `failures` requests, or 429
+/// with `retry_after` to the first `throttled` ones.
+#[derive(Clone, Default)]
+struct Flaky {
+    failures: usize,
+    throttled: usize,
+    retry_after: &'static str,
+    hits: Arc<AtomicUsize>,
+    user_agent: Arc<Mutex<Option<String>>>,
+}
+
+async fn workspaces(
+    State(flaky): State<Flaky>,
+    headers: HeaderMap,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    *flaky.user_agent.lock().unwrap() = headers
+        .get(USER_AGENT)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string);
+    if flaky.hits.fetch_add(1, Ordering::SeqCst) < flaky.failures {
+        return Err(StatusCode::SERVICE_UNAVAILABLE);
+    }
+
+    Ok(Json(json!({ "workspaces": [], "stats": [] })))
+}
+
+async fn toggle(State(flaky): State<Flaky>) -> Response {
+    let hit = flaky.hits.fetch_add(1, Ordering::SeqCst);
+    if hit < flaky.throttled {
+        return (
+            StatusCode::TOO_MANY_REQUESTS,
+            [(RETRY_AFTER, flaky.retry_after)],
+        )
+            .into_response();
+    }
+    if hit < flaky.failures {
+        return StatusCode::SERVICE_UNAVAILABLE.into_response();
+    }
+
+    StatusCode::NO_CONTENT.into_response()
+}
+
+async fn hang() -> StatusCode {
+    tokio::time::sleep(Duration::from_secs(60)).await;
+    StatusCode::NO_CONTENT
+}
+
+async fn serve(flaky: Flaky) -> SocketAddr {
+    let app = Router::new()
+        .route("/api/v1/workspaces", get(workspaces))
+        .route("/api/v1/tasks/{task_id}/toggle", post(toggle))
+        .route("/api/v1/tasks/{task_id}", get(hang).delete(hang))
+        .with_state(flaky);
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    addr
+}
+
+fn client(addr: SocketAddr, max_retries: u32) -> Client {
+    Client::builder(
+        format!("http://{addr}"),
+        CryptoKey::from_recovery_phrase(PHRASE),
+    )
+    .request_timeout(Duration::from_millis(200))
+    .retry(RetryPolicy {
+        max_retries,
+        base_delay: Duration::from_millis(1),
+        max_delay: Duration::from_millis(10),
+        max_retry_after: Duration::from_secs(2),
+    })
+    .build()
+    .unwrap()
+}
+
+#[tokio::test]
+async fn idempotent_requests_retry_transient_failures() {
+    let flaky = Flaky {
+        failures: 2,
+        ..Flaky::default()
+    };
+    let addr = serve(flaky.clone()).await;
+
+    let stats = client(addr, 3).get_workspace_stats().await.unwrap();
+
+    assert!(stats.is_empty());
+    assert_eq!(flaky.hits.load(Ordering::SeqCst), 3);
+}
+
+#[tokio::test]
+async fn retries_stop_after_max_retries() {
+    let flaky = Flaky {
+        failures: usize::MAX,
+        ..Flaky::default()
+    };
+    let addr = serve(flaky.clone()).await;
+
+    let result = client(addr, 2).get_workspace_stats().await;
+
+    assert!(result.is_err());
+    assert_eq!(flaky.hits.load(Ordering::SeqCst), 3);
+}
+
+#[tokio::test]
+async fn non_idempotent_requests_are_not_retried() {
+    let flaky = Flaky {
+        failures: 1,
+        ..Flaky::default()
+    };
+    let addr = serve(flaky.clone()).await;
+
+    let result = client(addr, 3).toggle_task_completion(1).await;
+
+    assert!(result.is_err());
+    assert_eq!(flaky.hits.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn hung_requests_time_out() {
+    let addr = serve(Flaky::default()).await;
+
+    let started = std::time::Instant::now();
+    let result = client(addr, 1).delete_task(1).await;
+
+    assert!(result.is_err());
+    assert!(started.elapsed() < Duration::from_secs(5));
+}
+
+#[tokio::test]
+async fn user_agent_carries_crate_version() {
+    let flaky = Flaky::default();
+    let addr = serve(flaky.clone()).await;
+
+    client(addr, 0).get_workspace_stats().await.unwrap();
+
+    let user_agent = flaky.user_agent.lock().unwrap().clone();
+    assert_eq!(
+        user_agent.as_deref(),
+        Some(concat!("todo-client/", env!("CARGO_PKG_VERSION")))
+    );
+}
+
+#[tokio::test]
+async fn rate_limited_requests_wait_for_retry_after() {
+    let flaky = Flaky {
+        throttled: 1,
+        retry_after: "1",
+        ..Flaky::default()
+    };
+    let addr = serve(flaky.clone()).await;
+
+    let started = std::time::Instant::now();
+    client(addr, 3).toggle_task_completion(1).await.unwrap();
+
+    assert!(started.elapsed() >= Duration::from_secs(1));
+    assert_eq!(flaky.hits.load(Ordering::SeqCst), 2);
+}
+
+#[tokio::test]
+async fn long_retry_after_is_returned_to_the_caller() {
+    let flaky = Flaky {
+        throttled: usize::MAX,
+        retry_after: "3600",
+        ..Flaky::default()
+    };
+    let addr = serve(flaky.clone()).await;
+
+    let result = client(addr, 3).toggle_task_completion(1).await;
+
+    assert!(result.is_err());
+    assert_eq!(flaky.hits.load(Ordering::SeqCst), 1);
+}
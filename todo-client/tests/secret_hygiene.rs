@@ -0,0 +1,83 @@
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use secrecy::ExposeSecret;
+use todo_client::{Client, CryptoKey, ShareKey, generate_recovery_phrase};
+
+const PHRASE: &str =
+    "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+
+fn leaked_representations(bytes: &[u8]) -> Vec<String> {
+    vec![
+        blake3::Hash::from_bytes(bytes.try_into().unwrap())
+            .to_hex()
+            .to_string(),
+        URL_SAFE_NO_PAD.encode(bytes),
+        format!("{bytes:?}"),
+        format!("{:?}", &bytes[..4])
+            .trim_end_matches(']')
+            .to_string(),
+    ]
+}
+
+fn derived_secret(label: &str) -> [u8; 32] {
+    let normalized: String = PHRASE.chars().filter(|ch| !ch.is_whitespace()).collect();
+    *blake3::hash(format!("{label}{normalized}").as_bytes()).as_bytes()
+}
+
+#[test]
+fn crypto_key_debug_redacts_key_material() {
+    let key = CryptoKey::from_recovery_phrase(PHRASE);
+    let debug = format!("{key:?}");
+
+    for secret in [
+        derived_secret("encryption v1:"),
+        derived_secret("x25519 v1:"),
+    ] {
+        for leaked in leaked_representations(&secret) {
+            assert!(!debug.contains(&leaked), "{debug} leaks {leaked}");
+        }
+    }
+    assert!(!debug.contains("abandon"));
+    assert!(debug.contains("redacted"));
+}
+
+#[test]
+fn client_debug_redacts_key_material() {
+    let client = Client::new(
+        "http://localhost:3000".to_string(),
+        CryptoKey::from_recovery_phrase(PHRASE),
+    );
+    let debug = format!("{client:?}");
+
+    for leaked in leaked_representations(&derived_secret("encryption v1:")) {
+        assert!(!debug.contains(&leaked), "{debug} leaks {leaked}");
+    }
+}
+
+#[test]
+fn share_key_debug_redacts_key_material() {
+    let key = CryptoKey::from_recovery_phrase(PHRASE).generate_share_key();
+    let encoded = key.encode();
+    let bytes = URL_SAFE_NO_PAD.decode(&encoded).unwrap();
+
+    for debug in [
+        format!("{key:?}"),
+        format!("{:?}", ShareKey::decode(&encoded).unwrap()),
+    ] {
+        for leaked in leaked_representations(&bytes) {
+            assert!(!debug.contains(&leaked), "{debug} leaks {leaked}");
+        }
+    }
+}
+
+#[test]
+fn recovery_phrase_debug_is_redacted() {
+    let phrase = generate_recovery_phrase(24).unwrap();
+    let debug = format!("{phrase:?}");
+
+    let first_words: Vec<_> = phrase.expose_secret().split_whitespace().take(2).collect();
+    assert!(
+        !debug.contains(&first_words.join(" ")),
+        "{debug} leaks the phrase"
+    );
+    assert!(debug.contains("REDACTED"));
+}
@@ -0,0 +1,75 @@
+//! The agenda: open tasks from every workspace grouped by when they are
+//! due, soonest first.
+
+use std::cmp::Reverse;
+
+use chrono::{DateTime, Datelike, Days, Local, NaiveDate, Utc};
+use todo_client::Task;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Group {
+    Overdue,
+    Today,
+    Tomorrow,
+    /// The rest of the week, up to and including Sunday.
+    ThisWeek,
+    Later,
+    /// No due date, or one no client could have written.
+    NoDate,
+}
+
+impl Group {
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Overdue => "Overdue",
+            Self::Today => "Today",
+            Self::Tomorrow => "Tomorrow",
+            Self::ThisWeek => "This week",
+            Self::Later => "Later",
+            Self::NoDate => "No date",
+        }
+    }
+
+    pub fn of(task: &Task, now: DateTime<Utc>) -> Self {
+        let Some(due) = task.due() else {
+            return Self::NoDate;
+        };
+        if due.deadline() <= now {
+            return Self::Overdue;
+        }
+        let today = now.with_timezone(&Local).date_naive();
+        let date = due.local_date();
+        if date <= today {
+            Self::Today
+        } else if date == today + Days::new(1) {
+            Self::Tomorrow
+        } else if date <= end_of_week(today) {
+            Self::ThisWeek
+        } else {
+            Self::Later
+        }
+    }
+}
+
+fn end_of_week(today: NaiveDate) -> NaiveDate {
+    today + Days::new(6 - u64::from(today.weekday().num_days_from_monday()))
+}
+
+/// The open tasks among `tasks` in agenda order: by group, then by due
+/// time, priority and age.
+pub fn arrange(tasks: &[Task], now: DateTime<Utc>) -> Vec<(Group, Task)> {
+    let mut entries: Vec<(Group, Task)> = tasks
+        .iter()
+        .filter(|task| !task.completed)
+        .map(|task| (Group::of(task, now), task.clone()))
+        .collect();
+    entries.sort_by_key(|(group, task)| {
+        (
+            *group,
+            task.due().map(|due| due.deadline()),
+            Reverse(task.priority()),
+            task.created_at,
+        )
+    });
+    entries
+}
@@ -1,14 +1,34 @@
 use std::{
+    collections::BTreeMap,
     fs,
     io::{self, Write},
     path::PathBuf,
+    time::Duration,
 };
 
 use keyring::Entry;
+use ratatui::crossterm::{
+    event::{self, Event, KeyCode, KeyEventKind, KeyModifiers},
+    terminal::{disable_raw_mode, enable_raw_mode},
+};
+use secrecy::{ExposeSecret, SecretString};
 use serde::{Deserialize, Serialize};
-use todo_client::{generate_recovery_phrase, normalize_phrase_for_storage};
+use todo_client::{
+    Client, CryptoKey, Padding, RegistrationStatus, RetryPolicy, SUPPORTED_WORD_COUNTS, Transport,
+    generate_recovery_phrase, normalize_phrase_for_storage,
+};
+use zeroize::Zeroizing;
+
+use crate::{keymap::Keymap, theme::Theme};
 
 const DEFAULT_ENDPOINT: &str = "api.todo.omfj.no";
+const DEFAULT_PADDING_BLOCK_SIZE: usize = 32;
+const DEFAULT_PHRASE_WORDS: usize = 12;
+const DEFAULT_CONNECT_TIMEOUT_SECS: u64 = 10;
+const DEFAULT_REQUEST_TIMEOUT_SECS: u64 = 30;
+const DEFAULT_RETRIES: u32 = 3;
+const DEFAULT_DUE_SOON_HOURS: u64 = 24;
+const INVITE_CODE_ATTEMPTS: usize = 3;
 const KEYCHAIN_SERVICE: &str = "todo";
 const KEYCHAIN_ACCOUNT: &str = "phrase";
 const DEV_KEYCHAIN_ACCOUNT: &str = "phrase_dev";
@@ -17,6 +37,18 @@ const DEV_KEYCHAIN_ACCOUNT: &str = "phrase_dev";
 pub struct Config {
     #[serde(default)]
     pub general: GeneralConfig,
+    #[serde(default)]
+    pub encryption: EncryptionConfig,
+    #[serde(default)]
+    pub network: NetworkConfig,
+    #[serde(default)]
+    pub display: DisplayConfig,
+    /// Key bindings by action name; see [`crate::keymap`].
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub keys: BTreeMap<String, KeyBinding>,
+    /// A built-in theme and styles overriding it; see [`crate::theme`].
+    #[serde(default, skip_serializing_if = "ThemeConfig::is_empty")]
+    pub theme: ThemeConfig,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -32,9 +64,210 @@ impl Default for GeneralConfig {
     }
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EncryptionConfig {
+    #[serde(default)]
+    pub padding: PaddingMode,
+    #[serde(default = "default_padding_block_size")]
+    pub padding_block_size: usize,
+    /// Length of newly generated recovery phrases, 12 or 24 words.
+    #[serde(default = "default_phrase_words")]
+    pub phrase_words: usize,
+    /// Whether the phrase is combined with a BIP39 passphrase that is asked
+    /// for on every start. The passphrase itself is never stored.
+    #[serde(default)]
+    pub use_passphrase: bool,
+}
+
+impl Default for EncryptionConfig {
+    fn default() -> Self {
+        Self {
+            padding: PaddingMode::default(),
+            padding_block_size: DEFAULT_PADDING_BLOCK_SIZE,
+            phrase_words: DEFAULT_PHRASE_WORDS,
+            use_passphrase: false,
+        }
+    }
+}
+
+impl EncryptionConfig {
+    fn padding(&self) -> Padding {
+        match self.padding {
+            PaddingMode::None => Padding::None,
+            PaddingMode::PowerOfTwo => Padding::PowerOfTwo,
+            PaddingMode::Block => Padding::Block(self.padding_block_size.max(1)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NetworkConfig {
+    #[serde(default = "default_connect_timeout_secs")]
+    pub connect_timeout_secs: u64,
+    #[serde(default = "default_request_timeout_secs")]
+    pub request_timeout_secs: u64,
+    /// Retries for requests that are safe to repeat.
+    #[serde(default = "default_retries")]
+    pub retries: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proxy: Option<String>,
+    /// PEM file with extra root certificates, e.g. for a self-hosted server.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ca_certificate: Option<PathBuf>,
+}
+
+impl Default for NetworkConfig {
+    fn default() -> Self {
+        Self {
+            connect_timeout_secs: DEFAULT_CONNECT_TIMEOUT_SECS,
+            request_timeout_secs: DEFAULT_REQUEST_TIMEOUT_SECS,
+            retries: DEFAULT_RETRIES,
+            proxy: None,
+            ca_certificate: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DisplayConfig {
+    /// Open tasks due within this many hours are highlighted.
+    #[serde(default = "default_due_soon_hours")]
+    pub due_soon_hours: u64,
+}
+
+impl Default for DisplayConfig {
+    fn default() -> Self {
+        Self {
+            due_soon_hours: DEFAULT_DUE_SOON_HOURS,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct ThemeConfig {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub base: Option<String>,
+    #[serde(flatten)]
+    pub styles: BTreeMap<String, String>,
+}
+
+impl ThemeConfig {
+    fn is_empty(&self) -> bool {
+        self.base.is_none() && self.styles.is_empty()
+    }
+}
+
+/// One key or a list of keys for an action.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum KeyBinding {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl KeyBinding {
+    pub fn keys(&self) -> Vec<&str> {
+        match self {
+            Self::One(key) => vec![key.as_str()],
+            Self::Many(keys) => keys.iter().map(String::as_str).collect(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum PaddingMode {
+    #[default]
+    None,
+    PowerOfTwo,
+    Block,
+}
+
+fn default_padding_block_size() -> usize {
+    DEFAULT_PADDING_BLOCK_SIZE
+}
+
+fn default_phrase_words() -> usize {
+    DEFAULT_PHRASE_WORDS
+}
+
+fn default_connect_timeout_secs() -> u64 {
+    DEFAULT_CONNECT_TIMEOUT_SECS
+}
+
+fn default_request_timeout_secs() -> u64 {
+    DEFAULT_REQUEST_TIMEOUT_SECS
+}
+
+fn default_retries() -> u32 {
+    DEFAULT_RETRIES
+}
+
+fn default_due_soon_hours() -> u64 {
+    DEFAULT_DUE_SOON_HOURS
+}
+
 pub struct AppConfig {
     pub endpoint: String,
-    pub phrase: String,
+    pub phrase: SecretString,
+    pub passphrase: Option<SecretString>,
+    pub padding: Padding,
+    pub network: NetworkConfig,
+    pub display: DisplayConfig,
+    pub keymap: Keymap,
+    pub theme: Theme,
+}
+
+impl AppConfig {
+    pub fn crypto_key(&self) -> anyhow::Result<CryptoKey> {
+        let passphrase = self
+            .passphrase
+            .as_ref()
+            .map_or("", |passphrase| passphrase.expose_secret());
+        Ok(
+            CryptoKey::from_recovery_phrase_and_passphrase(
+                self.phrase.expose_secret(),
+                passphrase,
+            )?
+            .with_padding(self.padding),
+        )
+    }
+
+    pub fn client(&self) -> anyhow::Result<Client> {
+        self.client_with_key(self.crypto_key()?)
+    }
+
+    pub fn client_with_key(&self, crypto: CryptoKey) -> anyhow::Result<Client> {
+        Client::builder(self.endpoint.clone(), crypto)
+            .transport(self.network.transport()?)
+            .retry(RetryPolicy {
+                max_retries: self.network.retries,
+                ..RetryPolicy::default()
+            })
+            .build()
+    }
+}
+
+impl NetworkConfig {
+    pub fn transport(&self) -> anyhow::Result<Transport> {
+        let mut transport = Transport::default()
+            .connect_timeout(Duration::from_secs(self.connect_timeout_secs))
+            .request_timeout(Duration::from_secs(self.request_timeout_secs))
+            .user_agent(concat!("todo/", env!("CARGO_PKG_VERSION")));
+        if let Some(proxy) = &self.proxy {
+            transport = transport.proxy(proxy);
+        }
+        if let Some(path) = &self.ca_certificate {
+            transport = transport.root_certificate_pem(fs::read(path)?);
+        }
+        Ok(transport)
+    }
+}
+
+/// The `[network]` settings, without creating a config or an identity when
+/// there is none yet.
+pub fn network_config() -> anyhow::Result<NetworkConfig> {
+    Ok(read_config(&config_path()?)?.network)
 }
 
 pub fn load_or_create_config() -> anyhow::Result<AppConfig> {
@@ -52,18 +285,114 @@ pub fn load_or_create_config() -> anyhow::Result<AppConfig> {
         changed = true;
     }
 
+    if !SUPPORTED_WORD_COUNTS.contains(&config.encryption.phrase_words) {
+        anyhow::bail!("encryption.phrase_words must be 12 or 24");
+    }
+
+    let keymap = Keymap::new(&config.keys)?;
+    let theme = Theme::new(&config.theme)?;
+
     if changed || !path.exists() {
         write_config(&path, &config)?;
     }
 
-    let phrase = load_or_create_phrase(is_development())?;
+    let (phrase, created) =
+        load_or_create_phrase(is_development(), config.encryption.phrase_words)?;
+    let passphrase = if config.encryption.use_passphrase {
+        Some(load_passphrase()?)
+    } else if created {
+        let passphrase = prompt_for_new_passphrase()?;
+        if passphrase.is_some() {
+            config.encryption.use_passphrase = true;
+            write_config(&path, &config)?;
+        }
+        passphrase
+    } else {
+        None
+    };
 
     Ok(AppConfig {
+        padding: config.encryption.padding(),
         endpoint: config.general.endpoint,
         phrase,
+        passphrase,
+        network: config.network,
+        display: config.display,
+        keymap,
+        theme,
     })
 }
 
+/// Registers the client's identity with the server, asking for an invite code
+/// on first run when the server only accepts invited users.
+pub async fn ensure_registered(client: &Client) -> anyhow::Result<()> {
+    let mut status = client.register(None).await?;
+    if status == RegistrationStatus::InviteRequired {
+        println!("This server only accepts invited users.");
+    }
+
+    for _ in 0..INVITE_CODE_ATTEMPTS {
+        match status {
+            RegistrationStatus::Registered => return Ok(()),
+            RegistrationStatus::Closed => {
+                anyhow::bail!("this server is not accepting new users")
+            }
+            RegistrationStatus::InvalidInvite => {
+                println!("That invite code is invalid, expired or already used.");
+            }
+            RegistrationStatus::InviteRequired => {}
+        }
+
+        print!("Invite code: ");
+        io::stdout().flush()?;
+        let mut code = String::new();
+        io::stdin().read_line(&mut code)?;
+        let code = code.trim();
+        if code.is_empty() {
+            anyhow::bail!("an invite code is required to use this server");
+        }
+        status = client.register(Some(code)).await?;
+    }
+
+    match status {
+        RegistrationStatus::Registered => Ok(()),
+        _ => anyhow::bail!("could not register with an invite code"),
+    }
+}
+
+/// Stores a replacement recovery phrase in the keychain and records whether it
+/// is used with a passphrase.
+pub fn replace_identity(
+    phrase: &SecretString,
+    phrase_words: usize,
+    use_passphrase: bool,
+) -> anyhow::Result<()> {
+    let path = config_path()?;
+    let mut config = read_config(&path)?;
+    config.encryption.phrase_words = phrase_words;
+    config.encryption.use_passphrase = use_passphrase;
+
+    store_phrase_in_keychain(is_development(), phrase)?;
+    write_config(&path, &config)
+}
+
+/// Asks for an optional passphrase for a new phrase, twice to catch typos.
+pub fn prompt_for_new_passphrase() -> anyhow::Result<Option<SecretString>> {
+    println!("Optionally protect your phrase with a passphrase. It is never stored,");
+    println!("and you will be asked for it every time todo starts.");
+    let passphrase = read_hidden_line("Passphrase (Enter to skip): ")?;
+    if passphrase.expose_secret().is_empty() {
+        return Ok(None);
+    }
+
+    let confirmation = read_hidden_line("Repeat passphrase: ")?;
+    if confirmation.expose_secret() != passphrase.expose_secret() {
+        anyhow::bail!("passphrases did not match");
+    }
+
+    Ok(Some(passphrase))
+}
+
 fn read_config(path: &PathBuf) -> anyhow::Result<Config> {
     if !path.exists() {
         return Ok(Config::default());
@@ -81,55 +410,108 @@ fn write_config(path: &PathBuf, config: &Config) -> anyhow::Result<()> {
     Ok(())
 }
 
-fn load_or_create_phrase(is_development: bool) -> anyhow::Result<String> {
+/// Returns the recovery phrase and whether it was entered or generated just
+/// now.
+fn load_or_create_phrase(
+    is_development: bool,
+    phrase_words: usize,
+) -> anyhow::Result<(SecretString, bool)> {
     if let Ok(phrase) = std::env::var("TODO_PHRASE") {
-        return normalize_phrase_for_storage(&phrase)
-            .ok_or_else(|| anyhow::anyhow!("TODO_PHRASE must be a valid 12-word phrase"));
+        let phrase = SecretString::from(phrase);
+        return normalize_phrase_for_storage(phrase.expose_secret())
+            .map(|phrase| (phrase, false))
+            .ok_or_else(|| anyhow::anyhow!("TODO_PHRASE must be a valid 12- or 24-word phrase"));
     }
 
     if let Some(phrase) = load_phrase_from_keychain(is_development)?
-        && let Some(phrase) = normalize_phrase_for_storage(&phrase)
+        && let Some(phrase) = normalize_phrase_for_storage(phrase.expose_secret())
     {
-        return Ok(phrase);
+        return Ok((phrase, false));
     }
 
-    let phrase = prompt_for_recovery_phrase()?;
+    let phrase = prompt_for_recovery_phrase(phrase_words)?;
     store_phrase_in_keychain(is_development, &phrase)?;
-    Ok(phrase)
+    Ok((phrase, true))
+}
+
+fn load_passphrase() -> anyhow::Result<SecretString> {
+    if let Ok(passphrase) = std::env::var("TODO_PASSPHRASE") {
+        return Ok(passphrase.into());
+    }
+
+    read_hidden_line("Passphrase: ")
+}
+
+fn read_hidden_line(prompt: &str) -> anyhow::Result<SecretString> {
+    print!("{prompt}");
+    io::stdout().flush()?;
+
+    enable_raw_mode()?;
+    let input = read_hidden_chars();
+    disable_raw_mode()?;
+    println!();
+
+    input
 }
 
-fn load_phrase_from_keychain(is_development: bool) -> anyhow::Result<Option<String>> {
+fn read_hidden_chars() -> anyhow::Result<SecretString> {
+    let mut input = Zeroizing::new(String::new());
+    loop {
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+
+        match key.code {
+            KeyCode::Enter => return Ok(std::mem::take(&mut *input).into()),
+            KeyCode::Backspace => {
+                input.pop();
+            }
+            KeyCode::Esc => anyhow::bail!("passphrase entry cancelled"),
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                anyhow::bail!("passphrase entry cancelled")
+            }
+            KeyCode::Char(ch) => input.push(ch),
+            _ => {}
+        }
+    }
+}
+
+fn load_phrase_from_keychain(is_development: bool) -> anyhow::Result<Option<SecretString>> {
     let entry = Entry::new(KEYCHAIN_SERVICE, keychain_account(is_development))?;
     match entry.get_password() {
-        Ok(phrase) => Ok(Some(phrase)),
+        Ok(phrase) => Ok(Some(phrase.into())),
         Err(keyring::Error::NoEntry) => Ok(None),
         Err(err) => Err(err.into()),
     }
 }
 
-fn store_phrase_in_keychain(is_development: bool, phrase: &str) -> anyhow::Result<()> {
+fn store_phrase_in_keychain(is_development: bool, phrase: &SecretString) -> anyhow::Result<()> {
     let entry = Entry::new(KEYCHAIN_SERVICE, keychain_account(is_development))?;
-    entry.set_password(phrase)?;
+    entry.set_password(phrase.expose_secret())?;
     Ok(())
 }
 
-fn prompt_for_recovery_phrase() -> anyhow::Result<String> {
+fn prompt_for_recovery_phrase(phrase_words: usize) -> anyhow::Result<SecretString> {
     println!("Paste an existing recovery phrase, or press Enter to generate a new one:");
     print!("> ");
     io::stdout().flush()?;
 
-    let mut input = String::new();
+    let mut input = Zeroizing::new(String::new());
     io::stdin().read_line(&mut input)?;
-    let phrase = input.trim().to_string();
+    let phrase = input.trim();
     if !phrase.is_empty() {
-        return normalize_phrase_for_storage(&phrase)
-            .ok_or_else(|| anyhow::anyhow!("recovery phrase must be a valid 12-word phrase"));
+        return normalize_phrase_for_storage(phrase).ok_or_else(|| {
+            anyhow::anyhow!("recovery phrase must be a valid 12- or 24-word phrase")
+        });
     }
 
-    let phrase = generate_recovery_phrase();
+    let phrase = generate_recovery_phrase(phrase_words)?;
 
     println!("Recovery phrase generated for local encryption:\n");
-    println!("{phrase}\n");
+    println!("{}\n", phrase.expose_secret());
     println!(
         "Store this phrase somewhere safe. You need it to decrypt this data on another device."
     );
@@ -156,6 +538,26 @@ fn config_path() -> anyhow::Result<PathBuf> {
     Ok(xdg_config_home()?.join("todo").join(filename))
 }
 
+/// Where the TUI keeps per-device state such as collapsed tasks.
+pub fn state_path(name: &str) -> anyhow::Result<PathBuf> {
+    let filename = if is_development() {
+        format!("{name}.dev.toml")
+    } else {
+        format!("{name}.toml")
+    };
+    Ok(xdg_state_home()?.join("todo").join(filename))
+}
+
+fn xdg_state_home() -> anyhow::Result<PathBuf> {
+    if let Ok(path) = std::env::var("XDG_STATE_HOME")
+        && !path.trim().is_empty()
+    {
+        return Ok(PathBuf::from(path));
+    }
+    let home = std::env::var("HOME")?;
+    Ok(PathBuf::from(home).join(".local").join("state"))
+}
+
 fn xdg_config_home() -> anyhow::Result<PathBuf> {
     if let Ok(path) = std::env::var("XDG_CONFIG_HOME")
         && !path.trim().is_empty()
@@ -0,0 +1,365 @@
+//! Parsing of the due dates typed into the edit popup. Besides `YYYY-MM-DD`
+//! this understands:
+//!
+//! - `today`, `tomorrow`, `yesterday`
+//! - weekdays (`fri`, `friday`): the next such day, today included;
+//!   `next fri` is the one a week after that
+//! - offsets: `+3d`, `-1w`, `+2m`, `+1y`, `in 2 weeks`, `in a month`,
+//!   `next week`, `next month`, `next year`
+//! - month names with a day and optional year: `oct 20`, `20th october`,
+//!   `oct 20, 2027`; without a year the next such date is meant
+//!
+//! Any of these may end in a time in the local zone (`fri 14:00`,
+//! `tomorrow at 9am`); a time alone means today. Stored values, including
+//! RFC 3339 timestamps, are accepted as they are.
+
+use anyhow::{Context, Result, bail};
+use chrono::{Datelike, Days, Local, Months, NaiveDate, NaiveTime, Weekday};
+use todo_client::Due;
+
+const DATE_FORMAT: &str = "%Y-%m-%d";
+
+/// Resolves `input` relative to `today`.
+pub fn parse(input: &str, today: NaiveDate) -> Result<Due> {
+    if let Some(due) = Due::parse(input) {
+        return Ok(due);
+    }
+
+    let input = input.trim().to_lowercase();
+    let mut words: Vec<&str> = input
+        .split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|word| !word.is_empty())
+        .collect();
+    let time = take_time(&mut words)?;
+    let date = if words.is_empty() && time.is_some() {
+        today
+    } else {
+        parse_date(&words, today)?
+    };
+
+    match time {
+        None => Ok(Due::Date(date)),
+        Some(time) => Due::local(date.and_time(time))
+            .with_context(|| format!("{time} does not exist on {date} here")),
+    }
+}
+
+/// Formats a resolved due date for the preview under the input.
+pub fn describe(due: &Due, today: NaiveDate) -> String {
+    let date = due.local_date();
+    let relative = match (date - today).num_days() {
+        0 => "today".to_string(),
+        1 => "tomorrow".to_string(),
+        -1 => "yesterday".to_string(),
+        days if days < 0 => format!("{} days ago", -days),
+        days => format!("in {days} days"),
+    };
+    let day = date.format("%a %-d %b %Y");
+    match due {
+        Due::Date(_) => format!("{day} ({relative})"),
+        Due::At(at) => format!(
+            "{day} {} ({relative})",
+            at.with_timezone(&Local).format("%H:%M")
+        ),
+    }
+}
+
+/// Removes a trailing time (`14:00`, `9am`, `9:30 pm`, `at noon`) from
+/// `words` and returns it.
+fn take_time(words: &mut Vec<&str>) -> Result<Option<NaiveTime>> {
+    let time = match words.as_slice() {
+        [.., hour, meridiem @ ("am" | "pm")] => {
+            let time = parse_time(&format!("{hour}{meridiem}"))?;
+            words.truncate(words.len() - 2);
+            time
+        }
+        [.., word] => match parse_time(word)? {
+            Some(time) => {
+                words.pop();
+                Some(time)
+            }
+            None => return Ok(None),
+        },
+        [] => return Ok(None),
+    };
+    if words.last() == Some(&"at") {
+        words.pop();
+    }
+    Ok(time)
+}
+
+/// `Ok(None)` when `word` does not look like a time at all.
+fn parse_time(word: &str) -> Result<Option<NaiveTime>> {
+    match word {
+        "noon" => return Ok(NaiveTime::from_hms_opt(12, 0, 0)),
+        "midnight" => return Ok(Some(NaiveTime::MIN)),
+        _ => {}
+    }
+    let (clock, offset) = if let Some(clock) = word.strip_suffix("am") {
+        (clock, Some(0))
+    } else if let Some(clock) = word.strip_suffix("pm") {
+        (clock, Some(12))
+    } else if word.contains(':') {
+        (word, None)
+    } else {
+        return Ok(None);
+    };
+    let (hour, minute) = clock.split_once(':').unwrap_or((clock, "0"));
+    let (Ok(hour), Ok(minute)) = (hour.parse::<u32>(), minute.parse::<u32>()) else {
+        bail!("\"{word}\" is not a time like 14:00 or 2pm");
+    };
+    let hour = match offset {
+        Some(_) if !(1..=12).contains(&hour) => bail!("\"{word}\" is not a time"),
+        Some(offset) => hour % 12 + offset,
+        None => hour,
+    };
+    NaiveTime::from_hms_opt(hour, minute, 0)
+        .map(Some)
+        .with_context(|| format!("\"{word}\" is not a time"))
+}
+
+fn parse_date(words: &[&str], today: NaiveDate) -> Result<NaiveDate> {
+    match words {
+        [] => bail!("empty date"),
+        ["today" | "tod"] => Ok(today),
+        ["tomorrow" | "tmr" | "tom"] => Ok(today + Days::new(1)),
+        ["yesterday"] => Ok(today - Days::new(1)),
+        [word] if word.starts_with(['+', '-']) => parse_offset(word, today),
+        ["next", unit @ ("week" | "month" | "year")] => offset(today, 1, unit),
+        ["next", day] => weekday(day)
+            .map(|day| upcoming(today, day) + Days::new(7))
+            .with_context(|| format!("unknown weekday \"{day}\"")),
+        ["in", count, unit] => {
+            let count = match *count {
+                "a" | "an" | "one" => 1,
+                count => count
+                    .parse()
+                    .with_context(|| format!("\"{count}\" is not a number"))?,
+            };
+            offset(today, count, unit)
+        }
+        [word] => match weekday(word) {
+            Some(day) => Ok(upcoming(today, day)),
+            None => NaiveDate::parse_from_str(word, DATE_FORMAT)
+                .with_context(|| format!("could not understand \"{word}\"")),
+        },
+        _ => parse_month_day(words, today),
+    }
+}
+
+fn parse_offset(word: &str, today: NaiveDate) -> Result<NaiveDate> {
+    let (sign, rest) = word.split_at(1);
+    let digits = rest.trim_end_matches(char::is_alphabetic);
+    let unit = match &rest[digits.len()..] {
+        "" => "d",
+        unit => unit,
+    };
+    let count: i64 = digits
+        .parse()
+        .with_context(|| format!("\"{word}\" is not an offset like +3d"))?;
+    offset(today, if sign == "-" { -count } else { count }, unit)
+}
+
+fn offset(today: NaiveDate, count: i64, unit: &str) -> Result<NaiveDate> {
+    let magnitude = count.unsigned_abs();
+    let date = match unit {
+        "d" | "day" | "days" => shift_days(today, count, magnitude),
+        "w" | "week" | "weeks" => shift_days(today, count, magnitude.saturating_mul(7)),
+        "m" | "month" | "months" => shift_months(today, count, magnitude),
+        "y" | "year" | "years" => shift_months(today, count, magnitude.saturating_mul(12)),
+        unit => bail!("unknown unit \"{unit}\", use days, weeks, months or years"),
+    };
+    date.context("date out of range")
+}
+
+fn shift_days(today: NaiveDate, count: i64, days: u64) -> Option<NaiveDate> {
+    if count < 0 {
+        today.checked_sub_days(Days::new(days))
+    } else {
+        today.checked_add_days(Days::new(days))
+    }
+}
+
+fn shift_months(today: NaiveDate, count: i64, months: u64) -> Option<NaiveDate> {
+    let months = Months::new(u32::try_from(months).ok()?);
+    if count < 0 {
+        today.checked_sub_months(months)
+    } else {
+        today.checked_add_months(months)
+    }
+}
+
+fn weekday(word: &str) -> Option<Weekday> {
+    Some(match word {
+        "mon" | "monday" => Weekday::Mon,
+        "tue" | "tues" | "tuesday" => Weekday::Tue,
+        "wed" | "wednesday" => Weekday::Wed,
+        "thu" | "thur" | "thurs" | "thursday" => Weekday::Thu,
+        "fri" | "friday" => Weekday::Fri,
+        "sat" | "saturday" => Weekday::Sat,
+        "sun" | "sunday" => Weekday::Sun,
+        _ => return None,
+    })
+}
+
+/// The next `day` on or after `today`.
+fn upcoming(today: NaiveDate, day: Weekday) -> NaiveDate {
+    let ahead = (7 + day.num_days_from_monday() - today.weekday().num_days_from_monday()) % 7;
+    today + Days::new(ahead.into())
+}
+
+fn month(word: &str) -> Option<u32> {
+    const MONTHS: [&str; 12] = [
+        "january",
+        "february",
+        "march",
+        "april",
+        "may",
+        "june",
+        "july",
+        "august",
+        "september",
+        "october",
+        "november",
+        "december",
+    ];
+    if word.len() < 3 {
+        return None;
+    }
+    let word = word.strip_suffix('.').unwrap_or(word);
+    let index = MONTHS.iter().position(|name| name.starts_with(word))?;
+    Some(index as u32 + 1)
+}
+
+fn day_of_month(word: &str) -> Option<u32> {
+    let digits = ["st", "nd", "rd", "th"]
+        .iter()
+        .find_map(|suffix| word.strip_suffix(suffix))
+        .unwrap_or(word);
+    digits.parse().ok().filter(|day| (1..=31).contains(day))
+}
+
+fn parse_month_day(words: &[&str], today: NaiveDate) -> Result<NaiveDate> {
+    let (month, day, year) = match words {
+        [first, second, rest @ ..] if rest.len() <= 1 => {
+            let (month, day) = match (month(first), day_of_month(second)) {
+                (Some(month), Some(day)) => (month, day),
+                _ => match (day_of_month(first), month(second)) {
+                    (Some(day), Some(month)) => (month, day),
+                    _ => bail!("could not understand \"{}\"", words.join(" ")),
+                },
+            };
+            let year = match rest {
+                [year] => Some(
+                    year.parse::<i32>()
+                        .with_context(|| format!("\"{year}\" is not a year"))?,
+                ),
+                _ => None,
+            };
+            (month, day, year)
+        }
+        _ => bail!("could not understand \"{}\"", words.join(" ")),
+    };
+
+    match year {
+        Some(year) => NaiveDate::from_ymd_opt(year, month, day)
+            .with_context(|| format!("{year}-{month:02}-{day:02} is not a date")),
+        None => {
+            // The next such date, skipping years where it does not exist
+            // (29 February).
+            (today.year()..today.year() + 8)
+                .filter_map(|year| NaiveDate::from_ymd_opt(year, month, day))
+                .find(|date| *date >= today)
+                .with_context(|| format!("day {day} does not exist in that month"))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(text: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(text, DATE_FORMAT).unwrap()
+    }
+
+    fn time(hour: u32, minute: u32) -> Option<NaiveTime> {
+        NaiveTime::from_hms_opt(hour, minute, 0)
+    }
+
+    fn take(input: &str) -> (Option<NaiveTime>, Vec<&str>) {
+        let mut words: Vec<&str> = input.split_whitespace().collect();
+        let time = take_time(&mut words).unwrap();
+        (time, words)
+    }
+
+    #[test]
+    fn takes_times_split_from_their_meridiem() {
+        assert_eq!(take("fri 9 pm"), (time(21, 0), vec!["fri"]));
+        assert_eq!(take("tomorrow at 9:30 am"), (time(9, 30), vec!["tomorrow"]));
+        assert_eq!(take("oct 20 12 am"), (time(0, 0), vec!["oct", "20"]));
+        assert_eq!(take("12pm"), (time(12, 0), vec![]));
+        assert_eq!(take("at noon"), (time(12, 0), vec![]));
+    }
+
+    #[test]
+    fn leaves_words_without_a_time_alone() {
+        assert_eq!(take("next fri"), (None, vec!["next", "fri"]));
+        assert_eq!(take("oct 20"), (None, vec!["oct", "20"]));
+        assert!(take_time(&mut vec!["13", "pm"]).is_err());
+        assert!(take_time(&mut vec!["25:00"]).is_err());
+    }
+
+    #[test]
+    fn upcoming_includes_today() {
+        let wednesday = date("2026-10-14");
+        assert_eq!(upcoming(wednesday, Weekday::Wed), wednesday);
+        assert_eq!(upcoming(wednesday, Weekday::Fri), date("2026-10-16"));
+        assert_eq!(upcoming(wednesday, Weekday::Tue), date("2026-10-20"));
+    }
+
+    #[test]
+    fn next_weekday_is_a_week_after_the_upcoming_one() {
+        let wednesday = date("2026-10-14");
+        assert_eq!(parse_date(&["fri"], wednesday).unwrap(), date("2026-10-16"));
+        assert_eq!(parse_date(&["wed"], wednesday).unwrap(), wednesday);
+        assert_eq!(
+            parse_date(&["next", "fri"], wednesday).unwrap(),
+            date("2026-10-23")
+        );
+        assert_eq!(
+            parse_date(&["next", "wed"], wednesday).unwrap(),
+            date("2026-10-21")
+        );
+        assert!(parse_date(&["next", "fry"], wednesday).is_err());
+    }
+
+    #[test]
+    fn month_days_without_a_year_are_the_next_one() {
+        let today = date("2026-10-14");
+        assert_eq!(
+            parse_month_day(&["oct", "20"], today).unwrap(),
+            date("2026-10-20")
+        );
+        assert_eq!(parse_month_day(&["14th", "october"], today).unwrap(), today);
+        assert_eq!(
+            parse_month_day(&["oct", "13"], today).unwrap(),
+            date("2027-10-13")
+        );
+    }
+
+    #[test]
+    fn the_29th_of_february_waits_for_a_leap_year() {
+        let today = date("2026-10-14");
+        assert_eq!(
+            parse_month_day(&["feb", "29"], today).unwrap(),
+            date("2028-02-29")
+        );
+        assert_eq!(
+            parse_month_day(&["29th", "february"], date("2028-02-29")).unwrap(),
+            date("2028-02-29")
+        );
+        assert!(parse_month_day(&["feb", "29", "2027"], today).is_err());
+        assert!(parse_month_day(&["feb", "30"], today).is_err());
+    }
+}
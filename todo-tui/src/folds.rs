@@ -0,0 +1,69 @@
+//! Which subtask trees are collapsed. Kept on this device only, by task id,
+//! in a state file next to other local TUI state.
+
+use std::{collections::BTreeSet, fs, path::PathBuf};
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::config;
+
+#[derive(Default, Serialize, Deserialize)]
+struct FoldsFile {
+    #[serde(default)]
+    collapsed: BTreeSet<i64>,
+}
+
+#[derive(Default)]
+pub struct Folds {
+    /// `None` when there is nowhere to save, which keeps folds for the
+    /// session only.
+    path: Option<PathBuf>,
+    collapsed: BTreeSet<i64>,
+}
+
+impl Folds {
+    pub fn load() -> Result<Self> {
+        let path = config::state_path("folds")?;
+        let file: FoldsFile = if path.exists() {
+            toml::from_str(&fs::read_to_string(&path)?)?
+        } else {
+            FoldsFile::default()
+        };
+        Ok(Self {
+            path: Some(path),
+            collapsed: file.collapsed,
+        })
+    }
+
+    pub fn is_collapsed(&self, task_id: i64) -> bool {
+        self.collapsed.contains(&task_id)
+    }
+
+    /// Collapses or expands `task_ids`, saving when anything changed.
+    pub fn set(&mut self, task_ids: impl IntoIterator<Item = i64>, collapsed: bool) -> Result<()> {
+        let mut changed = false;
+        for task_id in task_ids {
+            changed |= if collapsed {
+                self.collapsed.insert(task_id)
+            } else {
+                self.collapsed.remove(&task_id)
+            };
+        }
+        if changed { self.save() } else { Ok(()) }
+    }
+
+    fn save(&self) -> Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let file = FoldsFile {
+            collapsed: self.collapsed.clone(),
+        };
+        fs::write(path, toml::to_string_pretty(&file)?)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,451 @@
+//! Undo and redo for the TUI. Every mutation records the change that reverts
+//! it; applying a change performs it against the server and hands back the
+//! change that reverts *that*, so the same entries move between the undo and
+//! redo stacks.
+
+use std::collections::HashMap;
+
+use anyhow::Result;
+use todo_client::{Client, Priority, Recurrence, Task};
+
+/// Entries kept on each stack; older ones are dropped.
+const HISTORY_LIMIT: usize = 100;
+
+/// A task as it was when it was removed, enough to create it again.
+#[derive(Debug, Clone)]
+pub struct TaskSnapshot {
+    pub id: i64,
+    pub parent_task_id: Option<i64>,
+    pub title: String,
+    pub due_date: Option<String>,
+    pub recurrence: Option<Recurrence>,
+    pub priority: Priority,
+    pub tags: Vec<String>,
+    pub completed: bool,
+}
+
+impl TaskSnapshot {
+    pub fn new(task: &Task) -> Self {
+        Self {
+            id: task.id,
+            parent_task_id: task.parent_task_id,
+            title: task.title.clone(),
+            due_date: task.due_date.clone(),
+            recurrence: task.recurrence(),
+            priority: task.priority(),
+            tags: task.tags(),
+            completed: task.completed,
+        }
+    }
+
+    /// Snapshots `root_id` and its descendants among `tasks`, parents first.
+    pub fn subtree(tasks: &[Task], root_id: i64) -> Vec<Self> {
+        let mut snapshots = Vec::new();
+        let mut pending = vec![root_id];
+        while let Some(id) = pending.pop() {
+            let Some(task) = tasks.iter().find(|task| task.id == id) else {
+                continue;
+            };
+            snapshots.push(Self::new(task));
+            let mut children: Vec<i64> = tasks
+                .iter()
+                .filter(|task| task.parent_task_id == Some(id))
+                .map(|task| task.id)
+                .collect();
+            // Popped in ascending order, so siblings are recreated in the
+            // order they were first created.
+            children.sort_unstable_by(|a, b| b.cmp(a));
+            pending.extend(children);
+        }
+        snapshots
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum Change {
+    ToggleTask {
+        task_id: i64,
+    },
+    /// Edits hold `(current, target)` pairs; the inverse swaps them.
+    EditTask {
+        workspace_id: i64,
+        task_id: i64,
+        title: (String, String),
+        due_date: (Option<String>, Option<String>),
+        recurrence: (Option<Recurrence>, Option<Recurrence>),
+        tags: (Vec<String>, Vec<String>),
+    },
+    SetRecurrence {
+        workspace_id: i64,
+        task_id: i64,
+        recurrence: (Option<Recurrence>, Option<Recurrence>),
+    },
+    SetPriority {
+        workspace_id: i64,
+        task_id: i64,
+        priority: (Priority, Priority),
+    },
+    RenameWorkspace {
+        workspace_id: i64,
+        name: (String, String),
+    },
+    /// Re-archives completed tasks; the server reports which ones it took.
+    ArchiveCompleted {
+        workspace_id: i64,
+    },
+    UnarchiveTasks {
+        workspace_id: i64,
+        task_ids: Vec<i64>,
+    },
+    /// Deletes a task subtree; `tasks` is its snapshot, root first.
+    DeleteTasks {
+        workspace_id: i64,
+        tasks: Vec<TaskSnapshot>,
+    },
+    RestoreTasks {
+        workspace_id: i64,
+        tasks: Vec<TaskSnapshot>,
+    },
+    DeleteWorkspace {
+        workspace_id: i64,
+        name: String,
+        tasks: Vec<TaskSnapshot>,
+    },
+    /// Recreates a workspace with its active tasks. Members are not
+    /// restored: the new workspace has a new key that only we hold.
+    RestoreWorkspace {
+        workspace_id: i64,
+        name: String,
+        tasks: Vec<TaskSnapshot>,
+    },
+    /// Several changes made by one action, applied in order.
+    Sequence(Vec<Change>),
+}
+
+struct Entry {
+    description: String,
+    change: Change,
+}
+
+/// Ids of recreated items, old to new, so older entries still find them.
+#[derive(Default)]
+struct IdMap {
+    tasks: HashMap<i64, i64>,
+    workspaces: HashMap<i64, i64>,
+}
+
+impl IdMap {
+    fn task(&self, mut id: i64) -> i64 {
+        while let Some(&next) = self.tasks.get(&id) {
+            id = next;
+        }
+        id
+    }
+
+    fn workspace(&self, mut id: i64) -> i64 {
+        while let Some(&next) = self.workspaces.get(&id) {
+            id = next;
+        }
+        id
+    }
+}
+
+#[derive(Default)]
+pub struct History {
+    undo: Vec<Entry>,
+    redo: Vec<Entry>,
+    ids: IdMap,
+}
+
+impl History {
+    /// Records a mutation that just succeeded. `description` names what the
+    /// user did; `undo` reverts it.
+    pub fn record(&mut self, description: String, undo: Change) {
+        push_bounded(
+            &mut self.undo,
+            Entry {
+                description,
+                change: undo,
+            },
+        );
+        self.redo.clear();
+    }
+
+    /// Adds `undo` in front of the latest entry's undo, for changes made
+    /// after it on its behalf.
+    pub fn extend_latest(&mut self, undo: Change) {
+        if let Some(entry) = self.undo.last_mut() {
+            let latest = std::mem::replace(&mut entry.change, Change::Sequence(Vec::new()));
+            entry.change = Change::Sequence(vec![undo, latest]);
+        }
+    }
+
+    /// Reverts the latest mutation and returns its description, or `None`
+    /// when there is nothing to undo. A failed entry is dropped.
+    pub async fn undo(&mut self, client: &Client) -> Result<Option<String>> {
+        let Some(entry) = self.undo.pop() else {
+            return Ok(None);
+        };
+        let redo = apply(entry.change, client, &mut self.ids).await?;
+        push_bounded(
+            &mut self.redo,
+            Entry {
+                description: entry.description.clone(),
+                change: redo,
+            },
+        );
+        Ok(Some(entry.description))
+    }
+
+    /// Performs the latest undone mutation again.
+    pub async fn redo(&mut self, client: &Client) -> Result<Option<String>> {
+        let Some(entry) = self.redo.pop() else {
+            return Ok(None);
+        };
+        let undo = apply(entry.change, client, &mut self.ids).await?;
+        push_bounded(
+            &mut self.undo,
+            Entry {
+                description: entry.description.clone(),
+                change: undo,
+            },
+        );
+        Ok(Some(entry.description))
+    }
+}
+
+fn push_bounded(stack: &mut Vec<Entry>, entry: Entry) {
+    if stack.len() == HISTORY_LIMIT {
+        stack.remove(0);
+    }
+    stack.push(entry);
+}
+
+/// Performs `change` and returns the change that reverts it.
+async fn apply(change: Change, client: &Client, ids: &mut IdMap) -> Result<Change> {
+    Ok(match change {
+        Change::ToggleTask { task_id } => {
+            let task_id = ids.task(task_id);
+            client.toggle_task_completion(task_id).await?;
+            Change::ToggleTask { task_id }
+        }
+        Change::EditTask {
+            workspace_id,
+            task_id,
+            title: (current_title, title),
+            due_date: (current_due_date, due_date),
+            recurrence: (current_recurrence, recurrence),
+            tags: (current_tags, tags),
+        } => {
+            let workspace_id = ids.workspace(workspace_id);
+            let task_id = ids.task(task_id);
+            client
+                .update_task_name(workspace_id, task_id, &title)
+                .await?;
+            client
+                .update_task_due_date(workspace_id, task_id, due_date.as_deref())
+                .await?;
+            if recurrence != current_recurrence {
+                client
+                    .update_task_recurrence(workspace_id, task_id, recurrence.as_ref())
+                    .await?;
+            }
+            if tags != current_tags {
+                client
+                    .update_task_tags(workspace_id, task_id, &tags)
+                    .await?;
+            }
+            Change::EditTask {
+                workspace_id,
+                task_id,
+                title: (title, current_title),
+                due_date: (due_date, current_due_date),
+                recurrence: (recurrence, current_recurrence),
+                tags: (tags, current_tags),
+            }
+        }
+        Change::SetRecurrence {
+            workspace_id,
+            task_id,
+            recurrence: (current, recurrence),
+        } => {
+            let workspace_id = ids.workspace(workspace_id);
+            let task_id = ids.task(task_id);
+            client
+                .update_task_recurrence(workspace_id, task_id, recurrence.as_ref())
+                .await?;
+            Change::SetRecurrence {
+                workspace_id,
+                task_id,
+                recurrence: (recurrence, current),
+            }
+        }
+        Change::SetPriority {
+            workspace_id,
+            task_id,
+            priority: (current, priority),
+        } => {
+            let workspace_id = ids.workspace(workspace_id);
+            let task_id = ids.task(task_id);
+            client
+                .update_task_priority(workspace_id, task_id, priority)
+                .await?;
+            Change::SetPriority {
+                workspace_id,
+                task_id,
+                priority: (priority, current),
+            }
+        }
+        Change::RenameWorkspace {
+            workspace_id,
+            name: (current, name),
+        } => {
+            let workspace_id = ids.workspace(workspace_id);
+            client.update_workspace_name(workspace_id, &name).await?;
+            Change::RenameWorkspace {
+                workspace_id,
+                name: (name, current),
+            }
+        }
+        Change::ArchiveCompleted { workspace_id } => {
+            let workspace_id = ids.workspace(workspace_id);
+            let task_ids = client.archive_completed_tasks(workspace_id).await?;
+            Change::UnarchiveTasks {
+                workspace_id,
+                task_ids,
+            }
+        }
+        Change::UnarchiveTasks {
+            workspace_id,
+            task_ids,
+        } => {
+            let workspace_id = ids.workspace(workspace_id);
+            let task_ids: Vec<i64> = task_ids.into_iter().map(|id| ids.task(id)).collect();
+            client.unarchive_tasks(workspace_id, &task_ids).await?;
+            Change::ArchiveCompleted { workspace_id }
+        }
+        Change::DeleteTasks {
+            workspace_id,
+            tasks,
+        } => {
+            let workspace_id = ids.workspace(workspace_id);
+            let tasks = remap_tasks(tasks, ids);
+            if let Some(root) = tasks.first() {
+                client.delete_task(root.id).await?;
+            }
+            Change::RestoreTasks {
+                workspace_id,
+                tasks,
+            }
+        }
+        Change::RestoreTasks {
+            workspace_id,
+            tasks,
+        } => {
+            let workspace_id = ids.workspace(workspace_id);
+            let tasks = restore_tasks(client, workspace_id, tasks, ids).await?;
+            Change::DeleteTasks {
+                workspace_id,
+                tasks,
+            }
+        }
+        Change::DeleteWorkspace {
+            workspace_id,
+            name,
+            tasks,
+        } => {
+            let workspace_id = ids.workspace(workspace_id);
+            client.delete_workspace(workspace_id).await?;
+            Change::RestoreWorkspace {
+                workspace_id,
+                name,
+                tasks: remap_tasks(tasks, ids),
+            }
+        }
+        Change::RestoreWorkspace {
+            workspace_id,
+            name,
+            tasks,
+        } => {
+            let new_id = client.create_workspace(&name).await?;
+            ids.workspaces.insert(ids.workspace(workspace_id), new_id);
+            let tasks = restore_tasks(client, new_id, tasks, ids).await?;
+            Change::DeleteWorkspace {
+                workspace_id: new_id,
+                name,
+                tasks,
+            }
+        }
+        Change::Sequence(changes) => {
+            let mut inverses = Vec::with_capacity(changes.len());
+            for change in changes {
+                inverses.push(Box::pin(apply(change, client, ids)).await?);
+            }
+            inverses.reverse();
+            Change::Sequence(inverses)
+        }
+    })
+}
+
+fn remap_tasks(tasks: Vec<TaskSnapshot>, ids: &IdMap) -> Vec<TaskSnapshot> {
+    tasks
+        .into_iter()
+        .map(|task| TaskSnapshot {
+            id: ids.task(task.id),
+            parent_task_id: task.parent_task_id.map(|id| ids.task(id)),
+            ..task
+        })
+        .collect()
+}
+
+/// Creates `tasks` (parents first) in a workspace and returns them with
+/// their new ids.
+async fn restore_tasks(
+    client: &Client,
+    workspace_id: i64,
+    tasks: Vec<TaskSnapshot>,
+    ids: &mut IdMap,
+) -> Result<Vec<TaskSnapshot>> {
+    let mut restored = Vec::with_capacity(tasks.len());
+    for task in tasks {
+        let parent_task_id = task.parent_task_id.map(|id| ids.task(id));
+        let id = match parent_task_id {
+            Some(parent_task_id) => {
+                client
+                    .create_subtask(&task.title, workspace_id, parent_task_id)
+                    .await?
+            }
+            None => client.create_task(&task.title, workspace_id).await?,
+        };
+        ids.tasks.insert(ids.task(task.id), id);
+        if task.due_date.is_some() {
+            client
+                .update_task_due_date(workspace_id, id, task.due_date.as_deref())
+                .await?;
+        }
+        if let Some(recurrence) = &task.recurrence {
+            client
+                .update_task_recurrence(workspace_id, id, Some(recurrence))
+                .await?;
+        }
+        if task.priority != Priority::None {
+            client
+                .update_task_priority(workspace_id, id, task.priority)
+                .await?;
+        }
+        if !task.tags.is_empty() {
+            client
+                .update_task_tags(workspace_id, id, &task.tags)
+                .await?;
+        }
+        if task.completed {
+            client.toggle_task_completion(id).await?;
+        }
+        restored.push(TaskSnapshot {
+            id,
+            parent_task_id,
+            ..task
+        });
+    }
+    Ok(restored)
+}
@@ -0,0 +1,527 @@
+//! Key bindings for the task and workspace lists. Every action has default
+//! keys; the `[keys]` table in the config replaces them per action:
+//!
+//! ```toml
+//! [keys]
+//! redo = "ctrl-y"
+//! down = ["j", "down", "ctrl-n"]
+//! toggle-agenda = "g a"
+//! ```
+//!
+//! A key is a character or a name such as `enter`, `space` or `f1`, with
+//! optional `ctrl-`, `alt-` and `shift-` prefixes. Keys separated by spaces
+//! form a sequence that is typed one key after the other.
+
+use std::{collections::BTreeMap, fmt};
+
+use anyhow::{Context, Result, bail};
+use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+use crate::config::KeyBinding;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Action {
+    Down,
+    Up,
+    FocusWorkspaces,
+    FocusTasks,
+    SwitchFocus,
+    Search,
+    ToggleAgenda,
+    FilterByTag,
+    OpenTaskWorkspace,
+    Back,
+    Help,
+    Quit,
+    AddTask,
+    AddSubtask,
+    Edit,
+    ToggleCompletion,
+    RaisePriority,
+    LowerPriority,
+    ToggleSort,
+    ToggleFold,
+    FoldAll,
+    UnfoldAll,
+    Archive,
+    Delete,
+    Share,
+    Inspect,
+    Undo,
+    Redo,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Section {
+    Navigation,
+    Actions,
+}
+
+impl Action {
+    pub const ALL: [Self; 28] = [
+        Self::Down,
+        Self::Up,
+        Self::FocusWorkspaces,
+        Self::FocusTasks,
+        Self::SwitchFocus,
+        Self::Search,
+        Self::ToggleAgenda,
+        Self::FilterByTag,
+        Self::OpenTaskWorkspace,
+        Self::Back,
+        Self::Help,
+        Self::Quit,
+        Self::AddTask,
+        Self::AddSubtask,
+        Self::Edit,
+        Self::ToggleCompletion,
+        Self::RaisePriority,
+        Self::LowerPriority,
+        Self::ToggleSort,
+        Self::ToggleFold,
+        Self::FoldAll,
+        Self::UnfoldAll,
+        Self::Archive,
+        Self::Delete,
+        Self::Share,
+        Self::Inspect,
+        Self::Undo,
+        Self::Redo,
+    ];
+
+    /// The action's key in the `[keys]` table.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Down => "down",
+            Self::Up => "up",
+            Self::FocusWorkspaces => "focus-workspaces",
+            Self::FocusTasks => "focus-tasks",
+            Self::SwitchFocus => "switch-focus",
+            Self::Search => "search",
+            Self::ToggleAgenda => "toggle-agenda",
+            Self::FilterByTag => "filter-by-tag",
+            Self::OpenTaskWorkspace => "open-task-workspace",
+            Self::Back => "back",
+            Self::Help => "help",
+            Self::Quit => "quit",
+            Self::AddTask => "add-task",
+            Self::AddSubtask => "add-subtask",
+            Self::Edit => "edit",
+            Self::ToggleCompletion => "toggle-completion",
+            Self::RaisePriority => "raise-priority",
+            Self::LowerPriority => "lower-priority",
+            Self::ToggleSort => "toggle-sort",
+            Self::ToggleFold => "toggle-fold",
+            Self::FoldAll => "fold-all",
+            Self::UnfoldAll => "unfold-all",
+            Self::Archive => "archive",
+            Self::Delete => "delete",
+            Self::Share => "share",
+            Self::Inspect => "inspect",
+            Self::Undo => "undo",
+            Self::Redo => "redo",
+        }
+    }
+
+    pub fn description(self) -> &'static str {
+        match self {
+            Self::Down => "move down",
+            Self::Up => "move up",
+            Self::FocusWorkspaces => "focus workspaces",
+            Self::FocusTasks => "focus tasks",
+            Self::SwitchFocus => "switch focus",
+            Self::Search => "search tasks",
+            Self::ToggleAgenda => "agenda of open tasks in all workspaces",
+            Self::FilterByTag => "tasks with a tag in all workspaces",
+            Self::OpenTaskWorkspace => "go to the task's workspace (agenda, tags)",
+            Self::Back => "clear search, leave agenda or tag view",
+            Self::Help => "show/hide this help",
+            Self::Quit => "quit",
+            Self::AddTask => "add task (#tag and @context words become tags)",
+            Self::AddSubtask => "add subtask (on tasks) or workspace",
+            Self::Edit => "edit title, due date, repeat rule and tags",
+            Self::ToggleCompletion => "complete/reopen task (repeating tasks recur)",
+            Self::RaisePriority => "raise task priority",
+            Self::LowerPriority => "lower task priority",
+            Self::ToggleSort => "reverse creation-date sort (within priority)",
+            Self::ToggleFold => "fold/unfold subtasks (on a subtask: its parent)",
+            Self::FoldAll => "fold all subtask trees",
+            Self::UnfoldAll => "unfold all subtask trees",
+            Self::Archive => "archive completed tasks",
+            Self::Delete => "delete selected item",
+            Self::Share => "publish read-only share link for workspace",
+            Self::Inspect => "inspect an item that could not be decrypted",
+            Self::Undo => "undo last change",
+            Self::Redo => "redo",
+        }
+    }
+
+    pub fn section(self) -> Section {
+        if self <= Self::Quit {
+            Section::Navigation
+        } else {
+            Section::Actions
+        }
+    }
+
+    fn default_keys(self) -> &'static [&'static str] {
+        match self {
+            Self::Down => &["j", "down"],
+            Self::Up => &["k", "up"],
+            Self::FocusWorkspaces => &["h", "left"],
+            Self::FocusTasks => &["l", "right"],
+            Self::SwitchFocus => &["tab"],
+            Self::Search => &["/"],
+            Self::ToggleAgenda => &["g"],
+            Self::FilterByTag => &["#"],
+            Self::OpenTaskWorkspace => &["enter"],
+            Self::Back => &["esc"],
+            Self::Help => &["?"],
+            Self::Quit => &["q"],
+            Self::AddTask => &["a"],
+            Self::AddSubtask => &["A"],
+            Self::Edit => &["e"],
+            Self::ToggleCompletion => &["c", "space"],
+            Self::RaisePriority => &["+", "="],
+            Self::LowerPriority => &["-"],
+            Self::ToggleSort => &["s"],
+            Self::ToggleFold => &["z"],
+            Self::FoldAll => &["["],
+            Self::UnfoldAll => &["]"],
+            Self::Archive => &["x"],
+            Self::Delete => &["D"],
+            Self::Share => &["P"],
+            Self::Inspect => &["i"],
+            Self::Undo => &["u"],
+            Self::Redo => &["ctrl-r"],
+        }
+    }
+}
+
+/// One key press. Shifted characters are stored as the character they
+/// produce, so `A` and `shift-a` are the same key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyChord {
+    code: KeyCode,
+    modifiers: KeyModifiers,
+}
+
+impl KeyChord {
+    fn new(code: KeyCode, mut modifiers: KeyModifiers) -> Self {
+        let code = match code {
+            KeyCode::Char(c) if modifiers.contains(KeyModifiers::SHIFT) => {
+                modifiers.remove(KeyModifiers::SHIFT);
+                KeyCode::Char(c.to_ascii_uppercase())
+            }
+            KeyCode::Tab | KeyCode::BackTab if modifiers.contains(KeyModifiers::SHIFT) => {
+                modifiers.remove(KeyModifiers::SHIFT);
+                KeyCode::BackTab
+            }
+            code => code,
+        };
+        Self {
+            code,
+            modifiers: modifiers
+                & (KeyModifiers::CONTROL | KeyModifiers::ALT | KeyModifiers::SHIFT),
+        }
+    }
+
+    fn parse(text: &str) -> Result<Self> {
+        let mut modifiers = KeyModifiers::NONE;
+        let mut rest = text;
+        // A trailing `-` is the minus key itself, as in `ctrl--`.
+        while let Some((prefix, key)) = rest.split_once('-').filter(|(_, key)| !key.is_empty()) {
+            modifiers |= match prefix.to_lowercase().as_str() {
+                "ctrl" | "c" => KeyModifiers::CONTROL,
+                "alt" | "a" | "m" => KeyModifiers::ALT,
+                "shift" | "s" => KeyModifiers::SHIFT,
+                _ => break,
+            };
+            rest = key;
+        }
+
+        let mut chars = rest.chars();
+        let code = match (chars.next(), chars.next()) {
+            (Some(c), None) => KeyCode::Char(c),
+            _ => match rest.to_lowercase().as_str() {
+                "enter" | "return" => KeyCode::Enter,
+                "esc" | "escape" => KeyCode::Esc,
+                "tab" => KeyCode::Tab,
+                "backtab" => KeyCode::BackTab,
+                "space" => KeyCode::Char(' '),
+                "backspace" => KeyCode::Backspace,
+                "delete" | "del" => KeyCode::Delete,
+                "insert" | "ins" => KeyCode::Insert,
+                "up" => KeyCode::Up,
+                "down" => KeyCode::Down,
+                "left" => KeyCode::Left,
+                "right" => KeyCode::Right,
+                "home" => KeyCode::Home,
+                "end" => KeyCode::End,
+                "pageup" => KeyCode::PageUp,
+                "pagedown" => KeyCode::PageDown,
+                name => match name.strip_prefix('f').and_then(|n| n.parse().ok()) {
+                    Some(n @ 1..=24) => KeyCode::F(n),
+                    _ => bail!("unknown key \"{text}\""),
+                },
+            },
+        };
+        Ok(Self::new(code, modifiers))
+    }
+}
+
+impl From<KeyEvent> for KeyChord {
+    fn from(key: KeyEvent) -> Self {
+        Self::new(key.code, key.modifiers)
+    }
+}
+
+impl fmt::Display for KeyChord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.modifiers.contains(KeyModifiers::CONTROL) {
+            f.write_str("ctrl-")?;
+        }
+        if self.modifiers.contains(KeyModifiers::ALT) {
+            f.write_str("alt-")?;
+        }
+        if self.modifiers.contains(KeyModifiers::SHIFT) {
+            f.write_str("shift-")?;
+        }
+        match self.code {
+            KeyCode::Char(' ') => f.write_str("space"),
+            KeyCode::Char(c) => write!(f, "{c}"),
+            KeyCode::Enter => f.write_str("enter"),
+            KeyCode::Esc => f.write_str("esc"),
+            KeyCode::Tab => f.write_str("tab"),
+            KeyCode::BackTab => f.write_str("backtab"),
+            KeyCode::Backspace => f.write_str("backspace"),
+            KeyCode::Delete => f.write_str("delete"),
+            KeyCode::Insert => f.write_str("insert"),
+            KeyCode::Up => f.write_str("up"),
+            KeyCode::Down => f.write_str("down"),
+            KeyCode::Left => f.write_str("left"),
+            KeyCode::Right => f.write_str("right"),
+            KeyCode::Home => f.write_str("home"),
+            KeyCode::End => f.write_str("end"),
+            KeyCode::PageUp => f.write_str("pageup"),
+            KeyCode::PageDown => f.write_str("pagedown"),
+            KeyCode::F(n) => write!(f, "f{n}"),
+            code => write!(f, "{code:?}"),
+        }
+    }
+}
+
+/// Keys typed one after the other.
+type Sequence = Vec<KeyChord>;
+
+fn parse_sequence(text: &str) -> Result<Sequence> {
+    let sequence = text
+        .split_whitespace()
+        .map(KeyChord::parse)
+        .collect::<Result<Sequence>>()?;
+    if sequence.is_empty() {
+        bail!("empty key");
+    }
+    Ok(sequence)
+}
+
+fn format_sequence(sequence: &[KeyChord]) -> String {
+    sequence
+        .iter()
+        .map(KeyChord::to_string)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// What the keys typed so far amount to.
+pub enum Resolved {
+    Action(Action),
+    /// The start of a longer sequence; wait for the next key.
+    Pending,
+    Unbound,
+}
+
+pub struct Keymap {
+    bindings: Vec<(Sequence, Action)>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Self::new(&BTreeMap::new()).expect("the default bindings are valid")
+    }
+}
+
+impl Keymap {
+    /// The default bindings with those in `keys` replacing them per action.
+    /// Unknown actions, unreadable keys and conflicting bindings are errors.
+    pub fn new(keys: &BTreeMap<String, KeyBinding>) -> Result<Self> {
+        if let Some(name) = keys
+            .keys()
+            .find(|name| !Action::ALL.iter().any(|action| action.name() == *name))
+        {
+            bail!("unknown action \"{name}\" in [keys]");
+        }
+
+        let mut bindings = Vec::new();
+        for action in Action::ALL {
+            let texts: Vec<&str> = match keys.get(action.name()) {
+                Some(binding) => binding.keys(),
+                None => action.default_keys().to_vec(),
+            };
+            for text in texts {
+                let sequence = parse_sequence(text)
+                    .with_context(|| format!("in [keys] {} = \"{text}\"", action.name()))?;
+                bindings.push((sequence, action));
+            }
+        }
+
+        let conflicts = conflicts(&bindings);
+        if !conflicts.is_empty() {
+            bail!(
+                "conflicting key bindings in [keys]: {}",
+                conflicts.join("; ")
+            );
+        }
+        Ok(Self { bindings })
+    }
+
+    pub fn resolve(&self, typed: &[KeyChord]) -> Resolved {
+        let mut pending = false;
+        for (sequence, action) in &self.bindings {
+            if sequence.as_slice() == typed {
+                return Resolved::Action(*action);
+            }
+            pending |= sequence.starts_with(typed);
+        }
+        if pending {
+            Resolved::Pending
+        } else {
+            Resolved::Unbound
+        }
+    }
+
+    /// The keys bound to `action`, e.g. `c/space`.
+    pub fn label(&self, action: Action) -> String {
+        self.bindings
+            .iter()
+            .filter(|(_, bound)| *bound == action)
+            .map(|(sequence, _)| format_sequence(sequence))
+            .collect::<Vec<_>>()
+            .join("/")
+    }
+
+    /// `(keys, description)` rows for the help popup, in action order.
+    pub fn help(&self, section: Section) -> Vec<(String, &'static str)> {
+        Action::ALL
+            .into_iter()
+            .filter(|action| action.section() == section)
+            .map(|action| (self.label(action), action.description()))
+            .filter(|(keys, _)| !keys.is_empty())
+            .collect()
+    }
+}
+
+/// Sequences bound twice, or that can never be typed because a shorter
+/// binding fires first.
+fn conflicts(bindings: &[(Sequence, Action)]) -> Vec<String> {
+    let mut conflicts = Vec::new();
+    for (index, (sequence, action)) in bindings.iter().enumerate() {
+        for (other, other_action) in &bindings[index + 1..] {
+            if sequence == other && action == other_action {
+                continue;
+            }
+            if sequence == other {
+                conflicts.push(format!(
+                    "\"{}\" is bound to both {} and {}",
+                    format_sequence(sequence),
+                    action.name(),
+                    other_action.name()
+                ));
+            } else if other.starts_with(sequence) || sequence.starts_with(other) {
+                let ((short, short_action), (long, long_action)) = if sequence.len() < other.len() {
+                    ((sequence, action), (other, other_action))
+                } else {
+                    ((other, other_action), (sequence, action))
+                };
+                conflicts.push(format!(
+                    "\"{}\" ({}) hides \"{}\" ({})",
+                    format_sequence(short),
+                    short_action.name(),
+                    format_sequence(long),
+                    long_action.name()
+                ));
+            }
+        }
+    }
+    conflicts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bindings(keys: &[(&str, Action)]) -> Vec<(Sequence, Action)> {
+        keys.iter()
+            .map(|(text, action)| (parse_sequence(text).unwrap(), *action))
+            .collect()
+    }
+
+    #[test]
+    fn parses_the_minus_key_with_modifiers() {
+        let chord = KeyChord::parse("ctrl--").unwrap();
+        assert_eq!(chord.code, KeyCode::Char('-'));
+        assert_eq!(chord.modifiers, KeyModifiers::CONTROL);
+        assert_eq!(chord.to_string(), "ctrl--");
+    }
+
+    #[test]
+    fn shift_letters_are_the_capital_letter() {
+        assert_eq!(
+            KeyChord::parse("shift-a").unwrap(),
+            KeyChord::parse("A").unwrap()
+        );
+        assert_eq!(
+            KeyChord::parse("shift-a").unwrap(),
+            KeyChord::from(KeyEvent::new(KeyCode::Char('A'), KeyModifiers::SHIFT))
+        );
+        assert_eq!(
+            KeyChord::parse("shift-tab").unwrap(),
+            KeyChord::parse("backtab").unwrap()
+        );
+    }
+
+    #[test]
+    fn a_shorter_binding_hides_longer_ones_it_starts() {
+        for keys in [
+            [("g", Action::Down), ("g a", Action::ToggleAgenda)],
+            [("g a", Action::ToggleAgenda), ("g", Action::Down)],
+        ] {
+            assert_eq!(
+                conflicts(&bindings(&keys)),
+                ["\"g\" (down) hides \"g a\" (toggle-agenda)"]
+            );
+        }
+    }
+
+    #[test]
+    fn sequences_sharing_a_prefix_do_not_conflict() {
+        let bindings = bindings(&[
+            ("g a", Action::ToggleAgenda),
+            ("g g", Action::Up),
+            ("j", Action::Down),
+            ("j", Action::Down),
+        ]);
+        assert!(conflicts(&bindings).is_empty());
+    }
+
+    #[test]
+    fn a_key_bound_to_two_actions_conflicts() {
+        let conflicts = conflicts(&bindings(&[("j", Action::Down), ("j", Action::Up)]));
+        assert_eq!(conflicts, ["\"j\" is bound to both down and up"]);
+    }
+
+    #[test]
+    fn the_default_bindings_do_not_conflict() {
+        Keymap::new(&BTreeMap::new()).unwrap();
+    }
+}
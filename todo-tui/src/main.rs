@@ -1,18 +1,55 @@
-use todo_client::{Client, CryptoKey};
+use clap::{Parser, Subcommand};
 
+mod agenda;
 mod config;
+mod due_date;
+mod folds;
+mod history;
+mod keymap;
+mod migrate;
+mod repeat;
+mod share;
+mod tag_input;
+mod theme;
 mod ui;
 mod ui_helpers;
 
+#[derive(Parser)]
+#[command(name = "todo", version, about)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Open a read-only share link
+    Open { link: String },
+    /// Move your workspaces to a newly generated recovery phrase
+    MigratePhrase {
+        /// Number of words in the new phrase (12 or 24)
+        #[arg(long, default_value_t = 24)]
+        words: usize,
+    },
+}
+
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     dotenvy::dotenv().ok();
 
+    let cli = Cli::parse();
+    match cli.command {
+        Some(Command::Open { link }) => return share::open(&link).await,
+        Some(Command::MigratePhrase { words }) => return migrate::migrate_phrase(words).await,
+        None => {}
+    }
+
     let config = config::load_or_create_config()?;
-    let crypto = CryptoKey::from_recovery_phrase(&config.phrase);
-    let client = Client::new(config.endpoint, crypto);
+    let client = config.client()?;
+    client.handshake().await?;
+    config::ensure_registered(&client).await?;
 
-    ui::run_app(client).await?;
+    ui::run_app(client, &config.display, config.keymap, config.theme).await?;
 
     Ok(())
 }
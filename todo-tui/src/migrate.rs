@@ -0,0 +1,205 @@
+use std::collections::{HashMap, HashSet};
+
+use anyhow::Context;
+use futures_util::TryStreamExt;
+use secrecy::ExposeSecret;
+use todo_client::{
+    Client, CryptoKey, Decrypted, Priority, Task, TaskQuery, WorkspaceRole,
+    generate_recovery_phrase,
+};
+
+use crate::config;
+
+/// Copies every workspace owned by the current identity to a freshly generated
+/// phrase and makes the new phrase the default for this device. The old
+/// identity and its data stay on the server untouched. The new phrase is
+/// shown before anything is copied, so copies made before a failure are not
+/// lost with it.
+pub async fn migrate_phrase(words: usize) -> anyhow::Result<()> {
+    let config = config::load_or_create_config()?;
+    let old = config.client()?;
+    old.handshake().await?;
+
+    let phrase = generate_recovery_phrase(words)?;
+    let passphrase = config::prompt_for_new_passphrase()?;
+    let crypto = CryptoKey::from_recovery_phrase_and_passphrase(
+        phrase.expose_secret(),
+        passphrase
+            .as_ref()
+            .map_or("", |passphrase| passphrase.expose_secret()),
+    )?
+    .with_padding(config.padding);
+    let new = config.client_with_key(crypto)?;
+    new.handshake().await?;
+    config::ensure_registered(&new).await?;
+    new.register_public_key().await?;
+
+    println!("\nYour new {words}-word recovery phrase:\n");
+    println!("{}\n", phrase.expose_secret());
+    println!("Store this phrase somewhere safe. It replaces the old one on this device.");
+    println!("New user id: {}\n", new.user_id());
+
+    let skipped = copy_workspaces(&old, &new).await.context(
+        "copying stopped; the copies made so far belong to the new phrase above, \
+         and this device still uses the old one",
+    )?;
+
+    config::replace_identity(&phrase, words, passphrase.is_some())?;
+
+    println!("\nThis device now uses the new phrase.");
+    if !skipped.is_empty() {
+        println!(
+            "Shared workspaces you do not own were not copied; ask their owners to invite the new user id: {}",
+            skipped.join(", ")
+        );
+    }
+    println!("Your old workspaces are still available with the old phrase until you delete them.");
+
+    Ok(())
+}
+
+/// Copies the workspaces `old` owns to `new` and returns the names of the
+/// shared ones that were left out.
+async fn copy_workspaces(old: &Client, new: &Client) -> anyhow::Result<Vec<String>> {
+    let workspaces = old.get_workspaces().await?;
+    if !workspaces.failures.is_empty() {
+        println!(
+            "Skipping {} workspaces that could not be decrypted",
+            workspaces.failures.len()
+        );
+    }
+
+    let mut skipped = Vec::new();
+    for workspace in workspaces.items {
+        if workspace.role != WorkspaceRole::Owner {
+            skipped.push(workspace.name);
+            continue;
+        }
+
+        let workspace_id = new.create_workspace(&workspace.name).await?;
+        let query = TaskQuery {
+            include_archived: true,
+            ..TaskQuery::default()
+        };
+        let tasks: Decrypted<Task> = old.task_pages(workspace.id, query).try_collect().await?;
+        let new_ids = copy_tasks(new, workspace_id, &tasks.items).await?;
+        let archived = archive_copies(new, workspace_id, &tasks.items, &new_ids).await?;
+        println!(
+            "Copied {} ({} tasks, {archived} archived)",
+            workspace.name,
+            tasks.items.len()
+        );
+        if !tasks.failures.is_empty() {
+            println!(
+                "  skipped {} tasks that could not be decrypted",
+                tasks.failures.len()
+            );
+        }
+    }
+
+    Ok(skipped)
+}
+
+/// Copies `tasks` into a workspace and returns the new id of each task.
+async fn copy_tasks(
+    client: &Client,
+    workspace_id: i64,
+    tasks: &[Task],
+) -> anyhow::Result<HashMap<i64, i64>> {
+    let mut new_ids = HashMap::new();
+    for task in parents_first(tasks) {
+        let parent_id = task
+            .parent_task_id
+            .and_then(|parent_id| new_ids.get(&parent_id).copied());
+        let task_id = match parent_id {
+            Some(parent_id) => {
+                client
+                    .create_subtask(&task.title, workspace_id, parent_id)
+                    .await?
+            }
+            None => client.create_task(&task.title, workspace_id).await?,
+        };
+
+        if task.due_date.is_some() {
+            client
+                .update_task_due_date(workspace_id, task_id, task.due_date.as_deref())
+                .await?;
+        }
+        if let Some(recurrence) = task.recurrence() {
+            client
+                .update_task_recurrence(workspace_id, task_id, Some(&recurrence))
+                .await?;
+        }
+        if task.priority() != Priority::None {
+            client
+                .update_task_priority(workspace_id, task_id, task.priority())
+                .await?;
+        }
+        let tags = task.tags();
+        if !tags.is_empty() {
+            client
+                .update_task_tags(workspace_id, task_id, &tags)
+                .await?;
+        }
+        if task.completed {
+            client.toggle_task_completion(task_id).await?;
+        }
+
+        new_ids.insert(task.id, task_id);
+    }
+
+    Ok(new_ids)
+}
+
+/// Archives the copies of archived tasks and returns how many there are.
+/// The server only archives all completed tasks of a workspace at once, so
+/// the copies of completed tasks that were not archived are brought back.
+async fn archive_copies(
+    client: &Client,
+    workspace_id: i64,
+    tasks: &[Task],
+    new_ids: &HashMap<i64, i64>,
+) -> anyhow::Result<usize> {
+    let archived = tasks.iter().filter(|task| task.archived).count();
+    if archived == 0 {
+        return Ok(0);
+    }
+
+    client.archive_completed_tasks(workspace_id).await?;
+    let live: Vec<i64> = tasks
+        .iter()
+        .filter(|task| task.completed && !task.archived)
+        .filter_map(|task| new_ids.get(&task.id).copied())
+        .collect();
+    if !live.is_empty() {
+        client.unarchive_tasks(workspace_id, &live).await?;
+    }
+
+    Ok(archived)
+}
+
+/// Orders tasks so every parent comes before its subtasks. Tasks whose parent
+/// is not in the list are treated as top-level.
+fn parents_first(tasks: &[Task]) -> Vec<&Task> {
+    let ids: HashSet<i64> = tasks.iter().map(|task| task.id).collect();
+    let mut children: HashMap<Option<i64>, Vec<&Task>> = HashMap::new();
+    for task in tasks {
+        let parent_id = task
+            .parent_task_id
+            .filter(|parent_id| ids.contains(parent_id));
+        children.entry(parent_id).or_default().push(task);
+    }
+
+    let mut ordered = Vec::with_capacity(tasks.len());
+    let mut stack = children.remove(&None).unwrap_or_default();
+    stack.reverse();
+    while let Some(task) = stack.pop() {
+        ordered.push(task);
+        if let Some(mut subtasks) = children.remove(&Some(task.id)) {
+            subtasks.reverse();
+            stack.extend(subtasks);
+        }
+    }
+
+    ordered
+}
@@ -0,0 +1,203 @@
+//! The text form of recurrence rules typed into the edit popup:
+//!
+//! - `daily`, `weekly`, `monthly`, `every day`, `every 3 days`,
+//!   `every 2 weeks`, `every month`
+//! - weekdays: `every mon, thu`, `weekdays`, `every 2 weeks on fri`
+//! - `after completion` to count from the day the task is done instead of
+//!   its due date, and `with subtasks` to copy subtasks into each occurrence
+//!
+//! [`describe`] writes rules back in this form.
+
+use anyhow::{Context, Result, bail};
+use chrono::Weekday;
+use todo_client::{Frequency, Recurrence};
+
+const WEEKDAYS: [Weekday; 5] = [
+    Weekday::Mon,
+    Weekday::Tue,
+    Weekday::Wed,
+    Weekday::Thu,
+    Weekday::Fri,
+];
+
+/// Parses a rule; blank input means no rule.
+pub fn parse(input: &str) -> Result<Option<Recurrence>> {
+    let mut input = input.trim().to_lowercase().replace(',', " ");
+    if input.is_empty() {
+        return Ok(None);
+    }
+    let after_completion = take_phrase(&mut input, "after completion");
+    let copy_subtasks = take_phrase(&mut input, "with subtasks");
+
+    let (head, days) = match input.split_once(" on ") {
+        Some((head, days)) => (head.to_string(), Some(days.to_string())),
+        None => (input, None),
+    };
+    let words: Vec<&str> = head.split_whitespace().collect();
+    let (frequency, interval, mut weekdays) = match words.as_slice() {
+        ["daily"] | ["every", "day"] => (Frequency::Daily, 1, Vec::new()),
+        ["weekly"] | ["every", "week"] => (Frequency::Weekly, 1, Vec::new()),
+        ["monthly"] | ["every", "month"] => (Frequency::Monthly, 1, Vec::new()),
+        ["weekdays"] | ["every", "weekday"] => (Frequency::Weekly, 1, WEEKDAYS.to_vec()),
+        ["every", count, unit] if count.parse::<u32>().is_ok() => {
+            let interval: u32 = count
+                .parse()
+                .ok()
+                .filter(|interval| *interval > 0)
+                .with_context(|| format!("\"{count}\" is not a positive number"))?;
+            let frequency = match *unit {
+                "days" | "day" => Frequency::Daily,
+                "weeks" | "week" => Frequency::Weekly,
+                "months" | "month" => Frequency::Monthly,
+                unit => bail!("unknown unit \"{unit}\", use days, weeks or months"),
+            };
+            (frequency, interval, Vec::new())
+        }
+        ["every", days @ ..] => (Frequency::Weekly, 1, parse_weekdays(days)?),
+        _ => bail!("could not understand \"{head}\", try \"weekly on mon\""),
+    };
+
+    if let Some(days) = days {
+        if frequency != Frequency::Weekly {
+            bail!("only weekly rules can fall on given weekdays");
+        }
+        let days: Vec<&str> = days.split_whitespace().collect();
+        weekdays = parse_weekdays(&days)?;
+    }
+
+    Ok(Some(Recurrence {
+        frequency,
+        interval,
+        weekdays,
+        after_completion,
+        copy_subtasks,
+    }))
+}
+
+/// The text form of a rule, e.g. `every 2 weeks on mon, fri, after completion`.
+pub fn describe(recurrence: &Recurrence) -> String {
+    let interval = recurrence.interval.max(1);
+    let mut text = match (recurrence.frequency, interval) {
+        (Frequency::Weekly, 1) if recurrence.weekdays == WEEKDAYS => "weekdays".to_string(),
+        (Frequency::Daily, 1) => "daily".to_string(),
+        (Frequency::Weekly, 1) => "weekly".to_string(),
+        (Frequency::Monthly, 1) => "monthly".to_string(),
+        (Frequency::Daily, interval) => format!("every {interval} days"),
+        (Frequency::Weekly, interval) => format!("every {interval} weeks"),
+        (Frequency::Monthly, interval) => format!("every {interval} months"),
+    };
+    if !recurrence.weekdays.is_empty() && text != "weekdays" {
+        let days: Vec<String> = recurrence
+            .weekdays
+            .iter()
+            .map(|day| day.to_string().to_lowercase())
+            .collect();
+        text.push_str(&format!(" on {}", days.join(", ")));
+    }
+    if recurrence.after_completion {
+        text.push_str(", after completion");
+    }
+    if recurrence.copy_subtasks {
+        text.push_str(", with subtasks");
+    }
+    text
+}
+
+fn take_phrase(input: &mut String, phrase: &str) -> bool {
+    match input.find(phrase) {
+        Some(start) => {
+            input.replace_range(start..start + phrase.len(), " ");
+            *input = input.trim().to_string();
+            true
+        }
+        None => false,
+    }
+}
+
+fn parse_weekdays(words: &[&str]) -> Result<Vec<Weekday>> {
+    if words.is_empty() {
+        bail!("name at least one weekday");
+    }
+    let mut days = Vec::new();
+    for word in words.iter().filter(|word| **word != "and") {
+        let named = match *word {
+            "weekdays" => WEEKDAYS.to_vec(),
+            word => vec![
+                word.parse::<Weekday>()
+                    .ok()
+                    .with_context(|| format!("unknown weekday \"{word}\""))?,
+            ],
+        };
+        for day in named {
+            if !days.contains(&day) {
+                days.push(day);
+            }
+        }
+    }
+    days.sort_by_key(Weekday::num_days_from_monday);
+    Ok(days)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(frequency: Frequency, interval: u32, weekdays: &[Weekday]) -> Recurrence {
+        Recurrence {
+            frequency,
+            interval,
+            weekdays: weekdays.to_vec(),
+            after_completion: false,
+            copy_subtasks: false,
+        }
+    }
+
+    #[test]
+    fn described_rules_parse_back() {
+        let rules = [
+            rule(Frequency::Daily, 1, &[]),
+            rule(Frequency::Daily, 3, &[]),
+            rule(Frequency::Weekly, 1, &[]),
+            rule(Frequency::Weekly, 1, &WEEKDAYS),
+            rule(Frequency::Weekly, 1, &[Weekday::Mon, Weekday::Thu]),
+            rule(Frequency::Weekly, 2, &[Weekday::Fri]),
+            rule(Frequency::Weekly, 2, &[Weekday::Sat, Weekday::Sun]),
+            rule(Frequency::Monthly, 1, &[]),
+            rule(Frequency::Monthly, 6, &[]),
+            Recurrence {
+                after_completion: true,
+                ..rule(Frequency::Weekly, 1, &[Weekday::Tue])
+            },
+            Recurrence {
+                copy_subtasks: true,
+                ..rule(Frequency::Daily, 2, &[])
+            },
+            Recurrence {
+                after_completion: true,
+                copy_subtasks: true,
+                ..rule(Frequency::Monthly, 1, &[])
+            },
+        ];
+        for rule in rules {
+            let text = describe(&rule);
+            assert_eq!(parse(&text).unwrap(), Some(rule), "{text}");
+        }
+    }
+
+    #[test]
+    fn describes_rules_in_their_short_form() {
+        assert_eq!(describe(&rule(Frequency::Weekly, 1, &WEEKDAYS)), "weekdays");
+        assert_eq!(
+            describe(&Recurrence {
+                after_completion: true,
+                ..rule(Frequency::Weekly, 2, &[Weekday::Mon, Weekday::Fri])
+            }),
+            "every 2 weeks on mon, fri, after completion"
+        );
+    }
+
+    #[test]
+    fn blank_input_is_no_rule() {
+        assert_eq!(parse("  ").unwrap(), None);
+    }
+}
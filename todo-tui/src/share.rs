@@ -0,0 +1,62 @@
+use todo_client::{SharedTask, open_share_link};
+
+use crate::config;
+
+pub async fn open(link: &str) -> anyhow::Result<()> {
+    let transport = config::network_config()?.transport()?;
+    let workspace = open_share_link(&transport, link).await?;
+
+    println!("{} (read-only)", workspace.name);
+    match workspace.expires_at {
+        Some(expires_at) => println!(
+            "shared {}, expires {}\n",
+            workspace.created_at.format("%Y-%m-%d"),
+            expires_at.format("%Y-%m-%d %H:%M")
+        ),
+        None => println!("shared {}\n", workspace.created_at.format("%Y-%m-%d")),
+    }
+
+    if workspace.tasks.is_empty() {
+        println!("no tasks");
+        return Ok(());
+    }
+
+    let roots = ordered(workspace.tasks.iter().filter(|task| {
+        task.parent_task_id
+            .is_none_or(|parent_id| !workspace.tasks.iter().any(|parent| parent.id == parent_id))
+    }));
+    for task in roots {
+        print_task(&workspace.tasks, task, 0);
+    }
+
+    Ok(())
+}
+
+fn print_task(tasks: &[SharedTask], task: &SharedTask, level: usize) {
+    let checkbox = if task.completed { "×" } else { " " };
+    let due_date = match (task.due(), &task.due_date) {
+        (Some(due), _) => format!(" due {}", due.format_local()),
+        (None, Some(due_date)) => format!(" due {due_date}"),
+        (None, None) => String::new(),
+    };
+    println!(
+        "{}[{checkbox}] {}{due_date}",
+        "  ".repeat(level),
+        task.title
+    );
+
+    let children = ordered(
+        tasks
+            .iter()
+            .filter(|child| child.parent_task_id == Some(task.id)),
+    );
+    for child in children {
+        print_task(tasks, child, level + 1);
+    }
+}
+
+fn ordered<'a>(tasks: impl Iterator<Item = &'a SharedTask>) -> Vec<&'a SharedTask> {
+    let mut tasks: Vec<_> = tasks.collect();
+    tasks.sort_by_key(|task| task.completed);
+    tasks
+}
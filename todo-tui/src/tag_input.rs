@@ -0,0 +1,105 @@
+//! Tags as typed in the TUI: `#project` and `@home` words in new task
+//! titles, and the space- or comma-separated list in the edit popup, which
+//! completes from tags already in use.
+
+use anyhow::{Result, bail};
+use todo_client::tags;
+
+/// Completions offered for the word being typed.
+const MAX_SUGGESTIONS: usize = 5;
+
+/// Splits `#tag` and `@context` words off a new task's title. Numbers such
+/// as `#42` stay in the title, and so does everything when the title would
+/// otherwise be empty.
+pub fn extract(title: &str) -> (String, Vec<String>) {
+    let mut words = Vec::new();
+    let mut found = Vec::new();
+    for word in title.split_whitespace() {
+        match title_tag(word) {
+            Some(tag) => found.push(tag),
+            None => words.push(word),
+        }
+    }
+    if words.is_empty() {
+        return (title.trim().to_string(), Vec::new());
+    }
+    (words.join(" "), found)
+}
+
+fn title_tag(word: &str) -> Option<String> {
+    let name = word.strip_prefix(['#', '@'])?;
+    if !name.starts_with(char::is_alphanumeric) || name.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    tags::normalize(word)
+}
+
+/// Parses the tag list typed into the edit popup.
+pub fn parse(input: &str) -> Result<Vec<String>> {
+    let mut parsed = Vec::new();
+    for word in words(input) {
+        let Some(tag) = tags::normalize(word) else {
+            bail!("\"{word}\" is not a tag");
+        };
+        if !parsed.contains(&tag) {
+            parsed.push(tag);
+        }
+    }
+    Ok(parsed)
+}
+
+/// The text form of a tag list, e.g. `#project @home`.
+pub fn describe(tags: &[String]) -> String {
+    tags.iter()
+        .map(|tag| tags::label(tag))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Tags from `known` that complete the word at the end of `input`, leaving
+/// out ones already listed.
+pub fn suggestions(input: &str, known: &[String]) -> Vec<String> {
+    if input.ends_with(|c: char| c.is_whitespace() || c == ',') {
+        return Vec::new();
+    }
+    let Some(word) = words(input).last() else {
+        return Vec::new();
+    };
+    let Some(typed) = partial(word) else {
+        return Vec::new();
+    };
+    // `#` asks for tags, not contexts.
+    let contexts = !word.starts_with('#');
+    let listed: Vec<String> = words(input).filter_map(tags::normalize).collect();
+    known
+        .iter()
+        .filter(|tag| contexts || !tag.starts_with('@'))
+        .filter(|tag| tag.starts_with(&typed) && **tag != typed && !listed.contains(tag))
+        .take(MAX_SUGGESTIONS)
+        .cloned()
+        .collect()
+}
+
+/// `input` with its last word replaced by `tag`, ready for the next one.
+pub fn complete(input: &str, tag: &str) -> String {
+    let start = input
+        .rfind(|c: char| c.is_whitespace() || c == ',')
+        .map_or(0, |index| index + 1);
+    format!("{}{} ", &input[..start], tags::label(tag))
+}
+
+fn words(input: &str) -> impl Iterator<Item = &str> {
+    input
+        .split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|word| !word.is_empty())
+}
+
+/// A word being typed, normalized like a tag; `#` or `@` alone already
+/// narrows the completions.
+fn partial(word: &str) -> Option<String> {
+    match word {
+        "#" => Some(String::new()),
+        "@" => Some("@".to_string()),
+        word => tags::normalize(word),
+    }
+}
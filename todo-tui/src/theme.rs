@@ -0,0 +1,275 @@
+//! Colours and text styles for the TUI. A theme starts from a built-in one
+//! and the `[theme]` table in the config overrides single styles:
+//!
+//! ```toml
+//! [theme]
+//! base = "light"
+//! selection = "black on yellow"
+//! overdue = "bold #ff5f5f"
+//! ```
+//!
+//! A style is a list of words: colours (names, `#rrggbb` or a palette
+//! index) for the foreground, `on <colour>` for the background, and
+//! modifiers such as `bold` or `crossed-out`. With `NO_COLOR` set the base
+//! defaults to `monochrome`; styles set in the config still apply.
+
+use std::str::FromStr;
+
+use anyhow::{Context, Result, bail};
+use ratatui::style::{Color, Modifier, Style};
+
+use crate::config::ThemeConfig;
+
+pub const BUILT_IN: [&str; 4] = ["default", "light", "high-contrast", "monochrome"];
+
+pub struct Theme {
+    /// List and help text.
+    pub text: Style,
+    /// Creation dates, counts, hints and other secondary text.
+    pub muted: Style,
+    /// The selected row, on top of its own style.
+    pub selection: Style,
+    pub completed: Style,
+    pub overdue: Style,
+    pub due_soon: Style,
+    /// Due dates that are neither overdue nor due soon.
+    pub due: Style,
+    pub border: Style,
+    /// The border of the focused list.
+    pub focused_border: Style,
+    pub tag: Style,
+    /// Repeat rules, workspace roles and agenda headings.
+    pub accent: Style,
+    /// Edit popups and undecryptable items.
+    pub input: Style,
+    /// Previews of valid input, new-item popups and share links.
+    pub valid: Style,
+    /// Input errors and delete confirmations.
+    pub invalid: Style,
+    /// Info notifications.
+    pub info: Style,
+    /// Error notifications.
+    pub error: Style,
+    pub status: Style,
+    pub priority_low: Style,
+    pub priority_medium: Style,
+    pub priority_high: Style,
+    pub priority_urgent: Style,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            text: Style::default().fg(Color::White),
+            muted: Style::default().fg(Color::DarkGray),
+            selection: Style::default().add_modifier(Modifier::BOLD),
+            completed: Style::default()
+                .fg(Color::DarkGray)
+                .add_modifier(Modifier::CROSSED_OUT),
+            overdue: Style::default().fg(Color::Red),
+            due_soon: Style::default().fg(Color::Magenta),
+            due: Style::default().fg(Color::Yellow),
+            border: Style::default(),
+            focused_border: Style::default().fg(Color::Blue),
+            tag: Style::default().fg(Color::Green),
+            accent: Style::default().fg(Color::Cyan),
+            input: Style::default().fg(Color::Yellow),
+            valid: Style::default().fg(Color::Green),
+            invalid: Style::default().fg(Color::Red),
+            info: Style::default().fg(Color::Green),
+            error: Style::default().fg(Color::Red),
+            status: Style::default()
+                .fg(Color::White)
+                .add_modifier(Modifier::BOLD),
+            priority_low: Style::default().fg(Color::Blue),
+            priority_medium: Style::default().fg(Color::Yellow),
+            priority_high: Style::default().fg(Color::LightRed),
+            priority_urgent: Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+        }
+    }
+}
+
+impl Theme {
+    /// The built-in theme named in `config` (or picked from the
+    /// environment) with the config's styles on top.
+    pub fn new(config: &ThemeConfig) -> Result<Self> {
+        let no_color = std::env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty());
+        let base = match &config.base {
+            Some(base) => base.as_str(),
+            None if no_color => "monochrome",
+            None => "default",
+        };
+        let mut theme = Self::built_in(base).with_context(|| {
+            format!(
+                "unknown theme \"{base}\", expected one of {}",
+                BUILT_IN.join(", ")
+            )
+        })?;
+        for (name, value) in &config.styles {
+            let Some(style) = theme.style_mut(name) else {
+                bail!("unknown style \"{name}\" in [theme]");
+            };
+            *style =
+                parse_style(value).with_context(|| format!("in [theme] {name} = \"{value}\""))?;
+        }
+        Ok(theme)
+    }
+
+    fn built_in(name: &str) -> Option<Self> {
+        match name {
+            "default" => Some(Self::default()),
+            "light" => Some(Self::light()),
+            "high-contrast" => Some(Self::high_contrast()),
+            "monochrome" => Some(Self::monochrome()),
+            _ => None,
+        }
+    }
+
+    /// For terminals with a light background.
+    fn light() -> Self {
+        Self {
+            text: Style::default().fg(Color::Black),
+            muted: Style::default().fg(Color::DarkGray),
+            completed: Style::default()
+                .fg(Color::Gray)
+                .add_modifier(Modifier::CROSSED_OUT),
+            overdue: Style::default().fg(Color::Red),
+            due_soon: Style::default().fg(Color::Magenta),
+            due: Style::default().fg(Color::Blue),
+            focused_border: Style::default().fg(Color::Blue),
+            tag: Style::default().fg(Color::Green),
+            accent: Style::default().fg(Color::Blue),
+            input: Style::default().fg(Color::Black),
+            status: Style::default()
+                .fg(Color::Black)
+                .add_modifier(Modifier::BOLD),
+            priority_medium: Style::default().fg(Color::Magenta),
+            priority_high: Style::default().fg(Color::Red),
+            ..Self::default()
+        }
+    }
+
+    /// Bright colours and modifiers that stand out without relying on hue.
+    fn high_contrast() -> Self {
+        let bold = |color| Style::default().fg(color).add_modifier(Modifier::BOLD);
+        Self {
+            text: Style::default().fg(Color::White),
+            muted: Style::default().fg(Color::Gray),
+            selection: Style::default().add_modifier(Modifier::BOLD | Modifier::REVERSED),
+            completed: Style::default()
+                .fg(Color::Gray)
+                .add_modifier(Modifier::CROSSED_OUT),
+            overdue: bold(Color::LightRed).add_modifier(Modifier::UNDERLINED),
+            due_soon: bold(Color::LightMagenta),
+            due: Style::default().fg(Color::LightYellow),
+            border: Style::default().fg(Color::White),
+            focused_border: bold(Color::LightCyan),
+            tag: Style::default().fg(Color::LightGreen),
+            accent: Style::default().fg(Color::LightCyan),
+            input: Style::default().fg(Color::LightYellow),
+            valid: Style::default().fg(Color::LightGreen),
+            invalid: bold(Color::LightRed),
+            info: bold(Color::LightGreen),
+            error: bold(Color::LightRed),
+            status: bold(Color::White),
+            priority_low: Style::default().fg(Color::LightBlue),
+            priority_medium: Style::default().fg(Color::LightYellow),
+            priority_high: bold(Color::LightRed),
+            priority_urgent: bold(Color::LightRed).add_modifier(Modifier::UNDERLINED),
+        }
+    }
+
+    /// No colours at all, only modifiers.
+    fn monochrome() -> Self {
+        let plain = Style::default();
+        let with = |modifier| Style::default().add_modifier(modifier);
+        Self {
+            text: plain,
+            muted: with(Modifier::DIM),
+            selection: with(Modifier::REVERSED),
+            completed: with(Modifier::CROSSED_OUT | Modifier::DIM),
+            overdue: with(Modifier::BOLD | Modifier::UNDERLINED),
+            due_soon: with(Modifier::BOLD),
+            due: plain,
+            border: plain,
+            focused_border: with(Modifier::BOLD),
+            tag: with(Modifier::ITALIC),
+            accent: with(Modifier::ITALIC),
+            input: plain,
+            valid: plain,
+            invalid: with(Modifier::BOLD),
+            info: plain,
+            error: with(Modifier::BOLD),
+            status: with(Modifier::BOLD),
+            priority_low: plain,
+            priority_medium: plain,
+            priority_high: with(Modifier::BOLD),
+            priority_urgent: with(Modifier::BOLD | Modifier::UNDERLINED),
+        }
+    }
+
+    /// The style called `name` in the `[theme]` table.
+    fn style_mut(&mut self, name: &str) -> Option<&mut Style> {
+        Some(match name {
+            "text" => &mut self.text,
+            "muted" => &mut self.muted,
+            "selection" => &mut self.selection,
+            "completed" => &mut self.completed,
+            "overdue" => &mut self.overdue,
+            "due-soon" => &mut self.due_soon,
+            "due" => &mut self.due,
+            "border" => &mut self.border,
+            "focused-border" => &mut self.focused_border,
+            "tag" => &mut self.tag,
+            "accent" => &mut self.accent,
+            "input" => &mut self.input,
+            "valid" => &mut self.valid,
+            "invalid" => &mut self.invalid,
+            "info" => &mut self.info,
+            "error" => &mut self.error,
+            "status" => &mut self.status,
+            "priority-low" => &mut self.priority_low,
+            "priority-medium" => &mut self.priority_medium,
+            "priority-high" => &mut self.priority_high,
+            "priority-urgent" => &mut self.priority_urgent,
+            _ => return None,
+        })
+    }
+}
+
+fn parse_style(value: &str) -> Result<Style> {
+    let mut style = Style::default();
+    let mut words = value.split_whitespace();
+    while let Some(word) = words.next() {
+        let word = word.to_lowercase();
+        if word == "on" {
+            let Some(color) = words.next() else {
+                bail!("\"on\" needs a background colour");
+            };
+            style = style.bg(parse_color(color)?);
+        } else if let Some(modifier) = parse_modifier(&word) {
+            style = style.add_modifier(modifier);
+        } else {
+            style = style.fg(parse_color(&word)?);
+        }
+    }
+    Ok(style)
+}
+
+fn parse_color(value: &str) -> Result<Color> {
+    Color::from_str(value).map_err(|_| anyhow::anyhow!("unknown colour \"{value}\""))
+}
+
+fn parse_modifier(word: &str) -> Option<Modifier> {
+    match word {
+        "bold" => Some(Modifier::BOLD),
+        "dim" => Some(Modifier::DIM),
+        "italic" => Some(Modifier::ITALIC),
+        "underlined" | "underline" => Some(Modifier::UNDERLINED),
+        "reversed" => Some(Modifier::REVERSED),
+        "crossed-out" | "strikethrough" => Some(Modifier::CROSSED_OUT),
+        "blink" => Some(Modifier::SLOW_BLINK),
+        "hidden" => Some(Modifier::HIDDEN),
+        _ => None,
+    }
+}
@@ -1,33 +1,64 @@
 use anyhow::Result;
-use chrono::{Local, NaiveDate};
+use chrono::{Local, Utc};
+use futures_util::StreamExt;
 use ratatui::{
     Frame, Terminal,
     backend::CrosstermBackend,
     crossterm::{
         cursor::SetCursorStyle,
-        event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode},
+        event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEvent},
         execute,
         terminal::{EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode, enable_raw_mode},
     },
     layout::{Constraint, Direction, Layout},
-    style::{Color, Modifier, Style},
+    style::Modifier,
     text::{Line, Span},
     widgets::{Block, Borders, Clear, List, ListItem, ListState, Paragraph, Wrap},
 };
 use std::cmp::Reverse;
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::io;
+use std::ops::ControlFlow;
 use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
 
-use todo_client::{Client, Task, Workspace, WorkspaceStats};
+const SHARE_LINK_TTL_DAYS: i64 = 7;
+
+use todo_client::{
+    ChangeKind, Client, DecryptionFailure, LiveUpdate, Priority, Task, Workspace, WorkspaceRole,
+    WorkspaceStats, protocol::FEATURE_EVENTS, tags,
+};
 use tui_input::{Input, backend::crossterm::EventHandler};
 
-use crate::ui_helpers::{centered_rect, fuzzy_matches, top_right_rect};
+use crate::{
+    agenda,
+    config::DisplayConfig,
+    due_date,
+    folds::Folds,
+    history::{Change, History, TaskSnapshot},
+    keymap::{Action, KeyChord, Keymap, Resolved, Section},
+    repeat, tag_input,
+    theme::Theme,
+    ui_helpers::{centered_rect, fuzzy_matches, top_right_rect},
+};
 
 #[derive(Debug, Clone)]
 pub struct TaskDisplay {
     pub task: Task,
     pub level: usize,
+    /// Descendants left out because the task is collapsed.
+    pub hidden: usize,
+}
+
+/// What the task panel lists.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TaskView {
+    /// The selected workspace's tasks as a tree.
+    Workspace,
+    /// Tasks carrying this tag, from every workspace.
+    Tag(String),
+    /// Open tasks from every workspace, grouped by due date.
+    Agenda,
 }
 
 #[derive(PartialEq)]
@@ -44,18 +75,25 @@ pub enum InputMode {
     Help,
     Creating,
     Search,
+    ShareLink,
+    Inspect,
+    TagPicker,
 }
 
 #[derive(PartialEq)]
 pub enum EditField {
     Title,
     DueDate,
+    Repeat,
+    Tags,
 }
 
 pub struct App {
     pub workspaces: Vec<Workspace>,
+    pub workspace_failures: Vec<DecryptionFailure>,
     pub workspace_stats: HashMap<i64, WorkspaceStats>,
     pub tasks: Vec<Task>,
+    pub task_failures: Vec<DecryptionFailure>,
     pub task_displays: Vec<TaskDisplay>,
     pub workspace_state: ListState,
     pub task_state: ListState,
@@ -66,28 +104,56 @@ pub struct App {
     pub input_buffer: Input,
     pub edit_title_buffer: Input,
     pub edit_due_date_buffer: Input,
+    pub edit_repeat_buffer: Input,
+    pub edit_tags_buffer: Input,
     pub edit_field: EditField,
     pub search_query: String,
+    pub view: TaskView,
+    /// Where each agenda group starts in `task_displays`.
+    pub agenda_groups: Vec<(usize, agenda::Group)>,
+    /// The rendered agenda rows, which include group headings.
+    pub agenda_state: ListState,
+    /// Tags in use across all workspaces with their task counts, for the
+    /// tag picker and completion.
+    pub known_tags: Vec<(String, usize)>,
+    pub tag_state: ListState,
     pub delete_target: Option<String>,
+    pub share_link: Option<String>,
     pub creating_subtask: bool,
     pub sort_created_desc: bool,
     pub notification: Option<Notification>,
+    pub history: History,
+    pub folds: Folds,
+    pub due_soon: chrono::Duration,
+    pub keymap: Keymap,
+    /// Keys typed so far of a multi-key binding.
+    pub pending_keys: Vec<KeyChord>,
+    pub theme: Theme,
+}
+
+#[derive(PartialEq)]
+pub enum NotificationKind {
+    Info,
+    Error,
 }
 
 pub struct Notification {
+    pub kind: NotificationKind,
     pub message: String,
     pub created_at: Instant,
 }
 
 impl App {
-    pub fn new(client: Client) -> Self {
+    pub fn new(client: Client, display: &DisplayConfig, keymap: Keymap, theme: Theme) -> Self {
         let mut workspace_state = ListState::default();
         workspace_state.select(Some(0));
 
         Self {
             workspaces: vec![],
+            workspace_failures: vec![],
             workspace_stats: HashMap::new(),
             tasks: vec![],
+            task_failures: vec![],
             task_displays: vec![],
             workspace_state,
             task_state: ListState::default(),
@@ -98,17 +164,43 @@ impl App {
             input_buffer: Input::default(),
             edit_title_buffer: Input::default(),
             edit_due_date_buffer: Input::default(),
+            edit_repeat_buffer: Input::default(),
+            edit_tags_buffer: Input::default(),
             edit_field: EditField::Title,
             search_query: String::new(),
+            view: TaskView::Workspace,
+            agenda_groups: Vec::new(),
+            agenda_state: ListState::default(),
+            known_tags: Vec::new(),
+            tag_state: ListState::default(),
             delete_target: None,
+            share_link: None,
             creating_subtask: false,
             sort_created_desc: true,
             notification: None,
+            history: History::default(),
+            folds: Folds::default(),
+            due_soon: i64::try_from(display.due_soon_hours)
+                .ok()
+                .and_then(chrono::Duration::try_hours)
+                .unwrap_or(chrono::Duration::MAX),
+            keymap,
+            pending_keys: Vec::new(),
+            theme,
         }
     }
 
+    pub fn notify(&mut self, message: String) {
+        self.notification = Some(Notification {
+            kind: NotificationKind::Info,
+            message,
+            created_at: Instant::now(),
+        });
+    }
+
     pub fn notify_error(&mut self, action: &str, err: impl std::fmt::Display) {
         self.notification = Some(Notification {
+            kind: NotificationKind::Error,
             message: format!("{action}: {err}"),
             created_at: Instant::now(),
         });
@@ -129,7 +221,9 @@ impl App {
     }
 
     pub async fn load_workspaces(&mut self) -> Result<()> {
-        self.workspaces = self.client.get_workspaces().await?;
+        let workspaces = self.client.get_workspaces().await?;
+        self.workspaces = workspaces.items;
+        self.workspace_failures = workspaces.failures;
         self.refresh_workspace_stats().await?;
         if !self.workspaces.is_empty() {
             self.workspace_state.select(Some(0));
@@ -140,18 +234,260 @@ impl App {
     }
 
     pub async fn load_tasks_for_selected_workspace(&mut self) -> Result<()> {
-        if let Some(selected) = self.selected_workspace
+        if self.view != TaskView::Workspace {
+            let tasks = self.client.get_tasks_for_all_workspaces().await?;
+            self.known_tags = count_tags(&tasks.items);
+            self.tasks = match &self.view {
+                TaskView::Tag(tag) => tasks
+                    .items
+                    .into_iter()
+                    .filter(|task| task.has_tag(tag))
+                    .collect(),
+                _ => tasks.items,
+            };
+            self.task_failures = tasks.failures;
+            self.refresh_workspace_stats().await?;
+            self.build_task_hierarchy();
+            self.select_first_visible_task();
+        } else if let Some(selected) = self.selected_workspace
             && let Some(workspace) = self.workspaces.get(selected)
         {
-            self.tasks = self.client.get_tasks_for_workspace(workspace.id).await?;
+            let tasks = self.client.get_tasks_for_workspace(workspace.id).await?;
+            self.tasks = tasks.items;
+            self.task_failures = tasks.failures;
             self.refresh_workspace_stats().await?;
             self.build_task_hierarchy();
-            self.task_state.select(if self.task_displays.is_empty() {
-                None
-            } else {
-                Some(0)
+            self.select_first_visible_task();
+        } else {
+            self.tasks.clear();
+            self.task_failures.clear();
+            self.build_task_hierarchy();
+            self.select_first_visible_task();
+        }
+        Ok(())
+    }
+
+    /// Reloads what the given changes touched: the workspace list when
+    /// workspaces or memberships changed, otherwise only the open workspace's
+    /// tasks, or just the counts when another workspace changed.
+    pub async fn apply_live_updates(&mut self, updates: Vec<LiveUpdate>) -> Result<()> {
+        let mut reload_workspaces = false;
+        let mut changed_workspaces = HashSet::new();
+        for update in updates {
+            match update {
+                LiveUpdate::Resync => reload_workspaces = true,
+                LiveUpdate::Change(event) => match event.kind {
+                    ChangeKind::WorkspaceCreated
+                    | ChangeKind::WorkspaceUpdated
+                    | ChangeKind::WorkspaceDeleted
+                    | ChangeKind::MembersChanged => reload_workspaces = true,
+                    ChangeKind::TaskCreated
+                    | ChangeKind::TaskUpdated
+                    | ChangeKind::TaskDeleted
+                    | ChangeKind::TasksArchived => {
+                        changed_workspaces.insert(event.workspace_id);
+                    }
+                },
+            }
+        }
+
+        if reload_workspaces {
+            return self.reload_workspaces_keeping_selection().await;
+        }
+
+        let selected_workspace_id = self
+            .selected_workspace
+            .and_then(|selected| self.workspaces.get(selected))
+            .map(|workspace| workspace.id);
+        if selected_workspace_id.is_some_and(|id| changed_workspaces.contains(&id))
+            || (self.view != TaskView::Workspace && !changed_workspaces.is_empty())
+        {
+            self.reload_tasks_keeping_selection().await
+        } else if !changed_workspaces.is_empty() {
+            self.refresh_workspace_stats().await
+        } else {
+            Ok(())
+        }
+    }
+
+    async fn reload_workspaces_keeping_selection(&mut self) -> Result<()> {
+        let selected_workspace_id = self
+            .selected_workspace
+            .and_then(|selected| self.workspaces.get(selected))
+            .map(|workspace| workspace.id);
+
+        let workspaces = self.client.get_workspaces().await?;
+        self.workspaces = workspaces.items;
+        self.workspace_failures = workspaces.failures;
+
+        let selected = selected_workspace_id
+            .and_then(|id| {
+                self.workspaces
+                    .iter()
+                    .position(|workspace| workspace.id == id)
+            })
+            .or_else(|| (self.workspace_row_count() > 0).then_some(0));
+        self.workspace_state.select(selected);
+        self.selected_workspace = selected;
+        self.reload_tasks_keeping_selection().await
+    }
+
+    async fn reload_tasks_keeping_selection(&mut self) -> Result<()> {
+        let selected_task_id = self
+            .task_state
+            .selected()
+            .and_then(|selected| self.task_displays.get(selected))
+            .map(|task_display| task_display.task.id);
+
+        self.load_tasks_for_selected_workspace().await?;
+        if let Some(selected) = selected_task_id.and_then(|id| {
+            self.task_displays
+                .iter()
+                .position(|task_display| task_display.task.id == id)
+        }) {
+            self.task_state.select(Some(selected));
+        }
+        Ok(())
+    }
+
+    async fn refresh_known_tags(&mut self) -> Result<()> {
+        let tasks = self.client.get_tasks_for_all_workspaces().await?;
+        self.known_tags = count_tags(&tasks.items);
+        Ok(())
+    }
+
+    /// Tags to complete from: those in use anywhere as of the last time the
+    /// tag picker or a tag view loaded them, plus any on the loaded tasks.
+    fn tag_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self
+            .known_tags
+            .iter()
+            .map(|(tag, _)| tag.clone())
+            .chain(self.tasks.iter().flat_map(Task::tags))
+            .collect();
+        names.sort();
+        names.dedup();
+        names
+    }
+
+    /// The tag a tag view shows.
+    fn tag_filter(&self) -> Option<&str> {
+        match &self.view {
+            TaskView::Tag(tag) => Some(tag),
+            _ => None,
+        }
+    }
+
+    /// The tasks of `workspace_id` including those a tag view or the agenda
+    /// leaves out, for changes that touch whole subtrees.
+    async fn workspace_tasks(&self, workspace_id: i64) -> Result<Vec<Task>> {
+        if self.view == TaskView::Workspace {
+            return Ok(self.tasks.clone());
+        }
+        Ok(self
+            .client
+            .get_tasks_for_workspace(workspace_id)
+            .await?
+            .items)
+    }
+
+    pub async fn open_tag_picker(&mut self) -> Result<()> {
+        self.refresh_known_tags().await?;
+        let selected = self
+            .tag_filter()
+            .and_then(|filter| self.known_tags.iter().position(|(tag, _)| tag == filter))
+            .or_else(|| (!self.known_tags.is_empty()).then_some(0));
+        self.tag_state.select(selected);
+        self.input_mode = InputMode::TagPicker;
+        Ok(())
+    }
+
+    pub fn close_tag_picker(&mut self) {
+        self.input_mode = InputMode::Normal;
+    }
+
+    pub fn next_tag(&mut self) {
+        if !self.known_tags.is_empty() {
+            let i = self
+                .tag_state
+                .selected()
+                .map_or(0, |i| (i + 1) % self.known_tags.len());
+            self.tag_state.select(Some(i));
+        }
+    }
+
+    pub fn previous_tag(&mut self) {
+        if !self.known_tags.is_empty() {
+            let i = self.tag_state.selected().map_or(0, |i| {
+                if i == 0 {
+                    self.known_tags.len() - 1
+                } else {
+                    i - 1
+                }
             });
+            self.tag_state.select(Some(i));
+        }
+    }
+
+    /// Switches the task list to every task with the picked tag.
+    pub async fn apply_tag_filter(&mut self) -> Result<()> {
+        self.input_mode = InputMode::Normal;
+        if let Some((tag, _)) = self
+            .tag_state
+            .selected()
+            .and_then(|selected| self.known_tags.get(selected))
+        {
+            self.view = TaskView::Tag(tag.clone());
+            self.focus = Focus::Tasks;
+            self.load_tasks_for_selected_workspace().await?;
+        }
+        Ok(())
+    }
+
+    /// Switches between the agenda and the selected workspace.
+    pub async fn toggle_agenda(&mut self) -> Result<()> {
+        self.view = if self.view == TaskView::Agenda {
+            TaskView::Workspace
+        } else {
+            self.focus = Focus::Tasks;
+            TaskView::Agenda
+        };
+        self.load_tasks_for_selected_workspace().await
+    }
+
+    /// Leaves the agenda or a tag view for the selected workspace.
+    pub async fn show_workspace(&mut self) -> Result<()> {
+        self.view = TaskView::Workspace;
+        self.load_tasks_for_selected_workspace().await
+    }
+
+    /// Opens the workspace of the task selected in the agenda or a tag
+    /// view, with the task selected.
+    pub async fn jump_to_task_workspace(&mut self) -> Result<()> {
+        if self.view == TaskView::Workspace || self.focus != Focus::Tasks {
+            return Ok(());
         }
+        let Some(task) = self
+            .task_state
+            .selected()
+            .and_then(|selected| self.task_displays.get(selected))
+            .map(|task_display| task_display.task.clone())
+        else {
+            return Ok(());
+        };
+        let Some(index) = self
+            .workspaces
+            .iter()
+            .position(|workspace| workspace.id == task.workspace_id)
+        else {
+            return Ok(());
+        };
+        self.view = TaskView::Workspace;
+        self.search_query.clear();
+        self.workspace_state.select(Some(index));
+        self.selected_workspace = Some(index);
+        self.load_tasks_for_selected_workspace().await?;
+        self.select_task(task.id);
         Ok(())
     }
 
@@ -175,6 +511,26 @@ impl App {
             .filter(|task| search_query.is_empty() || fuzzy_matches(&task.title, &search_query))
             .cloned()
             .collect();
+
+        self.agenda_groups.clear();
+        if self.view == TaskView::Agenda {
+            for (group, task) in agenda::arrange(&tasks, Utc::now()) {
+                if self
+                    .agenda_groups
+                    .last()
+                    .is_none_or(|(_, last)| *last != group)
+                {
+                    self.agenda_groups.push((self.task_displays.len(), group));
+                }
+                self.task_displays.push(TaskDisplay {
+                    task,
+                    level: 0,
+                    hidden: 0,
+                });
+            }
+            return;
+        }
+
         let active_task_ids: HashSet<i64> = tasks.iter().map(|t| t.id).collect();
 
         let mut incomplete_root_tasks: Vec<Task> = tasks
@@ -186,7 +542,7 @@ impl App {
             })
             .cloned()
             .collect();
-        self.sort_tasks_by_created_at(&mut incomplete_root_tasks);
+        self.sort_tasks(&mut incomplete_root_tasks);
 
         let mut completed_root_tasks: Vec<Task> = tasks
             .iter()
@@ -197,7 +553,7 @@ impl App {
             })
             .cloned()
             .collect();
-        self.sort_tasks_by_created_at(&mut completed_root_tasks);
+        self.sort_tasks(&mut completed_root_tasks);
 
         let mut index = 0;
 
@@ -220,6 +576,7 @@ impl App {
         self.task_displays.push(TaskDisplay {
             task: task.clone(),
             level,
+            hidden: 0,
         });
         *index += 1;
 
@@ -228,7 +585,17 @@ impl App {
             .filter(|t| t.parent_task_id == Some(task.id))
             .cloned()
             .collect();
-        self.sort_tasks_by_created_at(&mut children);
+        // Search results show every match, folded or not.
+        if !children.is_empty()
+            && self.folds.is_collapsed(task.id)
+            && self.search_query.trim().is_empty()
+        {
+            if let Some(task_display) = self.task_displays.last_mut() {
+                task_display.hidden = count_descendants(all_tasks, task.id);
+            }
+            return;
+        }
+        self.sort_tasks(&mut children);
 
         let incomplete_children: Vec<Task> =
             children.iter().filter(|t| !t.completed).cloned().collect();
@@ -244,11 +611,12 @@ impl App {
         }
     }
 
-    fn sort_tasks_by_created_at(&self, tasks: &mut [Task]) {
+    /// Most important first, then by creation date in the chosen order.
+    fn sort_tasks(&self, tasks: &mut [Task]) {
         if self.sort_created_desc {
-            tasks.sort_by_key(|t| Reverse(t.created_at));
+            tasks.sort_by_key(|t| (Reverse(t.priority()), Reverse(t.created_at)));
         } else {
-            tasks.sort_by_key(|t| t.created_at);
+            tasks.sort_by_key(|t| (Reverse(t.priority()), t.created_at));
         }
     }
 
@@ -263,23 +631,141 @@ impl App {
         self.build_task_hierarchy();
 
         if let Some(task_id) = selected_task_id {
-            let new_selection = self
-                .task_displays
+            self.select_task(task_id);
+        }
+    }
+
+    /// Folds or unfolds the selected task's subtasks. On a task without
+    /// subtasks this folds its parent instead.
+    pub fn toggle_fold(&mut self) -> Result<()> {
+        if self.view != TaskView::Workspace || !self.search_query.trim().is_empty() {
+            self.notify("Subtasks fold in the workspace view without a search".to_string());
+            return Ok(());
+        }
+        let Some(task) = self
+            .task_state
+            .selected()
+            .and_then(|selected| self.task_displays.get(selected))
+            .map(|task_display| task_display.task.clone())
+        else {
+            return Ok(());
+        };
+        let has_subtasks = |task_id| self.tasks.iter().any(|t| t.parent_task_id == Some(task_id));
+        let Some(task_id) = Some(task.id)
+            .filter(|task_id| has_subtasks(*task_id))
+            .or(task.parent_task_id)
+        else {
+            self.notify(format!("\"{}\" has no subtasks", task.title));
+            return Ok(());
+        };
+
+        let collapsed = !self.folds.is_collapsed(task_id);
+        let saved = self.folds.set([task_id], collapsed);
+        self.build_task_hierarchy();
+        self.select_task(task_id);
+        saved
+    }
+
+    /// Folds or unfolds every subtask tree in the workspace.
+    pub fn set_all_folds(&mut self, collapsed: bool) -> Result<()> {
+        if self.view != TaskView::Workspace || !self.search_query.trim().is_empty() {
+            self.notify("Subtasks fold in the workspace view without a search".to_string());
+            return Ok(());
+        }
+        let selected_task_id = self
+            .task_state
+            .selected()
+            .and_then(|selected| self.task_displays.get(selected))
+            .map(|task_display| task_display.task.id);
+
+        let saved = if collapsed {
+            let parents: HashSet<i64> =
+                self.tasks.iter().filter_map(|t| t.parent_task_id).collect();
+            self.folds.set(parents, true)
+        } else {
+            self.folds.set(self.tasks.iter().map(|t| t.id), false)
+        };
+        self.build_task_hierarchy();
+        if let Some(task_id) = selected_task_id {
+            self.select_visible_task(task_id);
+        }
+        saved
+    }
+
+    /// Selects `task_id`, or its closest ancestor when it is folded away.
+    fn select_visible_task(&mut self, task_id: i64) {
+        let mut current = Some(task_id);
+        while let Some(id) = current
+            && !self.task_displays.iter().any(|td| td.task.id == id)
+        {
+            current = self
+                .tasks
                 .iter()
-                .position(|td| td.task.id == task_id)
-                .or_else(|| (!self.task_displays.is_empty()).then_some(0));
-            self.task_state.select(new_selection);
+                .find(|t| t.id == id)
+                .and_then(|t| t.parent_task_id);
         }
+        self.select_task(current.unwrap_or(task_id));
+    }
+
+    /// Selects the row of `task_id`, or the first row if it is gone.
+    fn select_task(&mut self, task_id: i64) {
+        let new_selection = self
+            .task_displays
+            .iter()
+            .position(|td| td.task.id == task_id)
+            .or_else(|| (!self.task_displays.is_empty()).then_some(0));
+        self.task_state.select(new_selection);
     }
 
     fn select_first_visible_task(&mut self) {
-        self.task_state.select(if self.task_displays.is_empty() {
+        self.task_state.select(if self.task_row_count() == 0 {
             None
         } else {
             Some(0)
         });
     }
 
+    /// Workspace rows: decrypted workspaces followed by placeholders for the
+    /// ones that could not be decrypted.
+    fn workspace_row_count(&self) -> usize {
+        self.workspaces.len() + self.workspace_failures.len()
+    }
+
+    /// Task rows: the task tree followed by placeholders for tasks that could
+    /// not be decrypted.
+    fn task_row_count(&self) -> usize {
+        self.task_displays.len() + self.task_failures.len()
+    }
+
+    fn selected_workspace_failure(&self) -> Option<&DecryptionFailure> {
+        let selected = self.workspace_state.selected()?;
+        self.workspace_failures
+            .get(selected.checked_sub(self.workspaces.len())?)
+    }
+
+    fn selected_task_failure(&self) -> Option<&DecryptionFailure> {
+        let selected = self.task_state.selected()?;
+        self.task_failures
+            .get(selected.checked_sub(self.task_displays.len())?)
+    }
+
+    fn selected_failure(&self) -> Option<&DecryptionFailure> {
+        match self.focus {
+            Focus::Workspaces => self.selected_workspace_failure(),
+            Focus::Tasks => self.selected_task_failure(),
+        }
+    }
+
+    pub fn inspect_failure(&mut self) {
+        if self.selected_failure().is_some() {
+            self.input_mode = InputMode::Inspect;
+        }
+    }
+
+    pub fn hide_inspect(&mut self) {
+        self.input_mode = InputMode::Normal;
+    }
+
     pub fn start_search(&mut self) {
         self.input_buffer = self.search_query.clone().into();
         self.input_mode = InputMode::Search;
@@ -309,9 +795,13 @@ impl App {
     }
 
     pub async fn next_workspace(&mut self) -> Result<()> {
+        if self.workspace_row_count() == 0 {
+            return Ok(());
+        }
+
         let i = match self.workspace_state.selected() {
             Some(i) => {
-                if i >= self.workspaces.len() - 1 {
+                if i >= self.workspace_row_count() - 1 {
                     0
                 } else {
                     i + 1
@@ -321,15 +811,20 @@ impl App {
         };
         self.workspace_state.select(Some(i));
         self.selected_workspace = Some(i);
+        self.view = TaskView::Workspace;
         self.load_tasks_for_selected_workspace().await?;
         Ok(())
     }
 
     pub async fn previous_workspace(&mut self) -> Result<()> {
+        if self.workspace_row_count() == 0 {
+            return Ok(());
+        }
+
         let i = match self.workspace_state.selected() {
             Some(i) => {
                 if i == 0 {
-                    self.workspaces.len() - 1
+                    self.workspace_row_count() - 1
                 } else {
                     i - 1
                 }
@@ -338,19 +833,20 @@ impl App {
         };
         self.workspace_state.select(Some(i));
         self.selected_workspace = Some(i);
+        self.view = TaskView::Workspace;
         self.load_tasks_for_selected_workspace().await?;
         Ok(())
     }
 
     pub fn next_task(&mut self) {
-        if self.task_displays.is_empty() {
+        if self.task_row_count() == 0 {
             self.task_state.select(None);
             return;
         }
 
         let i = match self.task_state.selected() {
             Some(i) => {
-                if i >= self.task_displays.len() - 1 {
+                if i >= self.task_row_count() - 1 {
                     0
                 } else {
                     i + 1
@@ -362,7 +858,7 @@ impl App {
     }
 
     pub fn previous_task(&mut self) {
-        if self.task_displays.is_empty() {
+        if self.task_row_count() == 0 {
             self.task_state.select(None);
             return;
         }
@@ -370,7 +866,7 @@ impl App {
         let i = match self.task_state.selected() {
             Some(i) => {
                 if i == 0 {
-                    self.task_displays.len() - 1
+                    self.task_row_count() - 1
                 } else {
                     i - 1
                 }
@@ -398,42 +894,79 @@ impl App {
             return Ok(());
         }
 
+        let title = self.input_buffer.value().to_string();
         match self.focus {
             Focus::Workspaces => {
-                self.client
-                    .create_workspace(self.input_buffer.value())
-                    .await?;
+                let workspace_id = self.client.create_workspace(&title).await?;
+                self.history.record(
+                    format!("create workspace \"{title}\""),
+                    Change::DeleteWorkspace {
+                        workspace_id,
+                        name: title,
+                        tasks: Vec::new(),
+                    },
+                );
                 self.load_workspaces().await?;
             }
             Focus::Tasks => {
-                if let Some(selected) = self.selected_workspace
-                    && let Some(workspace) = self.workspaces.get(selected)
-                {
-                    if self.creating_subtask {
-                        if let Some(task_display_idx) = self.task_state.selected() {
-                            if let Some(task_display) = self.task_displays.get(task_display_idx) {
-                                self.client
-                                    .create_subtask(
-                                        self.input_buffer.value(),
-                                        workspace.id,
-                                        task_display.task.id,
-                                    )
-                                    .await?;
-                            } else {
-                                self.client
-                                    .create_task(self.input_buffer.value(), workspace.id)
-                                    .await?;
-                            }
-                        } else {
+                let parent = if self.creating_subtask {
+                    self.task_state
+                        .selected()
+                        .and_then(|selected| self.task_displays.get(selected))
+                        .map(|task_display| (task_display.task.workspace_id, task_display.task.id))
+                } else {
+                    None
+                };
+                // Subtasks go where their parent is, which in a tag view
+                // need not be the selected workspace.
+                let workspace_id = parent.map(|(workspace_id, _)| workspace_id).or_else(|| {
+                    self.selected_workspace
+                        .and_then(|selected| self.workspaces.get(selected))
+                        .map(|workspace| workspace.id)
+                });
+                if let Some(workspace_id) = workspace_id {
+                    let parent_task_id = parent.map(|(_, task_id)| task_id);
+                    // A new subtask should not vanish into a folded tree.
+                    if let Some(parent_task_id) = parent_task_id
+                        && let Err(err) = self.folds.set([parent_task_id], false)
+                    {
+                        self.notify_error("Could not save folded tasks", err);
+                    }
+                    let (title, mut tags) = tag_input::extract(&title);
+                    if let Some(tag) = self.tag_filter()
+                        && !tags.iter().any(|own| own == tag)
+                    {
+                        tags.push(tag.to_string());
+                    }
+                    let task_id = match parent_task_id {
+                        Some(parent_task_id) => {
                             self.client
-                                .create_task(self.input_buffer.value(), workspace.id)
-                                .await?;
+                                .create_subtask(&title, workspace_id, parent_task_id)
+                                .await?
                         }
-                    } else {
+                        None => self.client.create_task(&title, workspace_id).await?,
+                    };
+                    if !tags.is_empty() {
                         self.client
-                            .create_task(self.input_buffer.value(), workspace.id)
+                            .update_task_tags(workspace_id, task_id, &tags)
                             .await?;
                     }
+                    self.history.record(
+                        format!("add \"{title}\""),
+                        Change::DeleteTasks {
+                            workspace_id,
+                            tasks: vec![TaskSnapshot {
+                                id: task_id,
+                                parent_task_id,
+                                title,
+                                due_date: None,
+                                recurrence: None,
+                                priority: Priority::None,
+                                tags,
+                                completed: false,
+                            }],
+                        },
+                    );
                     self.load_tasks_for_selected_workspace().await?;
                 }
             }
@@ -452,9 +985,21 @@ impl App {
             && let Some(selected_task_idx) = self.task_state.selected()
             && let Some(task_display) = self.task_displays.get(selected_task_idx)
         {
-            self.client
-                .toggle_task_completion(task_display.task.id)
-                .await?;
+            let task = task_display.task.clone();
+            self.clear_notification();
+            self.client.toggle_task_completion(task.id).await?;
+            let verb = if task.completed { "reopen" } else { "complete" };
+            self.history.record(
+                format!("{verb} \"{}\"", task.title),
+                Change::ToggleTask { task_id: task.id },
+            );
+            if !task.completed && task.recurrence().is_some() {
+                match self.spawn_next_occurrence(&task).await {
+                    Ok(Some(message)) => self.notify(message),
+                    Ok(None) => {}
+                    Err(err) => self.notify_error("Completed, but could not repeat it", err),
+                }
+            }
             let current_selection = self.task_state.selected();
             self.load_tasks_for_selected_workspace().await?;
             self.task_state.select(current_selection);
@@ -462,11 +1007,103 @@ impl App {
         Ok(())
     }
 
+    /// Creates the next occurrence of a just completed task and adds its
+    /// removal to the completion's undo entry. Returns a message naming it.
+    async fn spawn_next_occurrence(&mut self, task: &Task) -> Result<Option<String>> {
+        let workspace_tasks = self.workspace_tasks(task.workspace_id).await?;
+        let Some(next_id) = self
+            .client
+            .spawn_next_occurrence(task, &workspace_tasks)
+            .await?
+        else {
+            return Ok(None);
+        };
+
+        let reloaded = async {
+            self.load_tasks_for_selected_workspace().await?;
+            self.workspace_tasks(task.workspace_id).await
+        }
+        .await;
+        // Without a fresh list, undo still puts the rule back.
+        let next = reloaded
+            .as_deref()
+            .map(|tasks| TaskSnapshot::subtree(tasks, next_id))
+            .unwrap_or_default();
+        self.history.extend_latest(Change::Sequence(vec![
+            Change::DeleteTasks {
+                workspace_id: task.workspace_id,
+                tasks: next,
+            },
+            Change::SetRecurrence {
+                workspace_id: task.workspace_id,
+                task_id: task.id,
+                recurrence: (None, task.recurrence()),
+            },
+        ]));
+
+        let next_due = reloaded?
+            .iter()
+            .find(|task| task.id == next_id)
+            .and_then(Task::due)
+            .map_or_else(String::new, |due| format!(", due {}", due.format_local()));
+        Ok(Some(format!("Next \"{}\" created{next_due}", task.title)))
+    }
+
+    pub async fn raise_current_task_priority(&mut self) -> Result<()> {
+        self.change_current_task_priority(Priority::raised).await
+    }
+
+    pub async fn lower_current_task_priority(&mut self) -> Result<()> {
+        self.change_current_task_priority(Priority::lowered).await
+    }
+
+    async fn change_current_task_priority(&mut self, step: fn(Priority) -> Priority) -> Result<()> {
+        if self.focus == Focus::Tasks
+            && let Some(selected_task_idx) = self.task_state.selected()
+            && let Some(task_display) = self.task_displays.get(selected_task_idx)
+        {
+            let task = task_display.task.clone();
+            let current = task.priority();
+            let priority = step(current);
+            if priority == current {
+                return Ok(());
+            }
+            self.client
+                .update_task_priority(task.workspace_id, task.id, priority)
+                .await?;
+            self.history.record(
+                format!("set \"{}\" to {priority} priority", task.title),
+                Change::SetPriority {
+                    workspace_id: task.workspace_id,
+                    task_id: task.id,
+                    priority: (priority, current),
+                },
+            );
+            self.load_tasks_for_selected_workspace().await?;
+            self.select_task(task.id);
+        }
+        Ok(())
+    }
+
     pub async fn archive_completed_tasks(&mut self) -> Result<()> {
         if let Some(selected) = self.selected_workspace
             && let Some(workspace) = self.workspaces.get(selected)
         {
-            self.client.archive_completed_tasks(workspace.id).await?;
+            let workspace_id = workspace.id;
+            let task_ids = self.client.archive_completed_tasks(workspace_id).await?;
+            if !task_ids.is_empty() {
+                self.history.record(
+                    format!(
+                        "archive {} completed {}",
+                        task_ids.len(),
+                        tasks_noun(task_ids.len())
+                    ),
+                    Change::UnarchiveTasks {
+                        workspace_id,
+                        task_ids,
+                    },
+                );
+            }
             self.load_tasks_for_selected_workspace().await?;
         }
         Ok(())
@@ -491,12 +1128,19 @@ impl App {
                     return;
                 };
                 self.edit_title_buffer = task_display.task.title.clone().into();
-                self.edit_due_date_buffer = task_display
-                    .task
-                    .due_date
-                    .clone()
+                let task = &task_display.task;
+                self.edit_due_date_buffer = match task.due() {
+                    Some(due) => due.format_local(),
+                    None => task.due_date.clone().unwrap_or_default(),
+                }
+                .into();
+                self.edit_repeat_buffer = task
+                    .recurrence()
+                    .as_ref()
+                    .map(repeat::describe)
                     .unwrap_or_default()
                     .into();
+                self.edit_tags_buffer = tag_input::describe(&task.tags()).into();
                 self.edit_field = EditField::Title;
             }
         }
@@ -506,7 +1150,40 @@ impl App {
     pub fn toggle_edit_field(&mut self) {
         self.edit_field = match self.edit_field {
             EditField::Title => EditField::DueDate,
-            EditField::DueDate => EditField::Title,
+            EditField::DueDate => EditField::Repeat,
+            EditField::Repeat => EditField::Tags,
+            EditField::Tags => EditField::Title,
+        };
+    }
+
+    /// Completions for the tag being typed in the edit popup.
+    pub fn tag_suggestions(&self) -> Vec<String> {
+        tag_input::suggestions(self.edit_tags_buffer.value(), &self.tag_names())
+    }
+
+    /// Completes the tag being typed with the first suggestion; `false`
+    /// when there is none.
+    pub fn complete_tag(&mut self) -> bool {
+        let Some(tag) = self.tag_suggestions().into_iter().next() else {
+            return false;
+        };
+        self.edit_tags_buffer = tag_input::complete(self.edit_tags_buffer.value(), &tag).into();
+        true
+    }
+
+    pub fn next_edit_field(&mut self) {
+        self.edit_field = match self.edit_field {
+            EditField::Title => EditField::DueDate,
+            EditField::DueDate => EditField::Repeat,
+            EditField::Repeat | EditField::Tags => EditField::Tags,
+        };
+    }
+
+    pub fn previous_edit_field(&mut self) {
+        self.edit_field = match self.edit_field {
+            EditField::Title | EditField::DueDate => EditField::Title,
+            EditField::Repeat => EditField::DueDate,
+            EditField::Tags => EditField::Repeat,
         };
     }
 
@@ -516,6 +1193,8 @@ impl App {
             Focus::Tasks => match self.edit_field {
                 EditField::Title => &mut self.edit_title_buffer,
                 EditField::DueDate => &mut self.edit_due_date_buffer,
+                EditField::Repeat => &mut self.edit_repeat_buffer,
+                EditField::Tags => &mut self.edit_tags_buffer,
             },
         }
     }
@@ -526,9 +1205,19 @@ impl App {
                 if let Some(selected) = self.workspace_state.selected()
                     && let Some(workspace) = self.workspaces.get(selected)
                 {
+                    let name = self.input_buffer.value().to_string();
                     self.client
-                        .update_workspace_name(workspace.id, self.input_buffer.value())
+                        .update_workspace_name(workspace.id, &name)
                         .await?;
+                    if name != workspace.name {
+                        self.history.record(
+                            format!("rename workspace \"{}\"", workspace.name),
+                            Change::RenameWorkspace {
+                                workspace_id: workspace.id,
+                                name: (name, workspace.name.clone()),
+                            },
+                        );
+                    }
                     self.load_workspaces().await?;
                 }
             }
@@ -540,18 +1229,68 @@ impl App {
                     let normalized_due_date = if due_date.is_empty() {
                         None
                     } else {
-                        let Ok(date) = NaiveDate::parse_from_str(due_date, "%Y-%m-%d") else {
+                        // The popup stays open; its preview line shows why.
+                        let Ok(due) = due_date::parse(due_date, Local::now().date_naive()) else {
+                            self.edit_field = EditField::DueDate;
                             return Ok(());
                         };
-                        Some(date.format("%Y-%m-%d").to_string())
+                        // Keep the stored offset when the instant is unchanged.
+                        if task_display.task.due() == Some(due) {
+                            task_display.task.due_date.clone()
+                        } else {
+                            Some(due.to_string())
+                        }
+                    };
+                    let Ok(recurrence) = repeat::parse(self.edit_repeat_buffer.value()) else {
+                        self.edit_field = EditField::Repeat;
+                        return Ok(());
+                    };
+                    let Ok(tags) = tag_input::parse(self.edit_tags_buffer.value()) else {
+                        self.edit_field = EditField::Tags;
+                        return Ok(());
                     };
 
+                    let task = &task_display.task;
+                    let title = self.edit_title_buffer.value().to_string();
                     self.client
-                        .update_task_name(task_display.task.id, self.edit_title_buffer.value())
+                        .update_task_name(task.workspace_id, task.id, &title)
                         .await?;
                     self.client
-                        .update_task_due_date(task_display.task.id, normalized_due_date.as_deref())
+                        .update_task_due_date(
+                            task.workspace_id,
+                            task.id,
+                            normalized_due_date.as_deref(),
+                        )
                         .await?;
+                    let current_recurrence = task.recurrence();
+                    if recurrence != current_recurrence {
+                        self.client
+                            .update_task_recurrence(task.workspace_id, task.id, recurrence.as_ref())
+                            .await?;
+                    }
+                    let current_tags = task.tags();
+                    if tags != current_tags {
+                        self.client
+                            .update_task_tags(task.workspace_id, task.id, &tags)
+                            .await?;
+                    }
+                    if title != task.title
+                        || normalized_due_date != task.due_date
+                        || recurrence != current_recurrence
+                        || tags != current_tags
+                    {
+                        self.history.record(
+                            format!("edit \"{}\"", task.title),
+                            Change::EditTask {
+                                workspace_id: task.workspace_id,
+                                task_id: task.id,
+                                title: (title, task.title.clone()),
+                                due_date: (normalized_due_date, task.due_date.clone()),
+                                recurrence: (recurrence, current_recurrence),
+                                tags: (tags, current_tags),
+                            },
+                        );
+                    }
                     self.load_tasks_for_selected_workspace().await?;
                 }
             }
@@ -560,6 +1299,8 @@ impl App {
         self.input_buffer.reset();
         self.edit_title_buffer.reset();
         self.edit_due_date_buffer.reset();
+        self.edit_repeat_buffer.reset();
+        self.edit_tags_buffer.reset();
         Ok(())
     }
 
@@ -568,9 +1309,21 @@ impl App {
         self.input_buffer.reset();
         self.edit_title_buffer.reset();
         self.edit_due_date_buffer.reset();
+        self.edit_repeat_buffer.reset();
+        self.edit_tags_buffer.reset();
     }
 
     pub fn start_delete_confirm(&mut self) {
+        if let Some(failure) = self.selected_failure() {
+            let kind = match self.focus {
+                Focus::Workspaces => "workspace",
+                Focus::Tasks => "task",
+            };
+            self.delete_target = Some(format!("undecryptable {kind} #{}", failure.id));
+            self.input_mode = InputMode::DeleteConfirm;
+            return;
+        }
+
         let target_name = match self.focus {
             Focus::Workspaces => {
                 if let Some(selected) = self.workspace_state.selected() {
@@ -600,14 +1353,49 @@ impl App {
     pub async fn confirm_delete(&mut self) -> Result<()> {
         match self.focus {
             Focus::Workspaces => {
+                let workspace_id = self
+                    .selected_workspace_failure()
+                    .map(|failure| failure.id)
+                    .or_else(|| {
+                        self.workspace_state
+                            .selected()
+                            .and_then(|selected| self.workspaces.get(selected))
+                            .map(|workspace| workspace.id)
+                    });
                 if let Some(selected) = self.workspace_state.selected()
-                    && let Some(workspace) = self.workspaces.get(selected)
+                    && let Some(workspace_id) = workspace_id
                 {
-                    self.client.delete_workspace(workspace.id).await?;
+                    // Undecryptable workspaces cannot be recreated.
+                    let undo = match self.workspaces.iter().find(|w| w.id == workspace_id) {
+                        Some(workspace) => Some((
+                            workspace.name.clone(),
+                            self.client
+                                .get_tasks_for_workspace(workspace_id)
+                                .await?
+                                .items,
+                        )),
+                        None => None,
+                    };
+                    self.client.delete_workspace(workspace_id).await?;
+                    if let Some((name, tasks)) = undo {
+                        let tasks = tasks
+                            .iter()
+                            .filter(|task| task.parent_task_id.is_none())
+                            .flat_map(|root| TaskSnapshot::subtree(&tasks, root.id))
+                            .collect();
+                        self.history.record(
+                            format!("delete workspace \"{name}\""),
+                            Change::RestoreWorkspace {
+                                workspace_id,
+                                name,
+                                tasks,
+                            },
+                        );
+                    }
                     self.load_workspaces().await?;
-                    if !self.workspaces.is_empty() {
-                        let new_selection = if selected >= self.workspaces.len() {
-                            self.workspaces.len() - 1
+                    if self.workspace_row_count() > 0 {
+                        let new_selection = if selected >= self.workspace_row_count() {
+                            self.workspace_row_count() - 1
                         } else {
                             selected
                         };
@@ -618,14 +1406,49 @@ impl App {
                 }
             }
             Focus::Tasks => {
+                let task_id = self
+                    .selected_task_failure()
+                    .map(|failure| failure.id)
+                    .or_else(|| {
+                        self.task_state
+                            .selected()
+                            .and_then(|selected| self.task_displays.get(selected))
+                            .map(|task_display| task_display.task.id)
+                    });
                 if let Some(selected) = self.task_state.selected()
-                    && let Some(task_display) = self.task_displays.get(selected)
+                    && let Some(task_id) = task_id
                 {
-                    self.client.delete_task(task_display.task.id).await?;
+                    // Undecryptable tasks cannot be recreated.
+                    let deleted = match self.tasks.iter().find(|task| task.id == task_id) {
+                        Some(task) => {
+                            let workspace_tasks = self.workspace_tasks(task.workspace_id).await?;
+                            Some((
+                                task.workspace_id,
+                                TaskSnapshot::subtree(&workspace_tasks, task_id),
+                            ))
+                        }
+                        None => None,
+                    };
+                    self.client.delete_task(task_id).await?;
+                    if let Some((workspace_id, tasks)) = deleted {
+                        let title = &tasks[0].title;
+                        let description = match tasks.len() - 1 {
+                            0 => format!("delete \"{title}\""),
+                            1 => format!("delete \"{title}\" and 1 subtask"),
+                            n => format!("delete \"{title}\" and {n} subtasks"),
+                        };
+                        self.history.record(
+                            description,
+                            Change::RestoreTasks {
+                                workspace_id,
+                                tasks,
+                            },
+                        );
+                    }
                     self.load_tasks_for_selected_workspace().await?;
-                    if !self.task_displays.is_empty() {
-                        let new_selection = if selected >= self.task_displays.len() {
-                            self.task_displays.len() - 1
+                    if self.task_row_count() > 0 {
+                        let new_selection = if selected >= self.task_row_count() {
+                            self.task_row_count() - 1
                         } else {
                             selected
                         };
@@ -643,6 +1466,48 @@ impl App {
         self.delete_target = None;
     }
 
+    pub async fn publish_share_link(&mut self) -> Result<()> {
+        if let Some(selected) = self.selected_workspace
+            && let Some(workspace) = self.workspaces.get(selected)
+        {
+            let expires_at = Utc::now() + chrono::Duration::days(SHARE_LINK_TTL_DAYS);
+            let link = self
+                .client
+                .publish_share(workspace.id, Some(expires_at))
+                .await?;
+            self.share_link = Some(link);
+            self.input_mode = InputMode::ShareLink;
+        }
+        Ok(())
+    }
+
+    pub fn hide_share_link(&mut self) {
+        self.share_link = None;
+        self.input_mode = InputMode::Normal;
+    }
+
+    pub async fn undo(&mut self) -> Result<()> {
+        match self.history.undo(&self.client).await? {
+            Some(description) => self.notify(format!("Undid: {description}")),
+            None => {
+                self.notify("Nothing to undo".to_string());
+                return Ok(());
+            }
+        }
+        self.reload_workspaces_keeping_selection().await
+    }
+
+    pub async fn redo(&mut self) -> Result<()> {
+        match self.history.redo(&self.client).await? {
+            Some(description) => self.notify(format!("Redid: {description}")),
+            None => {
+                self.notify("Nothing to redo".to_string());
+                return Ok(());
+            }
+        }
+        self.reload_workspaces_keeping_selection().await
+    }
+
     pub fn show_help(&mut self) {
         self.input_mode = InputMode::Help;
     }
@@ -650,21 +1515,77 @@ impl App {
     pub fn hide_help(&mut self) {
         self.input_mode = InputMode::Normal;
     }
+
+    /// Adds a key to those typed so far and returns the action they are
+    /// bound to, if any. A key that does not continue a sequence starts a
+    /// new one.
+    pub fn resolve_key(&mut self, key: KeyEvent) -> Option<Action> {
+        self.pending_keys.push(KeyChord::from(key));
+        let mut resolved = self.keymap.resolve(&self.pending_keys);
+        if matches!(resolved, Resolved::Unbound) && self.pending_keys.len() > 1 {
+            self.pending_keys.drain(..self.pending_keys.len() - 1);
+            resolved = self.keymap.resolve(&self.pending_keys);
+        }
+        match resolved {
+            Resolved::Action(action) => {
+                self.pending_keys.clear();
+                Some(action)
+            }
+            Resolved::Pending => None,
+            Resolved::Unbound => {
+                self.pending_keys.clear();
+                None
+            }
+        }
+    }
+
+    /// Whether `key` alone is bound to `action`.
+    fn is_bound(&self, key: KeyEvent, action: Action) -> bool {
+        matches!(self.keymap.resolve(&[KeyChord::from(key)]), Resolved::Action(bound) if bound == action)
+    }
 }
 
-pub async fn run_app(client: Client) -> Result<()> {
+pub async fn run_app(
+    client: Client,
+    display: &DisplayConfig,
+    keymap: Keymap,
+    theme: Theme,
+) -> Result<()> {
     enable_raw_mode()?;
     let mut stdout = io::stdout();
     execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
 
-    let mut app = App::new(client);
+    let mut app = App::new(client, display, keymap, theme);
+    match Folds::load() {
+        Ok(folds) => app.folds = folds,
+        Err(err) => app.notify_error("Could not load folded tasks", err),
+    }
+    if let Err(err) = app.client.register_public_key().await {
+        app.notify_error("Could not register public key", err);
+    }
     if let Err(err) = app.load_workspaces().await {
         app.notify_error("Could not load workspaces", err);
     }
 
-    let res = run_app_loop(&mut terminal, &mut app).await;
+    let (updates_tx, mut updates) = mpsc::unbounded_channel();
+    let live_task = app.client.supports(FEATURE_EVENTS).then(|| {
+        let live_updates = app.client.subscribe();
+        tokio::spawn(async move {
+            let mut live_updates = std::pin::pin!(live_updates);
+            while let Some(update) = live_updates.next().await {
+                if updates_tx.send(update).is_err() {
+                    break;
+                }
+            }
+        })
+    });
+
+    let res = run_app_loop(&mut terminal, &mut app, &mut updates).await;
+    if let Some(live_task) = live_task {
+        live_task.abort();
+    }
 
     disable_raw_mode()?;
     execute!(
@@ -684,8 +1605,19 @@ pub async fn run_app(client: Client) -> Result<()> {
 async fn run_app_loop(
     terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
     app: &mut App,
+    updates: &mut mpsc::UnboundedReceiver<LiveUpdate>,
 ) -> Result<()> {
     loop {
+        // Changes from other devices wait until nothing is being edited.
+        if app.input_mode == InputMode::Normal {
+            let pending: Vec<LiveUpdate> = std::iter::from_fn(|| updates.try_recv().ok()).collect();
+            if !pending.is_empty()
+                && let Err(err) = app.apply_live_updates(pending).await
+            {
+                app.notify_error("Could not refresh", err);
+            }
+        }
+
         app.clear_expired_notification();
         terminal.draw(|f| ui(f, app))?;
 
@@ -704,74 +1636,13 @@ async fn run_app_loop(
 
         if let Event::Key(key) = event::read()? {
             match app.input_mode {
-                InputMode::Normal => match key.code {
-                    KeyCode::Char('q') => return Ok(()),
-                    KeyCode::Down | KeyCode::Char('j') => match app.focus {
-                        Focus::Workspaces => match app.next_workspace().await {
-                            Ok(()) => app.clear_notification(),
-                            Err(err) => app.notify_error("Could not load workspace", err),
-                        },
-                        Focus::Tasks => app.next_task(),
-                    },
-                    KeyCode::Up | KeyCode::Char('k') => match app.focus {
-                        Focus::Workspaces => match app.previous_workspace().await {
-                            Ok(()) => app.clear_notification(),
-                            Err(err) => app.notify_error("Could not load workspace", err),
-                        },
-                        Focus::Tasks => app.previous_task(),
-                    },
-                    KeyCode::Right | KeyCode::Char('l') => {
-                        app.focus = Focus::Tasks;
-                    }
-                    KeyCode::Left | KeyCode::Char('h') => {
-                        app.focus = Focus::Workspaces;
+                InputMode::Normal => {
+                    if let Some(action) = app.resolve_key(key)
+                        && perform(app, action).await.is_break()
+                    {
+                        return Ok(());
                     }
-                    KeyCode::Tab => {
-                        app.focus = match app.focus {
-                            Focus::Workspaces => Focus::Tasks,
-                            Focus::Tasks => Focus::Workspaces,
-                        };
-                    }
-                    KeyCode::Esc if !app.search_query.is_empty() => {
-                        app.cancel_search();
-                    }
-                    KeyCode::Char('A') => {
-                        if app.focus == Focus::Tasks {
-                            app.start_creating_subtask();
-                        } else {
-                            app.start_creating_task();
-                        }
-                    }
-                    KeyCode::Char('a') => {
-                        app.start_creating_task();
-                    }
-                    KeyCode::Char('c') | KeyCode::Char(' ') => {
-                        match app.toggle_current_task_completion().await {
-                            Ok(()) => app.clear_notification(),
-                            Err(err) => app.notify_error("Could not update task", err),
-                        }
-                    }
-                    KeyCode::Char('e') => {
-                        app.start_rename();
-                    }
-                    KeyCode::Char('/') => {
-                        app.start_search();
-                    }
-                    KeyCode::Char('s') => {
-                        app.toggle_sort_order();
-                    }
-                    KeyCode::Char('x') => match app.archive_completed_tasks().await {
-                        Ok(()) => app.clear_notification(),
-                        Err(err) => app.notify_error("Could not archive tasks", err),
-                    },
-                    KeyCode::Char('D') => {
-                        app.start_delete_confirm();
-                    }
-                    KeyCode::Char('?') => {
-                        app.show_help();
-                    }
-                    _ => {}
-                },
+                }
                 InputMode::Insert => match key.code {
                     KeyCode::Enter => match app.finish_rename().await {
                         Ok(()) => app.clear_notification(),
@@ -781,18 +1652,20 @@ async fn run_app_loop(
                         app.cancel_rename();
                     }
                     KeyCode::Tab => {
-                        if app.focus == Focus::Tasks {
+                        if app.focus == Focus::Tasks
+                            && !(app.edit_field == EditField::Tags && app.complete_tag())
+                        {
                             app.toggle_edit_field();
                         }
                     }
                     KeyCode::Up => {
                         if app.focus == Focus::Tasks {
-                            app.edit_field = EditField::Title;
+                            app.previous_edit_field();
                         }
                     }
                     KeyCode::Down => {
                         if app.focus == Focus::Tasks {
-                            app.edit_field = EditField::DueDate;
+                            app.next_edit_field();
                         }
                     }
                     _ => {
@@ -835,18 +1708,180 @@ async fn run_app_loop(
                     }
                     _ => {}
                 },
+                InputMode::TagPicker => match key.code {
+                    KeyCode::Enter => {
+                        if let Err(err) = app.apply_tag_filter().await {
+                            app.notify_error("Could not load tasks", err);
+                        }
+                    }
+                    KeyCode::Down | KeyCode::Char('j') => app.next_tag(),
+                    KeyCode::Up | KeyCode::Char('k') => app.previous_tag(),
+                    KeyCode::Esc | KeyCode::Char('#') | KeyCode::Char('q') => {
+                        app.close_tag_picker();
+                    }
+                    _ => {}
+                },
                 InputMode::Help => match key.code {
-                    KeyCode::Char('?') | KeyCode::Esc | KeyCode::Char('q') => {
+                    KeyCode::Esc | KeyCode::Char('q') => {
+                        app.hide_help();
+                    }
+                    _ if app.is_bound(key, Action::Help) => {
                         app.hide_help();
                     }
                     _ => {}
                 },
+                InputMode::ShareLink => match key.code {
+                    KeyCode::Enter | KeyCode::Esc | KeyCode::Char('q') => {
+                        app.hide_share_link();
+                    }
+                    _ => {}
+                },
+                InputMode::Inspect => match key.code {
+                    KeyCode::Char('D') => {
+                        app.start_delete_confirm();
+                    }
+                    KeyCode::Enter | KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('i') => {
+                        app.hide_inspect();
+                    }
+                    _ => {}
+                },
+            }
+        }
+    }
+}
+
+/// Runs a normal-mode action; breaks when the app should quit.
+async fn perform(app: &mut App, action: Action) -> ControlFlow<()> {
+    match action {
+        Action::Quit => return ControlFlow::Break(()),
+        Action::Redo => {
+            if let Err(err) = app.redo().await {
+                app.notify_error("Could not redo", err);
+            }
+        }
+        Action::Undo => {
+            if let Err(err) = app.undo().await {
+                app.notify_error("Could not undo", err);
+            }
+        }
+        Action::Down => match app.focus {
+            Focus::Workspaces => match app.next_workspace().await {
+                Ok(()) => app.clear_notification(),
+                Err(err) => app.notify_error("Could not load workspace", err),
+            },
+            Focus::Tasks => app.next_task(),
+        },
+        Action::Up => match app.focus {
+            Focus::Workspaces => match app.previous_workspace().await {
+                Ok(()) => app.clear_notification(),
+                Err(err) => app.notify_error("Could not load workspace", err),
+            },
+            Focus::Tasks => app.previous_task(),
+        },
+        Action::FocusTasks => {
+            app.focus = Focus::Tasks;
+        }
+        Action::FocusWorkspaces => {
+            app.focus = Focus::Workspaces;
+        }
+        Action::SwitchFocus => {
+            app.focus = match app.focus {
+                Focus::Workspaces => Focus::Tasks,
+                Focus::Tasks => Focus::Workspaces,
+            };
+        }
+        Action::Back => {
+            if !app.search_query.is_empty() {
+                app.cancel_search();
+            } else if app.view != TaskView::Workspace
+                && let Err(err) = app.show_workspace().await
+            {
+                app.notify_error("Could not load workspace", err);
+            }
+        }
+        Action::OpenTaskWorkspace => {
+            if let Err(err) = app.jump_to_task_workspace().await {
+                app.notify_error("Could not load workspace", err);
+            }
+        }
+        Action::ToggleAgenda => match app.toggle_agenda().await {
+            Ok(()) => app.clear_notification(),
+            Err(err) => app.notify_error("Could not load agenda", err),
+        },
+        Action::FilterByTag => match app.open_tag_picker().await {
+            Ok(()) => app.clear_notification(),
+            Err(err) => app.notify_error("Could not load tags", err),
+        },
+        Action::AddSubtask => {
+            if app.focus == Focus::Tasks {
+                app.start_creating_subtask();
+            } else {
+                app.start_creating_task();
+            }
+        }
+        Action::AddTask => {
+            app.start_creating_task();
+        }
+        Action::ToggleCompletion => {
+            if let Err(err) = app.toggle_current_task_completion().await {
+                app.notify_error("Could not update task", err);
+            }
+        }
+        Action::Edit => {
+            app.start_rename();
+        }
+        Action::Search => {
+            app.start_search();
+        }
+        Action::ToggleSort => {
+            app.toggle_sort_order();
+        }
+        Action::ToggleFold => {
+            if let Err(err) = app.toggle_fold() {
+                app.notify_error("Could not save folded tasks", err);
+            }
+        }
+        Action::FoldAll => {
+            if let Err(err) = app.set_all_folds(true) {
+                app.notify_error("Could not save folded tasks", err);
             }
         }
+        Action::UnfoldAll => {
+            if let Err(err) = app.set_all_folds(false) {
+                app.notify_error("Could not save folded tasks", err);
+            }
+        }
+        Action::RaisePriority => match app.raise_current_task_priority().await {
+            Ok(()) => app.clear_notification(),
+            Err(err) => app.notify_error("Could not change priority", err),
+        },
+        Action::LowerPriority => match app.lower_current_task_priority().await {
+            Ok(()) => app.clear_notification(),
+            Err(err) => app.notify_error("Could not change priority", err),
+        },
+        Action::Archive => match app.archive_completed_tasks().await {
+            Ok(()) => app.clear_notification(),
+            Err(err) => app.notify_error("Could not archive tasks", err),
+        },
+        Action::Delete => {
+            app.start_delete_confirm();
+        }
+        Action::Share => match app.publish_share_link().await {
+            Ok(()) => app.clear_notification(),
+            Err(err) => app.notify_error("Could not publish share link", err),
+        },
+        Action::Inspect => {
+            app.inspect_failure();
+        }
+        Action::Help => {
+            app.show_help();
+        }
     }
+    ControlFlow::Continue(())
 }
 
 fn ui(f: &mut Frame, app: &mut App) {
+    let theme = &app.theme;
     let main_chunks = Layout::default()
         .direction(Direction::Vertical)
         .constraints([Constraint::Min(0), Constraint::Length(1)])
@@ -865,33 +1900,43 @@ fn ui(f: &mut Frame, app: &mut App) {
             let completed = stats.map_or(0, |s| s.completed);
             let total = stats.map_or(0, |s| s.total);
 
-            ListItem::new(Line::from(vec![
+            let mut spans = vec![
                 Span::raw(&w.name),
-                Span::styled(
-                    format!(" ({completed}/{total})"),
-                    Style::default().fg(Color::DarkGray),
-                ),
-            ]))
+                Span::styled(format!(" ({completed}/{total})"), theme.muted),
+            ];
+            if w.role != WorkspaceRole::Owner {
+                spans.push(Span::styled(
+                    format!(" [{}]", w.role.as_str()),
+                    theme.accent,
+                ));
+            }
+            ListItem::new(Line::from(spans))
         })
+        .chain(
+            app.workspace_failures
+                .iter()
+                .map(|failure| failure_item(failure, theme)),
+        )
         .collect();
 
-    let workspace_block = if app.focus == Focus::Workspaces {
-        Block::default()
-            .title("workspaces")
-            .borders(Borders::ALL)
-            .border_style(Style::default().fg(Color::Blue))
-    } else {
-        Block::default().title("workspaces").borders(Borders::ALL)
-    };
+    let workspace_block = Block::default()
+        .title("workspaces")
+        .borders(Borders::ALL)
+        .border_style(if app.focus == Focus::Workspaces {
+            theme.focused_border
+        } else {
+            theme.border
+        });
     let workspaces = List::new(workspace_items)
         .block(workspace_block)
-        .style(Style::default().fg(Color::White))
-        .highlight_style(Style::default().add_modifier(Modifier::BOLD))
+        .style(theme.text)
+        .highlight_style(theme.selection)
         .highlight_symbol(">> ");
 
     f.render_stateful_widget(workspaces, content_chunks[0], &mut app.workspace_state);
 
-    let task_items: Vec<ListItem> = app
+    let now = Utc::now();
+    let mut task_items: Vec<ListItem> = app
         .task_displays
         .iter()
         .map(|td| {
@@ -899,74 +1944,143 @@ fn ui(f: &mut Frame, app: &mut App) {
             let checkbox = if td.task.completed { "×" } else { " " };
             let date = td.task.created_at.format("%m/%d/%y").to_string();
 
-            let task_span = Span::raw(format!("{}[{}] {}", indent, checkbox, td.task.title));
-            let date_span =
-                Span::styled(format!(" ({date})"), Style::default().fg(Color::DarkGray));
-            let today = Local::now().date_naive();
+            let task_span = Span::raw(td.task.title.clone());
+            let priority_span = priority_marker(td.task.priority(), theme);
+            let date_span = Span::styled(format!(" ({date})"), theme.muted);
             let due_date_span = td.task.due_date.as_ref().map(|due_date| {
-                let due_date_color = match NaiveDate::parse_from_str(due_date, "%Y-%m-%d") {
-                    Ok(parsed_due_date) if today > parsed_due_date => Color::Red,
-                    _ => Color::Yellow,
+                let Some(due) = td.task.due() else {
+                    return Span::styled(format!(" due {due_date}"), theme.due);
                 };
+                let deadline = due.deadline();
+                let due_date_style = if deadline <= now {
+                    theme.overdue
+                } else if !td.task.completed && deadline - now <= app.due_soon {
+                    theme.due_soon
+                } else {
+                    theme.due
+                };
+                Span::styled(format!(" due {}", due.format_local()), due_date_style)
+            });
+            let repeat_span = td.task.recurrence().map(|recurrence| {
                 Span::styled(
-                    format!(" due {due_date}"),
-                    Style::default().fg(due_date_color),
+                    format!(" ↻ {}", repeat::describe(&recurrence)),
+                    theme.accent,
                 )
             });
+            let folded_span = (td.hidden > 0).then(|| {
+                Span::styled(
+                    format!(" ▸ {} {}", td.hidden, subtasks_noun(td.hidden)),
+                    theme.accent,
+                )
+            });
+            let task_tags = td.task.tags();
+            let tags_span = (!task_tags.is_empty())
+                .then(|| Span::styled(format!(" {}", tag_input::describe(&task_tags)), theme.tag));
+            // A tag view mixes workspaces, so each row names its own.
+            let workspace_span = app
+                .workspaces
+                .iter()
+                .find(|workspace| workspace.id == td.task.workspace_id)
+                .filter(|_| app.view != TaskView::Workspace)
+                .map(|workspace| Span::styled(format!(" [{}]", workspace.name), theme.muted));
 
             if td.task.completed {
-                let mut spans = vec![
-                    Span::styled(
-                        format!("{}[{}] {}", indent, checkbox, td.task.title),
-                        Style::default()
-                            .add_modifier(Modifier::CROSSED_OUT)
-                            .fg(Color::DarkGray),
-                    ),
-                    date_span,
-                ];
-                if let Some(due_date_span) = due_date_span {
-                    spans.push(due_date_span);
-                }
+                let completed_style = theme.completed;
+                let mut spans = vec![Span::styled(
+                    format!("{}[{}] ", indent, checkbox),
+                    completed_style,
+                )];
+                spans.extend(priority_span.map(|span| span.style(theme.muted)));
+                spans.push(Span::styled(td.task.title.clone(), completed_style));
+                spans.push(date_span);
+                spans.extend(workspace_span);
+                spans.extend(due_date_span);
+                spans.extend(repeat_span);
+                spans.extend(tags_span);
+                spans.extend(folded_span);
                 ListItem::new(Line::from(spans))
             } else {
-                let mut spans = vec![task_span, date_span];
-                if let Some(due_date_span) = due_date_span {
-                    spans.push(due_date_span);
-                }
+                let mut spans = vec![Span::raw(format!("{}[{}] ", indent, checkbox))];
+                spans.extend(priority_span);
+                spans.push(task_span);
+                spans.push(date_span);
+                spans.extend(workspace_span);
+                spans.extend(due_date_span);
+                spans.extend(repeat_span);
+                spans.extend(tags_span);
+                spans.extend(folded_span);
                 ListItem::new(Line::from(spans))
             }
         })
+        .chain(
+            app.task_failures
+                .iter()
+                .map(|failure| failure_item(failure, theme)),
+        )
         .collect();
 
+    if app.view == TaskView::Agenda {
+        for (index, (start, group)) in app.agenda_groups.iter().enumerate().rev() {
+            let end = app
+                .agenda_groups
+                .get(index + 1)
+                .map_or(app.task_displays.len(), |(end, _)| *end);
+            task_items.insert(*start, agenda_heading(*group, end - start, theme));
+        }
+    }
+
     let sort_label = if app.sort_created_desc {
         "newest first"
     } else {
         "oldest first"
     };
-    let task_title = if app.search_query.trim().is_empty() {
-        format!("tasks ({sort_label})")
-    } else {
-        format!("tasks ({sort_label}, search)")
+    let task_title = match &app.view {
+        TaskView::Workspace => format!("tasks ({sort_label}"),
+        TaskView::Tag(tag) => format!(
+            "tasks tagged {} in all workspaces ({sort_label}",
+            tags::label(tag)
+        ),
+        TaskView::Agenda => "agenda of all workspaces (open tasks".to_string(),
     };
-    let task_block = if app.focus == Focus::Tasks {
-        Block::default()
-            .title(task_title)
-            .borders(Borders::ALL)
-            .border_style(Style::default().fg(Color::Blue))
+    let task_title = if app.search_query.trim().is_empty() {
+        format!("{task_title})")
     } else {
-        Block::default().title(task_title).borders(Borders::ALL)
+        format!("{task_title}, search)")
     };
+    let task_block = Block::default()
+        .title(task_title)
+        .borders(Borders::ALL)
+        .border_style(if app.focus == Focus::Tasks {
+            theme.focused_border
+        } else {
+            theme.border
+        });
     let tasks = List::new(task_items)
         .block(task_block)
-        .style(Style::default().fg(Color::White))
-        .highlight_style(Style::default().add_modifier(Modifier::BOLD))
+        .style(theme.text)
+        .highlight_style(theme.selection)
         .highlight_symbol(">> ");
 
-    f.render_stateful_widget(tasks, content_chunks[1], &mut app.task_state);
+    if app.view == TaskView::Agenda {
+        // Headings are extra rows, so the selection moves down past the
+        // ones above it.
+        let selected = app.task_state.selected().map(|selected| {
+            selected
+                + app
+                    .agenda_groups
+                    .iter()
+                    .filter(|(start, _)| *start <= selected)
+                    .count()
+        });
+        app.agenda_state.select(selected);
+        f.render_stateful_widget(tasks, content_chunks[1], &mut app.agenda_state);
+    } else {
+        f.render_stateful_widget(tasks, content_chunks[1], &mut app.task_state);
+    }
 
     match app.input_mode {
         InputMode::Insert => {
-            let popup_area = centered_rect(60, 20, f.area());
+            let popup_area = centered_rect(60, 50, f.area());
             f.render_widget(Clear, popup_area);
 
             match app.focus {
@@ -977,7 +2091,7 @@ fn ui(f: &mut Frame, app: &mut App) {
                                 .title("edit workspace")
                                 .borders(Borders::ALL),
                         )
-                        .style(Style::default().fg(Color::Yellow));
+                        .style(theme.input);
                     f.render_widget(input, popup_area);
                     f.set_cursor_position((
                         popup_area.x + 1 + app.input_buffer.visual_cursor() as u16,
@@ -998,7 +2112,10 @@ fn ui(f: &mut Frame, app: &mut App) {
                     let due_date_line = if app.edit_due_date_buffer.value().is_empty() {
                         Line::from(vec![
                             Span::raw(format!("{due_date_marker}due: ")),
-                            Span::styled("YYYY-MM-DD", Style::default().fg(Color::DarkGray)),
+                            Span::styled(
+                                "YYYY-MM-DD, tomorrow 9am, fri 14:00, +3d, oct 20...",
+                                theme.muted,
+                            ),
                         ])
                     } else {
                         Line::from(format!(
@@ -1006,15 +2123,91 @@ fn ui(f: &mut Frame, app: &mut App) {
                             app.edit_due_date_buffer.value()
                         ))
                     };
+                    let due_date_input = app.edit_due_date_buffer.value().trim();
+                    let preview_line = if due_date_input.is_empty() {
+                        Line::from(Span::styled("       no due date", theme.muted))
+                    } else {
+                        let today = Local::now().date_naive();
+                        match due_date::parse(due_date_input, today) {
+                            Ok(date) => Line::from(Span::styled(
+                                format!("       = {}", due_date::describe(&date, today)),
+                                theme.valid,
+                            )),
+                            Err(err) => {
+                                Line::from(Span::styled(format!("       {err}"), theme.invalid))
+                            }
+                        }
+                    };
+                    let repeat_marker = if app.edit_field == EditField::Repeat {
+                        "> "
+                    } else {
+                        "  "
+                    };
+                    let repeat_line = if app.edit_repeat_buffer.value().is_empty() {
+                        Line::from(vec![
+                            Span::raw(format!("{repeat_marker}repeat: ")),
+                            Span::styled(
+                                "weekly on mon, every 2 days, monthly after completion...",
+                                theme.muted,
+                            ),
+                        ])
+                    } else {
+                        Line::from(format!(
+                            "{repeat_marker}repeat: {}",
+                            app.edit_repeat_buffer.value()
+                        ))
+                    };
+                    let repeat_preview_line = match repeat::parse(app.edit_repeat_buffer.value()) {
+                        Ok(None) => Line::from(Span::styled("       does not repeat", theme.muted)),
+                        Ok(Some(recurrence)) => Line::from(Span::styled(
+                            format!("       = {}", repeat::describe(&recurrence)),
+                            theme.valid,
+                        )),
+                        Err(err) => {
+                            Line::from(Span::styled(format!("       {err}"), theme.invalid))
+                        }
+                    };
+                    let tags_marker = if app.edit_field == EditField::Tags {
+                        "> "
+                    } else {
+                        "  "
+                    };
+                    let tags_line = if app.edit_tags_buffer.value().is_empty() {
+                        Line::from(vec![
+                            Span::raw(format!("{tags_marker}tags: ")),
+                            Span::styled("#project @home, tab completes", theme.muted),
+                        ])
+                    } else {
+                        Line::from(format!(
+                            "{tags_marker}tags: {}",
+                            app.edit_tags_buffer.value()
+                        ))
+                    };
+                    let suggestions = app.tag_suggestions();
+                    let tags_preview_line = match tag_input::parse(app.edit_tags_buffer.value()) {
+                        Err(err) => {
+                            Line::from(Span::styled(format!("       {err}"), theme.invalid))
+                        }
+                        Ok(_) if !suggestions.is_empty() => Line::from(Span::styled(
+                            format!("       tab: {}", tag_input::describe(&suggestions)),
+                            theme.muted,
+                        )),
+                        Ok(_) => Line::default(),
+                    };
                     let input = Paragraph::new(vec![
                         Line::from(format!(
                             "{title_marker}title: {}",
                             app.edit_title_buffer.value()
                         )),
                         due_date_line,
+                        preview_line,
+                        repeat_line,
+                        repeat_preview_line,
+                        tags_line,
+                        tags_preview_line,
                     ])
                     .block(Block::default().title("edit task").borders(Borders::ALL))
-                    .style(Style::default().fg(Color::Yellow));
+                    .style(theme.input);
                     f.render_widget(input, popup_area);
 
                     let (cursor_y, cursor_x) = match app.edit_field {
@@ -1034,6 +2227,22 @@ fn ui(f: &mut Frame, app: &mut App) {
                                 + 5
                                 + app.edit_due_date_buffer.visual_cursor() as u16,
                         ),
+                        EditField::Repeat => (
+                            popup_area.y + 4,
+                            popup_area.x
+                                + 1
+                                + repeat_marker.len() as u16
+                                + 8
+                                + app.edit_repeat_buffer.visual_cursor() as u16,
+                        ),
+                        EditField::Tags => (
+                            popup_area.y + 6,
+                            popup_area.x
+                                + 1
+                                + tags_marker.len() as u16
+                                + 6
+                                + app.edit_tags_buffer.visual_cursor() as u16,
+                        ),
                     };
                     f.set_cursor_position((cursor_x, cursor_y));
                 }
@@ -1052,7 +2261,7 @@ fn ui(f: &mut Frame, app: &mut App) {
                         .title("confirm delete")
                         .borders(Borders::ALL),
                 )
-                .style(Style::default().fg(Color::Red));
+                .style(theme.invalid);
             f.render_widget(confirm, popup_area);
         }
         InputMode::Creating => {
@@ -1071,7 +2280,7 @@ fn ui(f: &mut Frame, app: &mut App) {
             };
             let input = Paragraph::new(app.input_buffer.value())
                 .block(Block::default().title(title).borders(Borders::ALL))
-                .style(Style::default().fg(Color::Green));
+                .style(theme.valid);
             f.render_widget(input, popup_area);
             f.set_cursor_position((
                 popup_area.x + 1 + app.input_buffer.visual_cursor() as u16,
@@ -1079,48 +2288,154 @@ fn ui(f: &mut Frame, app: &mut App) {
             ));
         }
         InputMode::Help => {
-            let popup_area = centered_rect(80, 60, f.area());
+            let popup_area = centered_rect(80, 80, f.area());
+            f.render_widget(Clear, popup_area);
+            let block = Block::default().title("help").borders(Borders::ALL);
+            let columns = Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+                .split(block.inner(popup_area));
+            f.render_widget(block, popup_area);
+
+            let mut navigation = help_section("Navigation:", &app.keymap.help(Section::Navigation));
+            navigation.push(Line::default());
+            navigation.push(Line::from("Editing:"));
+            for (keys, description) in [
+                ("tab", "next field, or complete a tag"),
+                ("up/down", "previous/next field"),
+                ("enter", "save"),
+                ("esc", "cancel"),
+            ] {
+                navigation.push(Line::from(format!("  {keys}: {description}")));
+            }
+            navigation.push(Line::default());
+            navigation.push(Line::from(format!(
+                "Press {} or esc to close",
+                app.keymap.label(Action::Help)
+            )));
+            let actions = help_section("Actions:", &app.keymap.help(Section::Actions));
+
+            for (lines, area) in [(navigation, columns[0]), (actions, columns[1])] {
+                let help = Paragraph::new(lines)
+                    .style(theme.text)
+                    .wrap(Wrap { trim: false });
+                f.render_widget(help, area);
+            }
+        }
+        InputMode::ShareLink => {
+            let popup_area = centered_rect(80, 30, f.area());
             f.render_widget(Clear, popup_area);
 
-            let help_text = r#"Navigation:
-  h/l/tab: switch focus between workspaces and tasks
-  j/k: navigate up/down in focused panel
-
-Actions:
-  A: add subtask (when on tasks) or workspace
-  a: add new top-level task
-  /: search tasks
-  e: edit selected item
-  tab: switch edit fields
-  s: reverse creation-date sort
-  x: archive completed tasks
-  c: complete/uncomplete task
-  D: delete selected item
-  ?: show/hide this help
-  q: quit
-
-Press ? or ESC to close"#;
-            let help = Paragraph::new(help_text)
-                .block(Block::default().title("help").borders(Borders::ALL))
-                .style(Style::default().fg(Color::White));
-            f.render_widget(help, popup_area);
+            let link = app.share_link.as_deref().unwrap_or_default();
+            let share_text = format!(
+                "{link}\n\nAnyone with this link can read a snapshot of this workspace for {SHARE_LINK_TTL_DAYS} days.\nOpen it with: todo open <link>\n\nenter/esc: close"
+            );
+            let share = Paragraph::new(share_text)
+                .block(Block::default().title("share link").borders(Borders::ALL))
+                .style(theme.valid)
+                .wrap(Wrap { trim: false });
+            f.render_widget(share, popup_area);
+        }
+        InputMode::Inspect => {
+            let popup_area = centered_rect(60, 30, f.area());
+            f.render_widget(Clear, popup_area);
+
+            let (kind, failure) = match app.focus {
+                Focus::Workspaces => ("Workspace", app.selected_workspace_failure()),
+                Focus::Tasks => ("Task", app.selected_task_failure()),
+            };
+            let inspect_text = failure.map_or_else(String::new, |failure| {
+                format!(
+                    "{kind} #{} could not be decrypted:\n{}\n\nD: delete | enter/esc: close",
+                    failure.id, failure.reason
+                )
+            });
+            let inspect = Paragraph::new(inspect_text)
+                .block(
+                    Block::default()
+                        .title("undecryptable item")
+                        .borders(Borders::ALL),
+                )
+                .style(theme.input)
+                .wrap(Wrap { trim: false });
+            f.render_widget(inspect, popup_area);
+        }
+        InputMode::TagPicker => {
+            let popup_area = centered_rect(40, 50, f.area());
+            f.render_widget(Clear, popup_area);
+
+            let block = Block::default()
+                .title("tasks with tag (enter: show | esc: close)")
+                .borders(Borders::ALL);
+            if app.known_tags.is_empty() {
+                let empty = Paragraph::new("No tags yet. Add some with e, or #tag in a new title.")
+                    .block(block)
+                    .style(theme.muted)
+                    .wrap(Wrap { trim: true });
+                f.render_widget(empty, popup_area);
+            } else {
+                let tag_items: Vec<ListItem> = app
+                    .known_tags
+                    .iter()
+                    .map(|(tag, count)| {
+                        ListItem::new(Line::from(vec![
+                            Span::styled(tags::label(tag), theme.tag),
+                            Span::styled(format!(" ({count})"), theme.muted),
+                        ]))
+                    })
+                    .collect();
+                let tag_list = List::new(tag_items)
+                    .block(block)
+                    .highlight_style(theme.selection)
+                    .highlight_symbol(">> ");
+                f.render_stateful_widget(tag_list, popup_area, &mut app.tag_state);
+            }
         }
         InputMode::Search => {}
         InputMode::Normal => {}
     }
 
+    let keys = |action| app.keymap.label(action);
     let status_text = if app.input_mode == InputMode::Search {
         format!("/{}", app.input_buffer.value())
+    } else if !app.pending_keys.is_empty() {
+        let typed: Vec<String> = app.pending_keys.iter().map(KeyChord::to_string).collect();
+        format!("{} …", typed.join(" "))
+    } else if let Some(tag) = app
+        .tag_filter()
+        .filter(|_| app.search_query.trim().is_empty())
+    {
+        format!(
+            "tag: {} | {}: change | {}: go to workspace | {}: back to workspace",
+            tags::label(tag),
+            keys(Action::FilterByTag),
+            keys(Action::OpenTaskWorkspace),
+            keys(Action::Back)
+        )
+    } else if app.view == TaskView::Agenda && app.search_query.trim().is_empty() {
+        format!(
+            "agenda | {}: toggle | {}: edit | {}: go to workspace | {}/{}: back to workspace",
+            keys(Action::ToggleCompletion),
+            keys(Action::Edit),
+            keys(Action::OpenTaskWorkspace),
+            keys(Action::ToggleAgenda),
+            keys(Action::Back)
+        )
     } else if app.search_query.trim().is_empty() {
-        "q: quit | ?: help".to_string()
+        format!(
+            "{}: quit | {}: help",
+            keys(Action::Quit),
+            keys(Action::Help)
+        )
     } else {
-        format!("search: {} | /: edit | esc: clear", app.search_query)
+        format!(
+            "search: {} | {}: edit | {}: clear",
+            app.search_query,
+            keys(Action::Search),
+            keys(Action::Back)
+        )
     };
-    let status_bar = Paragraph::new(status_text).style(
-        Style::default()
-            .fg(Color::White)
-            .add_modifier(Modifier::BOLD),
-    );
+    let status_bar = Paragraph::new(status_text).style(theme.status);
     f.render_widget(status_bar, main_chunks[1]);
     if app.input_mode == InputMode::Search {
         f.set_cursor_position((
@@ -1132,15 +2447,84 @@ Press ? or ESC to close"#;
     if let Some(notification) = &app.notification {
         let notification_area = top_right_rect(f.area(), 56, 5);
         f.render_widget(Clear, notification_area);
+        let (title, style) = match notification.kind {
+            NotificationKind::Info => ("info", theme.info),
+            NotificationKind::Error => ("error", theme.error),
+        };
         let notification = Paragraph::new(notification.message.as_str())
             .block(
                 Block::default()
-                    .title("error")
+                    .title(title)
                     .borders(Borders::ALL)
-                    .border_style(Style::default().fg(Color::Red)),
+                    .border_style(style),
             )
-            .style(Style::default().fg(Color::Red))
+            .style(style)
             .wrap(Wrap { trim: true });
         f.render_widget(notification, notification_area);
     }
 }
+
+fn help_section(title: &'static str, rows: &[(String, &'static str)]) -> Vec<Line<'static>> {
+    std::iter::once(Line::from(title))
+        .chain(
+            rows.iter()
+                .map(|(keys, description)| Line::from(format!("  {keys}: {description}"))),
+        )
+        .collect()
+}
+
+fn agenda_heading<'a>(group: agenda::Group, count: usize, theme: &Theme) -> ListItem<'a> {
+    let style = match group {
+        agenda::Group::Overdue => theme.overdue,
+        agenda::Group::Today => theme.due_soon,
+        _ => theme.accent,
+    };
+    ListItem::new(Line::from(vec![
+        Span::styled(group.label(), style.add_modifier(Modifier::BOLD)),
+        Span::styled(format!(" ({count})"), theme.muted),
+    ]))
+}
+
+fn count_descendants(tasks: &[Task], task_id: i64) -> usize {
+    tasks
+        .iter()
+        .filter(|t| t.parent_task_id == Some(task_id))
+        .map(|child| 1 + count_descendants(tasks, child.id))
+        .sum()
+}
+
+/// Tags on `tasks` with how many tasks carry each, by name.
+fn count_tags(tasks: &[Task]) -> Vec<(String, usize)> {
+    let mut counts = BTreeMap::new();
+    for tag in tasks.iter().flat_map(Task::tags) {
+        *counts.entry(tag).or_insert(0) += 1;
+    }
+    counts.into_iter().collect()
+}
+
+fn tasks_noun(count: usize) -> &'static str {
+    if count == 1 { "task" } else { "tasks" }
+}
+
+fn subtasks_noun(count: usize) -> &'static str {
+    if count == 1 { "subtask" } else { "subtasks" }
+}
+
+/// A marker in front of the title of prioritised tasks.
+fn priority_marker<'a>(priority: Priority, theme: &Theme) -> Option<Span<'a>> {
+    let (marker, style) = match priority {
+        Priority::None => return None,
+        Priority::Low => ("↓ ", theme.priority_low),
+        Priority::Medium => ("! ", theme.priority_medium),
+        Priority::High => ("!! ", theme.priority_high),
+        Priority::Urgent => ("!!! ", theme.priority_urgent),
+    };
+    Some(Span::styled(marker, style))
+}
+
+fn failure_item<'a>(failure: &DecryptionFailure, theme: &Theme) -> ListItem<'a> {
+    ListItem::new(Line::from(vec![
+        Span::styled(format!("[undecryptable #{}]", failure.id), theme.input),
+        Span::styled(" i: inspect", theme.muted),
+    ]))
+}